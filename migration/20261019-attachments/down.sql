@@ -0,0 +1,7 @@
+ALTER TABLE trash_files DROP COLUMN label;
+
+ALTER TABLE trash_files DROP COLUMN role;
+
+ALTER TABLE "files" DROP COLUMN label;
+
+ALTER TABLE "files" DROP COLUMN role;
@@ -0,0 +1,11 @@
+ALTER TABLE "files" ADD COLUMN role VARCHAR(50);
+
+ALTER TABLE "files" ADD COLUMN label VARCHAR(150);
+
+UPDATE "files" SET object_type='comment' WHERE object_type='commment';
+
+UPDATE "files" SET role=CASE object_type WHEN 'comment' THEN 'notes' ELSE 'main' END;
+
+ALTER TABLE trash_files ADD COLUMN role VARCHAR(50);
+
+ALTER TABLE trash_files ADD COLUMN label VARCHAR(150);
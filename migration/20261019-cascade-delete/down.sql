@@ -0,0 +1 @@
+DROP TRIGGER IF EXISTS lose_journal;
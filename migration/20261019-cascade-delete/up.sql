@@ -0,0 +1,94 @@
+DROP TRIGGER IF EXISTS lose_authorship;
+DROP TRIGGER IF EXISTS lose_keyword;
+
+CREATE TABLE new_extra_fields (
+    item_id VARCHAR(50) NOT NULL,
+    field VARCHAR(50) NOT NULL,
+    value VARCHAR(200) NOT NULL,
+    PRIMARY KEY (item_id, field),
+    FOREIGN KEY (item_id) REFERENCES items (citation)
+);
+
+INSERT INTO new_extra_fields SELECT item_id, field, value FROM extra_fields;
+DROP TABLE extra_fields;
+ALTER TABLE new_extra_fields RENAME TO extra_fields;
+
+CREATE TABLE new_files (
+    item_id VARCHAR(50) NOT NULL,
+    name VARCHAR(150) NOT NULL,
+    "note" VARCHAR(50),
+    object_type VARCHAR(50),
+    FOREIGN KEY(item_id) REFERENCES items (citation)
+);
+
+INSERT INTO new_files SELECT item_id, name, "note", object_type FROM "files";
+DROP TABLE "files";
+ALTER TABLE new_files RENAME TO "files";
+
+CREATE INDEX x_files_item_id ON "files" (item_id);
+
+CREATE TABLE new_item_keywords (
+    item_id VARCHAR(50),
+    keyword_id INTEGER,
+    PRIMARY KEY (item_id, keyword_id),
+    FOREIGN KEY(item_id) REFERENCES items (citation),
+    FOREIGN KEY(keyword_id) REFERENCES keywords (id)
+);
+
+INSERT INTO new_item_keywords SELECT item_id, keyword_id FROM item_keywords;
+DROP TABLE item_keywords;
+ALTER TABLE new_item_keywords RENAME TO item_keywords;
+
+CREATE TABLE new_item_persons (
+    item_id VARCHAR(50),
+    person_id INTEGER,
+    order_seq INTEGER,
+    is_editor BOOLEAN NOT NULL CHECK (is_editor IN (0, 1)) DEFAULT 0,
+    PRIMARY KEY (item_id, person_id),
+    UNIQUE (item_id, order_seq, is_editor),
+    FOREIGN KEY(item_id) REFERENCES items (citation),
+    FOREIGN KEY(person_id) REFERENCES persons (id)
+);
+
+INSERT INTO new_item_persons SELECT item_id, person_id, order_seq, is_editor FROM item_persons;
+DROP TABLE item_persons;
+ALTER TABLE new_item_persons RENAME TO item_persons;
+
+CREATE TRIGGER lose_authorship
+    AFTER DELETE ON item_persons WHEN (
+        NOT EXISTS (
+            SELECT *
+              FROM item_persons
+             WHERE person_id=OLD.person_id
+        )
+    )
+BEGIN
+    DELETE FROM persons
+     WHERE persons.id=OLD.person_id;
+END;
+
+CREATE TRIGGER lose_keyword
+    AFTER DELETE ON item_keywords WHEN (
+        NOT EXISTS (
+            SELECT *
+              FROM item_keywords
+             WHERE keyword_id=OLD.keyword_id
+        )
+    )
+BEGIN
+    DELETE FROM keywords
+     WHERE keywords.id=OLD.keyword_id;
+END;
+
+CREATE TRIGGER lose_journal
+    AFTER DELETE ON items WHEN (
+        OLD.journal_id IS NOT NULL AND NOT EXISTS (
+            SELECT *
+              FROM items
+             WHERE journal_id=OLD.journal_id
+        )
+    )
+BEGIN
+    DELETE FROM journals
+     WHERE journals.id=OLD.journal_id;
+END;
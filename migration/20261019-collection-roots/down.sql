@@ -0,0 +1 @@
+DROP INDEX IF EXISTS x_collections_root_name;
@@ -0,0 +1,10 @@
+UPDATE collections
+   SET name=name || ' (' || id || ')'
+ WHERE parent_id IS NULL AND id NOT IN (
+     SELECT min(id)
+       FROM collections
+      WHERE parent_id IS NULL
+      GROUP BY name
+ );
+
+CREATE UNIQUE INDEX x_collections_root_name ON collections (name) WHERE parent_id IS NULL;
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS trash_item_collections;
+DROP TABLE IF EXISTS item_collections;
+DROP TABLE IF EXISTS collections;
@@ -0,0 +1,23 @@
+CREATE TABLE collections (
+    id INTEGER PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    parent_id INTEGER,
+    UNIQUE (parent_id, name),
+    FOREIGN KEY(parent_id) REFERENCES collections (id) ON DELETE CASCADE
+);
+
+CREATE TABLE item_collections (
+    item_id VARCHAR(50) NOT NULL,
+    collection_id INTEGER NOT NULL,
+    PRIMARY KEY (item_id, collection_id),
+    FOREIGN KEY(item_id) REFERENCES items (citation),
+    FOREIGN KEY(collection_id) REFERENCES collections (id) ON DELETE CASCADE
+);
+
+CREATE INDEX x_item_collections_collection_id ON item_collections (collection_id);
+
+CREATE TABLE trash_item_collections (
+    trash_id INTEGER NOT NULL,
+    collection_id INTEGER NOT NULL,
+    FOREIGN KEY(trash_id) REFERENCES trash_items (trash_id) ON DELETE CASCADE
+);
@@ -0,0 +1,9 @@
+ALTER TABLE trash_files DROP COLUMN hash;
+
+ALTER TABLE trash_files DROP COLUMN size;
+
+DROP INDEX IF EXISTS x_files_hash;
+
+ALTER TABLE "files" DROP COLUMN hash;
+
+ALTER TABLE "files" DROP COLUMN size;
@@ -0,0 +1,9 @@
+ALTER TABLE "files" ADD COLUMN size INTEGER;
+
+ALTER TABLE "files" ADD COLUMN hash CHAR(64);
+
+CREATE INDEX x_files_hash ON "files" (hash);
+
+ALTER TABLE trash_files ADD COLUMN size INTEGER;
+
+ALTER TABLE trash_files ADD COLUMN hash CHAR(64);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS fulltext_files;
+DROP TABLE IF EXISTS fulltext_pages;
+DROP TABLE IF EXISTS fulltext_documents;
@@ -0,0 +1,18 @@
+CREATE TABLE fulltext_documents (
+    hash CHAR(64) PRIMARY KEY,
+    page_no INTEGER NOT NULL,
+    indexed_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
+CREATE VIRTUAL TABLE fulltext_pages USING fts5(body, hash UNINDEXED, page UNINDEXED);
+
+CREATE TABLE fulltext_files (
+    item_id VARCHAR(50) NOT NULL,
+    name VARCHAR(200) NOT NULL,
+    hash CHAR(64) NOT NULL,
+    PRIMARY KEY (item_id, name),
+    FOREIGN KEY(item_id) REFERENCES items (citation),
+    FOREIGN KEY(hash) REFERENCES fulltext_documents (hash)
+);
+
+CREATE INDEX x_fulltext_files_hash ON fulltext_files (hash);
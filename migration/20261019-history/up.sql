@@ -0,0 +1,12 @@
+CREATE TABLE item_history (
+    id INTEGER PRIMARY KEY,
+    item_id VARCHAR(50) NOT NULL,
+    operation VARCHAR(20) NOT NULL,
+    field VARCHAR(50),
+    old_value TEXT,
+    new_value TEXT,
+    changed_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    user VARCHAR(50)
+);
+
+CREATE INDEX x_item_history_item_id ON item_history (item_id);
@@ -0,0 +1,13 @@
+CREATE TABLE old_identifiers (
+    id_type VARCHAR(10) NOT NULL,
+    value VARCHAR(200) NOT NULL,
+    item_id VARCHAR(50) NOT NULL,
+    PRIMARY KEY (id_type, value),
+    FOREIGN KEY(item_id) REFERENCES items (citation)
+);
+
+INSERT OR IGNORE INTO old_identifiers SELECT id_type, value, item_id FROM identifiers;
+DROP TABLE identifiers;
+ALTER TABLE old_identifiers RENAME TO identifiers;
+
+CREATE INDEX x_identifiers_item_id ON identifiers (item_id);
@@ -0,0 +1,13 @@
+CREATE TABLE new_identifiers (
+    id_type VARCHAR(10) NOT NULL,
+    value VARCHAR(200) NOT NULL,
+    item_id VARCHAR(50) NOT NULL,
+    PRIMARY KEY (item_id, id_type, value),
+    FOREIGN KEY(item_id) REFERENCES items (citation)
+);
+
+INSERT INTO new_identifiers SELECT id_type, value, item_id FROM identifiers;
+DROP TABLE identifiers;
+ALTER TABLE new_identifiers RENAME TO identifiers;
+
+CREATE INDEX x_identifiers_value ON identifiers (id_type, value);
@@ -0,0 +1,19 @@
+CREATE TABLE identifiers (
+    id_type VARCHAR(10) NOT NULL,
+    value VARCHAR(200) NOT NULL,
+    item_id VARCHAR(50) NOT NULL,
+    PRIMARY KEY (id_type, value),
+    FOREIGN KEY(item_id) REFERENCES items (citation)
+);
+
+CREATE INDEX x_identifiers_item_id ON identifiers (item_id);
+
+INSERT OR IGNORE INTO identifiers (id_type, value, item_id)
+SELECT 'doi', lower(value), item_id
+  FROM extra_fields
+ WHERE field='doi' AND value LIKE '10.%/%';
+
+INSERT OR IGNORE INTO identifiers (id_type, value, item_id)
+SELECT 'pmid', value, item_id
+  FROM extra_fields
+ WHERE field='pmid' AND value NOT GLOB '*[^0-9]*' AND value != '';
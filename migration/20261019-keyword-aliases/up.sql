@@ -0,0 +1,5 @@
+CREATE TABLE keyword_aliases (
+    alias VARCHAR(50) PRIMARY KEY,
+    keyword_id INTEGER NOT NULL,
+    FOREIGN KEY(keyword_id) REFERENCES keywords (id) ON DELETE CASCADE
+);
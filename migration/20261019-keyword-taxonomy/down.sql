@@ -0,0 +1,32 @@
+DROP TRIGGER IF EXISTS lose_keyword;
+
+PRAGMA foreign_keys=OFF;
+
+CREATE TABLE keywords_new (
+    id INTEGER PRIMARY KEY,
+    text VARCHAR(50),
+    UNIQUE (text)
+);
+
+INSERT INTO keywords_new (id, text) SELECT id, text FROM keywords;
+
+DROP TABLE keywords;
+
+ALTER TABLE keywords_new RENAME TO keywords;
+
+CREATE UNIQUE INDEX x_keywords_text ON keywords (text);
+
+PRAGMA foreign_keys=ON;
+
+CREATE TRIGGER lose_keyword
+    AFTER DELETE ON item_keywords WHEN (
+        NOT EXISTS (
+            SELECT *
+              FROM item_keywords
+             WHERE keyword_id=OLD.keyword_id
+        )
+    )
+BEGIN
+    DELETE FROM keywords
+     WHERE keywords.id=OLD.keyword_id;
+END;
@@ -0,0 +1,27 @@
+ALTER TABLE keywords ADD COLUMN parent_id INTEGER REFERENCES keywords (id) ON DELETE SET NULL;
+
+CREATE INDEX x_keywords_parent ON keywords (parent_id);
+
+DROP TRIGGER IF EXISTS lose_keyword;
+
+CREATE TRIGGER lose_keyword
+    AFTER DELETE ON item_keywords WHEN (
+        NOT EXISTS (
+            SELECT *
+              FROM item_keywords
+             WHERE keyword_id=OLD.keyword_id
+        ) AND NOT EXISTS (
+            SELECT *
+              FROM keywords
+             WHERE id=OLD.keyword_id AND parent_id IS NOT NULL
+                OR parent_id=OLD.keyword_id
+        ) AND NOT EXISTS (
+            SELECT *
+              FROM keyword_aliases
+             WHERE keyword_id=OLD.keyword_id
+        )
+    )
+BEGIN
+    DELETE FROM keywords
+     WHERE keywords.id=OLD.keyword_id;
+END;
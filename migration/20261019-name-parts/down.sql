@@ -0,0 +1,40 @@
+PRAGMA foreign_keys=OFF;
+
+DROP TRIGGER IF EXISTS lose_authorship;
+
+CREATE TABLE persons_new (
+    id INTEGER NOT NULL,
+    last_name VARCHAR(50) NOT NULL,
+    first_name VARCHAR(50),
+    search_term VARCHAR(50) NOT NULL,
+    PRIMARY KEY (id),
+    UNIQUE (last_name, first_name)
+);
+
+INSERT OR IGNORE INTO persons_new (id, last_name, first_name, search_term)
+SELECT id, last_name, first_name, search_term FROM persons;
+
+DROP TABLE persons;
+
+ALTER TABLE persons_new RENAME TO persons;
+
+CREATE INDEX x_persons_search_term ON persons (search_term);
+
+CREATE TRIGGER lose_authorship
+    AFTER DELETE ON item_persons WHEN (
+        NOT EXISTS (
+            SELECT *
+              FROM item_persons
+             WHERE person_id=OLD.person_id
+        )
+    )
+BEGIN
+    DELETE FROM persons
+     WHERE persons.id=OLD.person_id;
+END;
+
+PRAGMA foreign_keys=ON;
+
+ALTER TABLE trash_item_persons DROP COLUMN prefix;
+
+ALTER TABLE trash_item_persons DROP COLUMN suffix;
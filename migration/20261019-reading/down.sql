@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS trash_reading;
+DROP INDEX IF EXISTS x_reading_status;
+DROP TABLE IF EXISTS reading;
@@ -0,0 +1,23 @@
+CREATE TABLE reading (
+    item_id VARCHAR(50) PRIMARY KEY,
+    status VARCHAR(10) NOT NULL CHECK (status IN ('unread', 'reading', 'read', 'skimmed')) DEFAULT 'unread',
+    priority INTEGER,
+    rating INTEGER CHECK (rating BETWEEN 1 AND 5),
+    marked_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    started_at INTEGER,
+    finished_at INTEGER,
+    FOREIGN KEY(item_id) REFERENCES items (citation)
+);
+
+CREATE INDEX x_reading_status ON reading (status);
+
+CREATE TABLE trash_reading (
+    trash_id INTEGER PRIMARY KEY,
+    status VARCHAR(10) NOT NULL,
+    priority INTEGER,
+    rating INTEGER,
+    marked_at INTEGER NOT NULL,
+    started_at INTEGER,
+    finished_at INTEGER,
+    FOREIGN KEY(trash_id) REFERENCES trash_items (trash_id) ON DELETE CASCADE
+);
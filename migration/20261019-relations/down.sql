@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS trash_item_relations;
+DROP INDEX IF EXISTS x_item_relations_other_id;
+DROP TABLE IF EXISTS item_relations;
@@ -0,0 +1,18 @@
+CREATE TABLE item_relations (
+    item_id VARCHAR(50) NOT NULL,
+    relation VARCHAR(20) NOT NULL CHECK (relation IN ('cites', 'erratum of', 'preprint of', 'commentary on')),
+    other_id VARCHAR(50) NOT NULL,
+    PRIMARY KEY (item_id, relation, other_id),
+    FOREIGN KEY(item_id) REFERENCES items (citation),
+    FOREIGN KEY(other_id) REFERENCES items (citation)
+);
+
+CREATE INDEX x_item_relations_other_id ON item_relations (other_id);
+
+CREATE TABLE trash_item_relations (
+    trash_id INTEGER NOT NULL,
+    relation VARCHAR(20) NOT NULL,
+    other_id VARCHAR(50) NOT NULL,
+    incoming BOOLEAN NOT NULL CHECK (incoming IN (0, 1)) DEFAULT 0,
+    FOREIGN KEY(trash_id) REFERENCES trash_items (trash_id) ON DELETE CASCADE
+);
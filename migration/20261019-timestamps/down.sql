@@ -0,0 +1,11 @@
+ALTER TABLE trash_items DROP COLUMN modified_at;
+
+ALTER TABLE trash_items DROP COLUMN added_at;
+
+DROP INDEX IF EXISTS x_items_modified_at;
+
+DROP INDEX IF EXISTS x_items_added_at;
+
+ALTER TABLE items DROP COLUMN modified_at;
+
+ALTER TABLE items DROP COLUMN added_at;
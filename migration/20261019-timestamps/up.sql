@@ -0,0 +1,15 @@
+ALTER TABLE items ADD COLUMN added_at INTEGER;
+
+ALTER TABLE items ADD COLUMN modified_at INTEGER;
+
+UPDATE items
+   SET added_at=(SELECT min(changed_at) FROM item_history WHERE item_id=citation),
+       modified_at=(SELECT max(changed_at) FROM item_history WHERE item_id=citation);
+
+CREATE INDEX x_items_added_at ON items (added_at);
+
+CREATE INDEX x_items_modified_at ON items (modified_at);
+
+ALTER TABLE trash_items ADD COLUMN added_at INTEGER;
+
+ALTER TABLE trash_items ADD COLUMN modified_at INTEGER;
@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS operations;
+DROP TABLE IF EXISTS trash_files;
+DROP TABLE IF EXISTS trash_extra_fields;
+DROP TABLE IF EXISTS trash_item_keywords;
+DROP TABLE IF EXISTS trash_item_persons;
+DROP TABLE IF EXISTS trash_items;
@@ -0,0 +1,60 @@
+CREATE TABLE trash_items (
+    trash_id INTEGER PRIMARY KEY,
+    reason VARCHAR(10) NOT NULL CHECK (reason IN ('delete', 'update')) DEFAULT 'delete',
+    deleted_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    citation VARCHAR(50) NOT NULL,
+    entry_type VARCHAR(15) NOT NULL,
+    title VARCHAR(150) NOT NULL,
+    booktitle VARCHAR(200),
+    year INTEGER NOT NULL,
+    month INTEGER,
+    chapter INTEGER,
+    edition INTEGER,
+    volume INTEGER,
+    "number" INTEGER,
+    pages VARCHAR(50),
+    journal_name VARCHAR,
+    journal_abbr VARCHAR,
+    journal_abbr_no_dot VARCHAR
+);
+
+CREATE INDEX x_trash_items_citation ON trash_items (citation);
+
+CREATE TABLE trash_item_persons (
+    trash_id INTEGER NOT NULL,
+    last_name VARCHAR(50) NOT NULL,
+    first_name VARCHAR(50),
+    search_term VARCHAR(50) NOT NULL,
+    order_seq INTEGER NOT NULL,
+    is_editor BOOLEAN NOT NULL CHECK (is_editor IN (0, 1)) DEFAULT 0,
+    FOREIGN KEY(trash_id) REFERENCES trash_items (trash_id) ON DELETE CASCADE
+);
+
+CREATE TABLE trash_item_keywords (
+    trash_id INTEGER NOT NULL,
+    text VARCHAR(50) NOT NULL,
+    FOREIGN KEY(trash_id) REFERENCES trash_items (trash_id) ON DELETE CASCADE
+);
+
+CREATE TABLE trash_extra_fields (
+    trash_id INTEGER NOT NULL,
+    field VARCHAR(50) NOT NULL,
+    value VARCHAR(200) NOT NULL,
+    FOREIGN KEY(trash_id) REFERENCES trash_items (trash_id) ON DELETE CASCADE
+);
+
+CREATE TABLE trash_files (
+    trash_id INTEGER NOT NULL,
+    name VARCHAR(150) NOT NULL,
+    object_type VARCHAR(50),
+    stored_name VARCHAR(200),
+    FOREIGN KEY(trash_id) REFERENCES trash_items (trash_id) ON DELETE CASCADE
+);
+
+CREATE TABLE operations (
+    id INTEGER PRIMARY KEY,
+    item_id VARCHAR(50) NOT NULL,
+    operation VARCHAR(20) NOT NULL,
+    payload TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
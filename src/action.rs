@@ -2,23 +2,140 @@ use core::panic;
 use std::path::PathBuf;
 use std::fs;
 
-use crate::formatter::{ToString, LabeledPrint, bibtex::BibPrint};
-use crate::database::{SqliteBibDB, BibDataBase};
+use inquire::Select;
+use crate::config::CONFIG;
+use crate::formatter::{ToString, LabeledPrint};
+use crate::database::{SqliteBibDB, BibDataBase, SearchFilter, operation::Operation, journal::Journal};
 use crate::reader::pandoc::read_pandoc;
 use crate::file::{File, BibFile};
+use crate::identifier::Identifier;
+use crate::model::{Entry, ReadingStatus};
+use crate::util::{now, parse_age};
 
-mod keywords;
+pub mod keywords;
 mod add_item;
+pub mod trash;
+mod undo;
+mod history;
+pub mod collection;
+mod lint;
+mod enrich;
+mod add_pdf;
+pub mod fulltext;
+mod notes;
+mod attach;
+mod fsck;
+pub mod files;
+mod pack;
+mod sync;
+mod mark;
+pub mod library;
+pub mod relation;
+mod stats;
 pub use add_item::add_item;
 pub use self::keywords::keywords;
+pub use self::undo::undo;
+pub use self::history::log;
+pub use self::lint::lint;
+pub use self::enrich::enrich_from_dump;
+pub use self::add_pdf::add_pdf;
+pub use self::notes::notes;
+pub use self::attach::attach;
+pub use self::fsck::fsck;
+pub use self::pack::{pack, unpack};
+pub use self::sync::sync;
+pub use self::mark::mark;
+pub use self::stats::stats;
 
-pub fn search(conn: &SqliteBibDB, mut author: Vec<String>, mut keywords: Vec<String>) -> String {
+/// id of the journal of the entry, a journal new to the library is added under its name
+fn journal_or_add(conn: &SqliteBibDB, entry: &Entry) -> Option<i32> {
+    let name = entry.journal.as_ref()?;
+    Some(match conn.journal_id(name).expect("Failed to read journals!") {
+        Some(id) => id,
+        None => conn.add_journal(Journal{id: None, name: name.clone(), abbr: name.clone(),
+                                         abbr_no_dot: name.replace('.', "")}).expect("Failed to add journal!"),
+    })
+}
+
+/// Save a new version of an existing entry, the old version is kept so that the update can be
+/// undone
+fn save_update(conn: &SqliteBibDB, entry: &Entry) {
+    let journal_id = journal_or_add(conn, entry);
+    let trash_id = conn.snapshot_item(&entry.citation)
+        .unwrap_or_else(|_| panic!("Failed to keep the old version of {}!", entry.citation));
+    conn.update_item(entry, journal_id).unwrap_or_else(|_| panic!("Failed to update entry {}!", entry.citation));
+    conn.record_operation(&Operation::Update{citation: entry.citation.clone(), trash_id})
+        .expect("Failed to update operation journal!");
+}
+
+/// search conditions from the command line, the collection is looked up by path
+fn build_filter(conn: &SqliteBibDB, authors: &[String], keywords: &[String], collection: Option<&str>,
+                expand: bool) -> Result<SearchFilter, String> {
+    let collection_id = match collection {
+        Some(path) => match conn.find_collection(path).expect("Search Fail!") {
+            Some(id) => Some(id),
+            None => return Err(format!("Cannot find collection {}", path)),
+        },
+        None => None,
+    };
+    Ok(SearchFilter{authors: authors.to_vec(), keywords: keywords.to_vec(), expand, collection: collection_id,
+                    ..Default::default()})
+}
+
+/// Conditions of a search beside authors, keywords and collection, as given on the command line
+#[derive(Default, Debug)]
+pub struct Conditions<'a> {
+    pub status: Option<&'a str>,
+    pub min_rating: Option<i32>,
+    /// local dates like 2026-10-19 or ages like 7d, bounding the date added, or the date last
+    /// modified with modified
+    pub since: Option<&'a str>,
+    pub until: Option<&'a str>,
+    pub modified: bool,
+}
+
+impl Conditions<'_> {
+    fn is_empty(&self) -> bool {
+        self.status.is_none() && self.min_rating.is_none() && self.since.is_none() && self.until.is_none()
+    }
+}
+
+/// unix timestamp of a local date like 2026-10-19, or of an age like 7d before now
+fn parse_time(conn: &SqliteBibDB, input: &str) -> Result<i64, String> {
+    if let Some(timestamp) = conn.parse_date(input).expect("Failed to read date!") { return Ok(timestamp) }
+    parse_age(input).map(|x| now() - x)
+        .ok_or_else(|| format!("Cannot understand date {}, use something like 2026-10-19 or 7d", input))
+}
+
+/// Entries by author last names, keywords, collection, reading status, lowest rating and dates
+pub fn search(conn: &SqliteBibDB, mut author: Vec<String>, mut keywords: Vec<String>, collection: Option<&str>,
+              expand: bool, conditions: &Conditions) -> String {
     author.retain(|x| !x.is_empty());
     keywords.retain(|x| !x.is_empty());
-    if author.is_empty() && keywords.is_empty() {
-        return "Search by author last names either/or keywords!".to_string();
+    if author.is_empty() && keywords.is_empty() && collection.is_none() && conditions.is_empty() {
+        return "Search by author last names, keywords, collection, reading status and/or dates!".to_string();
+    }
+    let mut filter = match build_filter(conn, &author, &keywords, collection, expand) {
+        Ok(x) => x,
+        Err(x) => return x,
+    };
+    if let Some(status) = conditions.status {
+        match ReadingStatus::parse(status) {
+            Some(x) => filter.status = Some(x.as_str().to_owned()),
+            None => return format!("Unknown reading status {}, use unread, reading, read or skimmed", status),
+        }
     }
-    let results = conn.search(&author, &keywords).expect("Search Fail!");    
+    filter.min_rating = conditions.min_rating;
+    for (input, bound) in [(conditions.since, &mut filter.since), (conditions.until, &mut filter.until)] {
+        if let Some(input) = input {
+            match parse_time(conn, input) {
+                Ok(x) => *bound = Some(x),
+                Err(x) => return x,
+            }
+        }
+    }
+    filter.modified = conditions.modified;
+    let results = conn.search_by(&filter).expect("Search Fail!");
     if results.is_empty() {
         format!("Entries not found for authors [{}] and keywords [{}]", author.join(", "), keywords.join(", "))
     } else {
@@ -26,43 +143,110 @@ pub fn search(conn: &SqliteBibDB, mut author: Vec<String>, mut keywords: Vec<Str
     }
 }
 
+/// Entries added in the last days, or modified with modified, newest first with the date
+pub fn recent(conn: &SqliteBibDB, days: i64, modified: bool) -> String {
+    let filter = SearchFilter{since: Some(now() - days * 86400), modified, ..Default::default()};
+    let results = conn.search_by(&filter).expect("Search Fail!");
+    if results.is_empty() {
+        return format!("No entries {} in the last {} days", if modified { "modified" } else { "added" }, days)
+    }
+    results.iter().map(|x| {
+        let timestamp = if modified { x.modified_at } else { x.added_at };
+        let date = conn.local_date(timestamp.unwrap_or_default()).expect("Failed to read date!");
+        format!("{}  {}", date, x.labeled_to_str(&[]))
+    }).collect::<Vec<String>>().join("\n")
+}
+
+/// find the entry by an identifier like doi:10.1002/andp.19053221004, pmid:, arxiv: or isbn:
+pub fn get(conn: &SqliteBibDB, query: &str) -> String {
+    let identifier = match Identifier::parse(query) {
+        Some(x) => x,
+        None => return format!("Cannot read identifier {}, use doi:, pmid:, arxiv: or isbn:", query),
+    };
+    match conn.find_identifier(&identifier).expect("Search Fail!") {
+        Some(citation) => {
+            let entry = conn.get_item(&citation).unwrap_or_else(|_| panic!("Cannot find entry {}", citation));
+            format!("{}: {}", citation, entry.to_str())
+        },
+        None => format!("No entry with {}", identifier),
+    }
+}
+
+/// Open the main pdf and/or the comment file. Without either flag the attached files are listed
+/// to choose the one to open.
 pub fn open(conn: &SqliteBibDB, id: &str, comment: bool, pdf: bool) {
     let result = conn.get_item(id).unwrap_or_else(|_| panic!("Cannot find entry with id {}", &id));
-    let files = conn.get_files(&result.citation).expect("Find file record in db fail!");
-    let mut has_comment = false;
-    for (file_name, file_type) in files.iter() {
-        if pdf && (file_type == "pdf") {
-            let pdf_file = File::new(file_name, file_type);
-            pdf_file.open().unwrap();
-        }
-        if comment && (file_type == "comment") {
-            has_comment = true;
-            let comment_file = File::new(file_name, file_type);
-            comment_file.open().unwrap();
+    let files = conn.get_attachments(&result.citation).expect("Find file record in db fail!");
+    if pdf {
+        match files.iter().find(|x| x.object_type == "pdf") {
+            Some(x) => File::new(&x.name, &x.object_type).open().unwrap(),
+            None => println!("No pdf attached to {}", result.citation),
         }
     }
-    if comment && !has_comment {
-        let comment_file = File::new(&result.citation, "comment");
-        fs::write(comment_file.path(), result.to_comment()).unwrap();
-        conn.add_file(&result.citation, &result.citation, "commment").unwrap();
-        comment_file.open().unwrap();
+    if comment {
+        comment_file(conn, &result).open().unwrap();
+    }
+    if !pdf && !comment {
+        let chosen = match files.len() {
+            0 => { println!("No files attached to {}, add one with bibrs attach", result.citation); return },
+            1 => &files[0],
+            _ => {
+                let options: Vec<String> = files.iter().map(|x| x.to_str()).collect();
+                match Select::new("Open:", options.clone()).prompt() {
+                    Ok(x) => &files[options.iter().position(|y| *y == x).unwrap()],
+                    Err(_) => return,
+                }
+            },
+        };
+        File::new(&chosen.name, &chosen.object_type).open().unwrap();
     }
 }
 
-pub fn delete(conn: &SqliteBibDB, id: &str) {
+/// The comment file of the entry, a new one is started with the title, authors and year
+fn comment_file(conn: &SqliteBibDB, entry: &Entry) -> File<'static> {
+    let files = conn.get_files(&entry.citation).expect("Find file record in db fail!");
+    if let Some((file_name, _)) = files.iter().find(|x| x.1 == "comment") {
+        return File::new(file_name, "comment")
+    }
+    let name = attach::storage_name(conn, &entry.citation, "notes", None, "comment", None);
+    let comment_file = File::new(&name, "comment");
+    if let Some(folder) = comment_file.path().parent() { fs::create_dir_all(folder).unwrap(); }
+    fs::write(comment_file.path(), entry.to_comment()).unwrap();
+    conn.add_file(&entry.citation, &name, "comment").unwrap();
+    comment_file
+}
+
+/// move the entry with all its relations to the trash, together with its pdf and comment files
+/// unless keep_files
+pub fn delete(conn: &SqliteBibDB, id: &str, keep_files: bool) {
     if let Ok(_) = conn.get_item(id) {
         let files = conn.get_files(id).unwrap();
-        conn.delete(id).unwrap_or_else(|_| panic!("Failed to delete existing entry {}!", id));
-        for (ref file_name, ref file_type) in files {
-            File::new(file_name, file_type).remove()
-                .unwrap_or_else(|_| panic!("Failed to remove file with name = '{}' and type = '{}'",
-                        file_name, file_type));
+        let trash_id = conn.trash_item(id).unwrap_or_else(|_| panic!("Failed to delete existing entry {}!", id));
+        if !keep_files {
+            // files in the content-addressed store can be shared with other entries
+            for (ref file_name, ref file_type) in files {
+                if conn.file_name_taken(file_name, file_type).expect("Find file record in db fail!") { continue }
+                trash::trash_file(conn, trash_id, file_name, file_type);
+            }
         }
+        conn.record_operation(&Operation::Delete{citation: id.to_owned(), trash_id})
+            .expect("Failed to update operation journal!");
+        println!("Moved {} to trash as {}", id, trash_id);
     } else {
         println!("Cannot find entry with citation = {}", id);
     }
 }
 
+/// change the citation of an entry, the attached files keep their names
+pub fn rename(conn: &SqliteBibDB, from: &str, to: &str) -> String {
+    if conn.get_item(from).is_err() { return format!("Cannot find entry with citation = {}", from) }
+    if conn.get_item(to).is_ok() { return format!("Citation {} is already in use", to) }
+    conn.rename(from, to).unwrap_or_else(|_| panic!("Failed to rename {} to {}", from, to));
+    conn.record_operation(&Operation::Rename{from: from.to_owned(), to: to.to_owned()})
+        .expect("Failed to update operation journal!");
+    format!("Renamed {} to {}", from, to)
+}
+
 pub fn output_str(conn: &SqliteBibDB, source: &str) -> String {
     if PathBuf::from(source).exists() {
         read_pandoc(&source.into())
@@ -81,11 +265,11 @@ pub fn output_bib(conn: &SqliteBibDB, source: &str) -> String {
         read_pandoc(&source.into())
             .unwrap_or_else(|_| panic!("Failed to read file for citation: {}", source))
             .iter().map(move |x| match conn.get_item(x) {
-                Ok(e) => e.to_bib(),
+                Ok(e) => e.bib_str(CONFIG.bibtex_utf8),
                 Err(_) => format!("Entry not found for {}!", x),
             }).collect::<Vec<String>>().join("\n")
     } else {
-        conn.get_item(source).unwrap_or_else(|_| panic!("Cannot find entry {}", source)).to_bib()
+        conn.get_item(source).unwrap_or_else(|_| panic!("Cannot find entry {}", source)).bib_str(CONFIG.bibtex_utf8)
     }
 }
 
@@ -96,12 +280,13 @@ mod tests {
     #[test]
     fn test_search() {
         let conn = SqliteBibDB::new(Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/library.sqlite")));
-        let res = search(&conn, vec!["sur".to_string()], vec!["review".to_string()]);
+        let none = Conditions::default();
+        let res = search(&conn, vec!["sur".to_string()], vec!["review".to_string()], None, false, &none);
         assert_eq!(res.split('\n').next(), Some("\u{1b}[38;5;1mMriganka\u{1b}[38;5;4m Sur\u{1b}[39m & John L.R. \
                 Rubenstein. (2005) Patterning And Plasticity Of The Cerebral Cortex. Science"));
-        let res = search(&conn, vec!["sur".to_string()], Vec::<String>::new());
+        let res = search(&conn, vec!["sur".to_string()], Vec::<String>::new(), None, false, &none);
         assert_eq!(res.matches('\n').count(), 12);
-        let res = search(&conn, Vec::<String>::new(), vec!["review".to_string()]);
+        let res = search(&conn, Vec::<String>::new(), vec!["review".to_string()], None, false, &none);
         assert_eq!(res.matches('\n').count(), 76);
     }
 
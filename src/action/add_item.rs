@@ -1,94 +1,172 @@
-use std::error::Error;
-
-use inquire::{Confirm, Text, Select};
-use rusqlite::Error::QueryReturnedNoRows;
+use inquire::{Confirm, Select, Text, InquireError};
 use crate::reader::bibtex::read_entries;
 use crate::file::{File, BibFile};
-use crate::database::{BibDataBase, journal::{Journal, JournalDB}};
+use crate::database::{SqliteBibDB, BibDataBase};
 use crate::formatter::ToString;
+use crate::model::{Entry, Person};
 use crate::config::CONFIG;
+use super::{journal_or_add, save_update};
+use super::attach::store;
 
-pub fn add_item(conn: BibDataBase, journal_conn: JournalDB, keywords: Vec<String>) -> Result<(), Error> {
-    let bib_file = File::temp("temp_bib").unwrap_or_else(
-        |_| panic!("Cannot find bibtex file in {:?}", CONFIG.temp_bib.folder));
-    let pdf_file: Option<File> = File::temp("temp_pdf").ok();
-    let pdf = File::temp("temp_pdf").ok();
-    let mut entries = read_entries(bib_file.path());
-    let mut entry = entries.pop().expect("empty bibtext file in download folder, or error in the bibtex file");
-    entry.citation = entry.generate_citation();
-    entry.keywords.extend(keywords);
-    println!("New Item: \n{}", entry.to_str());
+/// An entry already in the library that the new one would update
+#[derive(Debug)]
+enum Existing {
+    /// the same paper, sharing an identifier like the DOI
+    Duplicate(Entry),
+    /// another entry under the same citation
+    Citation(Entry),
+}
 
-    // Ask citation
-    'citation_check: loop {
-        match conn.get_item(&entry.citation) {
-            Ok(existing_entry) => {
-                println!("Conflicting citation: \n{}", existing_entry.to_str());
-                match Text("Input suffix, input nothing to update the existing entry").prompt()? {
-                    "" => {
-                        entry.update(&existing_entry);
-                        break 'citation_check;
-                    },
-                    new_citation => {
-                        entry.citation = entry.citation.push_str(new_citation);
-                    }
+fn find_existing(conn: &SqliteBibDB, entry: &Entry) -> Option<Existing> {
+    if let Some(citation) = conn.find_duplicate(entry).expect("Search Fail!") {
+        return Some(Existing::Duplicate(conn.get_item(&citation)
+            .unwrap_or_else(|_| panic!("Cannot find entry {}", citation))));
+    }
+    conn.get_item(&entry.citation).ok().map(Existing::Citation)
+}
+
+/// Pick the citation of the new entry. The same paper found by identifier is updated if confirmed,
+/// on a taken citation a suffix makes a new entry and an empty one updates the existing entry.
+/// Returns:
+///     the existing entry to update, None for a new entry
+fn ask_citation(conn: &SqliteBibDB, entry: &mut Entry) -> Result<Option<Entry>, InquireError> {
+    loop {
+        match find_existing(conn, entry) {
+            Some(Existing::Duplicate(existing)) => {
+                println!("Same paper as {}: \n{}", existing.citation, existing.to_str());
+                if !Confirm::new("Update it?").with_default(true).prompt()? {
+                    return Err(InquireError::OperationCanceled)
                 }
+                entry.citation = existing.citation.clone();
+                return Ok(Some(existing))
+            },
+            Some(Existing::Citation(existing)) => {
+                println!("Conflicting citation: \n{}", existing.to_str());
+                let suffix = Text::new("Input suffix, input nothing to update the existing entry").prompt()?;
+                if suffix.trim().is_empty() { return Ok(Some(existing)) }
+                entry.citation.push_str(suffix.trim());
             },
-            Err(_) => break 'citation_check
+            None => return Ok(None),
         }
     }
+}
 
-    // Ask Journal
-    if let Some(journal_name) = entry.journal {
-        entry.journal = 'journal_check: loop {
-            match conn.search_journal(&journal_name) {
-                Ok(insert) => break 'journal_check Some(insert),
-                Err(QueryReturnedNoRows) => {
-                    match journal_conn.search(&journal_name) {
-                        Ok(new_journal) => {
-                            conn.add_journal(new_journal);
-                            break 'journal_check Some(new_journal.name)
-                        },
-                        Err(QueryReturnedNoRows) => {
-                            match Text("Journal not found, please add a new entry:\n\tSeparated by commas: \
-                                full name, abbreviation, abbreviation without dots").prompt()? {
-                                Ok(result) => {
-                                    let new_journal = Journal::from_list(result.split(",")
-                                        .map(|x| x.trim()).collect::<Vec<String>>());
-                                    entry.journal = new_journal.name.clone();
-                                    conn.add_journal(new_journal);
-                                }
-                            }
-                        },
-                        Err(err) => return Err(err)
-                    }
-                },
-                Err(err) => return Err(err)
-            }
-        }
+/// Authors and editors whose last name is in the library only under other first names, like
+/// "Smith, J." next to "Smith, John", each with the people of that last name
+fn people_conflicts(conn: &SqliteBibDB, people: &[Person]) -> Vec<(usize, Vec<Person>)> {
+    people.iter().enumerate().filter_map(|(idx, person)| {
+        let existing = conn.search_lastname(&person.search_term).expect("Search Fail!");
+        if existing.is_empty() || existing.iter().any(|x| x.first_name == person.first_name) { None }
+        else { Some((idx, existing)) }
+    }).collect()
+}
+
+/// Ask whether each of the people new to the library is one already there under the same last name
+fn ask_people(conn: &SqliteBibDB, people: &mut Vec<Person>) -> Result<(), InquireError> {
+    for (idx, existing) in people_conflicts(conn, people) {
+        let mut options: Vec<String> = existing.iter().map(|x| x.to_str()).collect();
+        options.push(format!("{} (new)", people[idx].to_str()));
+        let message = format!("Is {} one of the people in the library?", people[idx].to_str());
+        let choice = Select::new(&message, options).raw_prompt()?;
+        if let Some(person) = existing.get(choice.index) { people[idx] = person.clone(); }
     }
+    Ok(())
+}
 
-    // Ask Author
-    for person in entry.authors.iter() {
-        match conn.search_lastname(&person.last_name) {
-            Ok(authors) => {
-                Select(authors.map(|x| x.first_names)).prompt()
-            },
-            Err(_) => {}
-        }
+/// Save the new entry, or update the existing one with it. An update fills in what the new entry
+/// lacks from the old version and only adds keywords.
+fn save(conn: &SqliteBibDB, mut entry: Entry, existing: Option<&Entry>) -> Entry {
+    match existing {
+        Some(existing) => {
+            entry.update(existing);
+            entry.keywords.extend(existing.keywords.iter().cloned());
+            save_update(conn, &entry);
+        },
+        None => {
+            let journal_id = journal_or_add(conn, &entry);
+            conn.add_item(&entry, journal_id).unwrap_or_else(|_| panic!("Failed to add entry {}!", entry.citation));
+        },
     }
-    'person_check: loop {
-        match conn.search_lastname(&entry.authors).check_people() {
-            Ok(insert) => break 'person_check insert,
-            Err(PersonError::Person(old_insert, _)) => {
-                insert = old_insert;
-            },
-            Err(_) => panic!("Database Error in author query!")
-        }
+    entry
+}
+
+/// Add the entry in the bibtex file of the download folder, or update the entry of the same paper,
+/// and store the downloaded pdf as its main pdf
+pub fn add_item(conn: &SqliteBibDB, keywords: Vec<String>) -> String {
+    let bib_file = match File::temp("temp_bib") {
+        Ok(x) => x,
+        Err(_) => return format!("Cannot find bibtex file in {}", CONFIG.temp_bib.folder.to_string_lossy()),
     };
-    match Confim::new("(c)ontinue or (a)bort?").prompt() {
-        Ok(true) => { insert.insert().unwrap() },
-        Ok(false) | Err(_) => { println!("Aborted.") }
+    let mut entry = match read_entries(bib_file.path()).pop() {
+        Some(x) => x,
+        None => return format!("No entry in {}", bib_file.path().to_string_lossy()),
     };
-    return Ok(())
+    entry.citation = entry.generate_citation();
+    entry.keywords.extend(keywords);
+    if let Some(journal) = entry.journal.take() {
+        entry.journal = Some(conn.search_journal(&journal).unwrap_or(journal));
+    }
+    println!("New Item: \n{}", entry.to_str());
+    let existing = match ask_citation(conn, &mut entry) {
+        Ok(x) => x,
+        Err(_) => return "Aborted.".to_owned(),
+    };
+    if ask_people(conn, &mut entry.authors).and_then(|_| ask_people(conn, &mut entry.editors)).is_err() {
+        return "Aborted.".to_owned()
+    }
+    let verb = if existing.is_some() { "Updated" } else { "Added" };
+    let entry = save(conn, entry, existing.as_ref());
+    // the downloaded pdf is stored under the naming template of the pdf folder
+    if let Ok(pdf) = File::temp("temp_pdf") {
+        let path = pdf.path().to_owned();
+        match store(conn, &entry.citation, &path, "main", None) {
+            Ok(_) => if let Err(err) = pdf.remove() { println!("Cannot remove {}: {}", path.to_string_lossy(), err) },
+            Err(err) => println!("{}", err),
+        }
+    }
+    format!("{} {}: {}", verb, entry.citation, entry.to_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::*;
+
+    #[test]
+    fn test_existing() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        for entry in entries.iter() { conn.add_item(entry, None).unwrap(); }
+        let mut entry = entries[0].clone();
+        entry.citation = "einstein1905".to_owned();
+        entry.keywords = vec!["relativity".to_owned()].into_iter().collect();
+        let existing = match find_existing(&conn, &entry) {
+            Some(Existing::Duplicate(x)) => x,
+            x => panic!("expected a duplicate of einstein, found {:?}", x),
+        };
+        assert_eq!(existing.citation, "einstein");
+        entry.citation = existing.citation.clone();
+        let saved = save(&conn, entry, Some(&existing));
+        assert!(saved.keywords.contains("relativity"));
+        assert!(conn.get_item("einstein").unwrap().keywords.contains("relativity"));
+        assert!(conn.get_item("einstein1905").is_err());
+        let mut other = entries[2].clone();
+        assert!(matches!(find_existing(&conn, &other), Some(Existing::Citation(_))));
+        other.citation = "knuthwebsite2".to_owned();
+        assert!(find_existing(&conn, &other).is_none());
+    }
+
+    #[test]
+    fn test_people_conflicts() {
+        let conn = SqliteBibDB::in_memory();
+        for entry in read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib")).iter() {
+            conn.add_item(entry, None).unwrap();
+        }
+        let people = vec![Person::load("Goossens, M."), Person::load("Goossens, Michel"),
+                          Person::load("Lamport, Leslie")];
+        let conflicts = people_conflicts(&conn, &people);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].0, 0);
+        assert_eq!(conflicts[0].1[0].first_name, "michel");
+    }
 }
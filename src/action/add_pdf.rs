@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use inquire::{Text, InquireError};
+use crate::database::{SqliteBibDB, BibDataBase};
+use crate::formatter::ToString;
+use crate::formatter::bibtex::BibPrint;
+use crate::model::Entry;
+use crate::pdf;
+use crate::reader::bibtex::load_people;
+use super::journal_or_add;
+use super::attach::store;
+
+fn ask(message: &str, initial: &str) -> Result<String, InquireError> {
+    Text::new(message).with_initial_value(initial).prompt().map(|x| x.trim().to_owned())
+}
+
+/// Complete the fields the pdf didn't have, the found ones are offered for correction
+fn ask_fields(conn: &SqliteBibDB, entry: &mut Entry) -> Result<(), InquireError> {
+    entry.title = ask("Title:", &entry.title)?;
+    while entry.title.is_empty() { entry.title = ask("Title cannot be empty:", "")?; }
+    entry.authors = load_people(&ask("Authors, separated by \"and\":", &entry.authors.to_bib())?);
+    let year = if entry.year == 0 { "".to_owned() } else { entry.year.to_string() };
+    entry.year = loop {
+        match ask("Year:", &year)?.parse::<i32>() {
+            Ok(x) => break x,
+            Err(_) => println!("Year has to be a number"),
+        }
+    };
+    let journal = ask("Journal, empty if none:", entry.journal.as_deref().unwrap_or(""))?;
+    entry.journal = if journal.is_empty() { None } else { Some(conn.search_journal(&journal).unwrap_or(journal)) };
+    entry.citation = ask("Citation:", &entry.generate_citation())?;
+    while entry.citation.is_empty() || conn.get_item(&entry.citation).is_ok() {
+        entry.citation = ask("Citation is taken, choose another:", &entry.citation)?;
+    }
+    Ok(())
+}
+
+/// Add an entry from a pdf alone, with its XMP and Info metadata and the DOI or arXiv ID on its
+/// first pages. The remaining fields are asked for, and the pdf is stored as the main pdf.
+pub fn add_pdf(conn: &SqliteBibDB, path: &Path, keywords: Vec<String>) -> String {
+    let mut entry = match pdf::read_entry(path) {
+        Ok(x) => x,
+        Err(err) => return format!("Cannot read pdf {}: {}", path.to_string_lossy(), err),
+    };
+    if let Some(citation) = conn.find_duplicate(&entry).expect("Search Fail!") {
+        return format!("{} is already in the library as {}", path.to_string_lossy(), citation);
+    }
+    println!("Found in pdf: \n{}", entry.bib_str(true));
+    if ask_fields(conn, &mut entry).is_err() { return "Aborted.".to_owned() }
+    entry.keywords.extend(keywords);
+    let journal_id = journal_or_add(conn, &entry);
+    conn.add_item(&entry, journal_id).unwrap_or_else(|_| panic!("Failed to add entry {}!", entry.citation));
+    if let Err(err) = store(conn, &entry.citation, path, "main", None) { println!("{}", err); }
+    format!("Added {}: {}", entry.citation, entry.to_str())
+}
@@ -0,0 +1,105 @@
+use std::fs::{copy, create_dir_all, metadata};
+use std::path::Path;
+
+use crate::config::CONFIG;
+use crate::database::{SqliteBibDB, BibDataBase, attachment::{Attachment, default_role}};
+use crate::file::{File, BibFile, handler, file_hash, content_name, render_template};
+
+/// Name from the naming template of the folder, or else after the citation. Files other than the
+/// main pdf and the notes get their role added unless the template has it.
+fn base_name(conn: &SqliteBibDB, citation: &str, role: &str, extension: Option<&str>, object_type: &str) -> String {
+    let with_role = role != default_role(object_type);
+    match handler(object_type).template {
+        Some(ref template) => {
+            let entry = conn.get_item(citation).unwrap_or_else(|_| panic!("Cannot find entry {}", citation));
+            let collection = conn.item_collection_paths(citation).expect("Failed to read collections!");
+            let template = match extension {
+                Some(ext) => template.trim_end_matches(&format!(".{}", ext)),
+                None => template,
+            };
+            let name = render_template(template, &entry, role, collection.first().map(|x| x.as_str()));
+            if with_role && !template.contains("{role}") { format!("{}-{}", name, role) } else { name }
+        },
+        None if with_role => format!("{}-{}", citation, role),
+        None => citation.to_owned(),
+    }
+}
+
+/// Stored name of the n-th try, attachment files keep their extension
+pub(super) fn candidate_name(base: &str, extension: Option<&str>, object_type: &str, n: usize) -> String {
+    let base = if n > 1 { format!("{}-{}", base, n) } else { base.to_owned() };
+    match extension {
+        Some(ext) if object_type == "attachment" => format!("{}.{}", base, ext),
+        _ => base,
+    }
+}
+
+/// First name free both on disk and in the library. The current name of a file that is already
+/// stored counts as free.
+pub(super) fn storage_name(conn: &SqliteBibDB, citation: &str, role: &str, extension: Option<&str>,
+                           object_type: &str, current: Option<&str>) -> String {
+    let base = base_name(conn, citation, role, extension, object_type);
+    (1..).map(|n| candidate_name(&base, extension, object_type, n))
+        .find(|x| current == Some(x.as_str()) || (!File::new(x, object_type).path().exists() &&
+              !conn.file_name_taken(x, object_type).expect("Find file record in db fail!")))
+        .unwrap()
+}
+
+/// Copy a file into the library and record it as an attachment of the entry. Pdf files go to the
+/// pdf folder, any other file to the attachment folder, under a name from the naming template that
+/// doesn't collide with other files. In the content-addressed store the name is the hash, and a
+/// file already in the store is not copied again.
+pub(super) fn store(conn: &SqliteBibDB, id: &str, path: &Path, role: &str, label: Option<&str>)
+        -> Result<Attachment, String> {
+    let role = role.trim().to_lowercase();
+    let extension = path.extension().map(|x| x.to_string_lossy().to_lowercase());
+    let object_type = if extension.as_deref() == Some("pdf") { "pdf" } else { "attachment" };
+    let attachments = conn.get_attachments(id).expect("Find file record in db fail!");
+    if role == "main" && attachments.iter().any(|x| x.role == "main" && x.object_type == object_type) {
+        return Err(format!("{} already has a main {}, attach this one with another role like preprint",
+                           id, object_type))
+    }
+    let hash = file_hash(path).map_err(|err| format!("Cannot read {}: {}", path.to_string_lossy(), err))?;
+    if attachments.iter().any(|x| x.hash.as_ref() == Some(&hash)) {
+        return Err(format!("{} is already attached to {}", path.to_string_lossy(), id))
+    }
+    let size = metadata(path).map(|x| x.len() as i64).ok();
+    let name = if CONFIG.content_addressed { content_name(&hash, extension.as_deref(), object_type) }
+               else { storage_name(conn, id, &role, extension.as_deref(), object_type, None) };
+    let file = File::new(&name, object_type);
+    if !file.path().exists() {
+        if let Some(folder) = file.path().parent() {
+            create_dir_all(folder).unwrap_or_else(|_| panic!("Failed to create folder {}", folder.to_string_lossy()));
+        }
+        copy(path, file.path()).unwrap_or_else(
+            |_| panic!("Failed to copy {} to {}", path.to_string_lossy(), file.path().to_string_lossy()));
+    }
+    let attachment = Attachment{name, object_type: object_type.to_owned(), role,
+                                label: label.map(|x| x.trim().to_owned()).filter(|x| !x.is_empty()),
+                                size, hash: Some(hash)};
+    conn.add_attachment(id, &attachment).expect("Failed to add file record!");
+    Ok(attachment)
+}
+
+/// attach a file to an existing entry
+pub fn attach(conn: &SqliteBibDB, id: &str, path: &Path, role: &str, label: Option<&str>) -> String {
+    if conn.get_item(id).is_err() { return format!("Cannot find entry with citation = {}", id) }
+    if !path.is_file() { return format!("Cannot find file {}", path.to_string_lossy()) }
+    match store(conn, id, path, role, label) {
+        Ok(x) => format!("Attached {} to {} as {}", path.to_string_lossy(), id, x.role),
+        Err(x) => x,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_name() {
+        assert_eq!(candidate_name("sur2005", Some("pdf"), "pdf", 1), "sur2005");
+        assert_eq!(candidate_name("2005/sur-patterning", Some("pdf"), "pdf", 2), "2005/sur-patterning-2");
+        assert_eq!(candidate_name("sur2005-supplement", Some("zip"), "attachment", 2), "sur2005-supplement-2.zip");
+        assert_eq!(candidate_name("sur2005-dataset", None, "attachment", 1), "sur2005-dataset");
+    }
+}
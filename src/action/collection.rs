@@ -0,0 +1,84 @@
+use crate::database::{SqliteBibDB, BibDataBase, SearchFilter};
+use crate::config::CONFIG;
+use crate::formatter::ToString;
+
+fn find(conn: &SqliteBibDB, path: &str) -> Result<i64, String> {
+    match conn.find_collection(path).expect("Failed to read collections!") {
+        Some(id) => Ok(id),
+        None => Err(format!("Cannot find collection {}", path)),
+    }
+}
+
+/// create the collection if needed and put the entries in it
+pub fn add(conn: &SqliteBibDB, path: &str, citations: &[String]) -> String {
+    let id = match conn.create_collection(path) {
+        Ok(id) => id,
+        Err(_) => return format!("Invalid collection path {}", path),
+    };
+    let mut output = Vec::new();
+    for citation in citations.iter() {
+        if conn.get_item(citation).is_err() {
+            output.push(format!("Cannot find entry with citation = {}", citation));
+            continue;
+        }
+        conn.add_to_collection(id, citation)
+            .unwrap_or_else(|_| panic!("Failed to add {} to collection {}", citation, path));
+    }
+    output.push(format!("Collection {}: {} entries", path, conn.collection_items(id).unwrap().len()));
+    output.join("\n")
+}
+
+/// take the entries out of the collection, or delete the collection with its subcollections if no
+/// entries are given
+pub fn remove(conn: &SqliteBibDB, path: &str, citations: &[String]) -> String {
+    let id = match find(conn, path) { Ok(x) => x, Err(x) => return x };
+    if citations.is_empty() {
+        conn.remove_collection(id).unwrap_or_else(|_| panic!("Failed to delete collection {}", path));
+        return format!("Deleted collection {}", path);
+    }
+    for citation in citations.iter() {
+        conn.remove_from_collection(id, citation)
+            .unwrap_or_else(|_| panic!("Failed to remove {} from collection {}", citation, path));
+    }
+    format!("Collection {}: {} entries", path, conn.collection_items(id).unwrap().len())
+}
+
+fn tree(conn: &SqliteBibDB, parent: Option<i64>, depth: usize, output: &mut Vec<String>) {
+    for collection in conn.list_collections(parent).expect("Failed to read collections!") {
+        output.push(format!("{}{} ({})", "    ".repeat(depth), collection.name, collection.item_no));
+        tree(conn, Some(collection.id), depth + 1, output);
+    }
+}
+
+/// the tree of subcollections, followed by the entries directly in the collection
+pub fn list(conn: &SqliteBibDB, path: Option<&str>) -> String {
+    let parent = match path {
+        Some(x) => match find(conn, x) { Ok(id) => Some(id), Err(x) => return x },
+        None => None,
+    };
+    let mut output = Vec::new();
+    tree(conn, parent, 0, &mut output);
+    if let Some(id) = parent {
+        for citation in conn.collection_items(id).expect("Failed to read collections!") {
+            let entry = conn.get_item(&citation).unwrap_or_else(|_| panic!("Cannot find entry {}", citation));
+            output.push(format!("{}: {}", citation, entry.to_str()));
+        }
+    }
+    if output.is_empty() { "No collections.".to_owned() } else { output.join("\n") }
+}
+
+pub fn move_to(conn: &SqliteBibDB, from: &str, to: &str) -> String {
+    let id = match find(conn, from) { Ok(x) => x, Err(x) => return x };
+    match conn.move_collection(id, to) {
+        Ok(_) => format!("Moved collection {} to {}", from, to),
+        Err(_) => format!("Cannot move collection {} to {}", from, to),
+    }
+}
+
+/// all entries in the collection and its subcollections, as bibtex or reference strings
+pub fn output(conn: &SqliteBibDB, path: &str, bibtex: bool) -> String {
+    let id = match find(conn, path) { Ok(x) => x, Err(x) => return x };
+    let entries = conn.search_by(&SearchFilter{collection: Some(id), ..Default::default()}).expect("Search Fail!");
+    entries.iter().map(|x| if bibtex { x.bib_str(CONFIG.bibtex_utf8) } else { x.to_str() })
+        .collect::<Vec<String>>().join("\n")
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use crate::config::CONFIG;
+use crate::database::{SqliteBibDB, BibDataBase, history::entry_diff};
+use crate::formatter::{ToString, added_str};
+use crate::metadata::{MetadataSource, JsonLinesSource};
+use crate::model::Entry;
+use super::save_update;
+
+/// Fill the fields missing in the given entries, or in the whole library if none is given, from
+/// the metadata source. Fields already set are never changed. The proposed fields are listed, and
+/// saved with apply, each entry as an update that can be undone.
+pub fn enrich(conn: &SqliteBibDB, source: &dyn MetadataSource, ids: &[String], apply: bool) -> String {
+    let citations = if ids.is_empty() { conn.citations().expect("Failed to read library!") } else { ids.to_vec() };
+    let mut output = Vec::new();
+    let mut enriched = 0;
+    for citation in citations.iter() {
+        let entry = match conn.get_item(citation) {
+            Ok(x) => x,
+            Err(_) => { output.push(format!("Cannot find entry with citation = {}", citation)); continue },
+        };
+        let found = match source.lookup(&entry) {
+            Some(x) => x,
+            None => continue,
+        };
+        let mut proposed = entry.clone();
+        proposed.update(&found);
+        // use the name the library already has for the journal
+        if let (None, Some(name)) = (&entry.journal, &proposed.journal) {
+            if let Ok(known) = conn.search_journal(name) { proposed.journal = Some(known); }
+        }
+        let diff = entry_diff(&entry, &proposed);
+        if diff.is_empty() { continue }
+        enriched += 1;
+        output.push(format!("{}: {}", citation, entry.to_str()));
+        output.extend(diff.into_iter().map(|(field, _, value)| format!("\t{}: {}", field,
+                                                                        added_str(value.unwrap_or_default()))));
+        if apply { save_update(conn, &proposed); }
+    }
+    output.push(format!("{} of {} entries {}", enriched, citations.len(),
+                        if apply { "enriched" } else { "can be enriched, save with --apply" }));
+    output.join("\n")
+}
+
+/// enrich from a JSON-lines dump, by default the metadata_dump in bibrs.toml
+pub fn enrich_from_dump(conn: &SqliteBibDB, path: Option<&Path>, ids: &[String], apply: bool) -> String {
+    let path = match path.or_else(|| CONFIG.metadata_dump.as_deref()) {
+        Some(x) => x,
+        None => return "Give a metadata dump with --source, or set metadata_dump in bibrs.toml".to_owned(),
+    };
+    let citations = if ids.is_empty() { conn.citations().expect("Failed to read library!") } else { ids.to_vec() };
+    let entries: Vec<Entry> = citations.iter().filter_map(|x| conn.get_item(x).ok()).collect();
+    let (source, skipped) = match JsonLinesSource::open(path, &entries) {
+        Ok(x) => x,
+        Err(err) => return format!("Cannot read metadata dump {}: {}", path.to_string_lossy(), err),
+    };
+    if skipped > 0 { eprintln!("Skipped {} unreadable lines of {}", skipped, path.to_string_lossy()); }
+    enrich(conn, &source, ids, apply)
+}
@@ -0,0 +1,49 @@
+use std::fs::{create_dir_all, remove_dir, rename};
+use std::path::Path;
+
+use crate::database::SqliteBibDB;
+use crate::file::{File, BibFile, handler};
+use super::attach::storage_name;
+use super::fsck::is_content_addressed;
+
+/// remove the folders left empty by a move, up to the folder of the file type
+fn remove_empty_folders(path: &Path, root: &Path) {
+    for folder in path.ancestors().skip(1).take_while(|x| x.starts_with(root) && *x != root) {
+        if remove_dir(folder).is_err() { break }
+    }
+}
+
+/// Move the attached files to the names the naming templates give now, and update their records.
+/// Files in the content-addressed store keep their names. With dry_run the moves are only listed.
+pub fn reorganize(conn: &SqliteBibDB, dry_run: bool) -> String {
+    let records = conn.all_attachments().expect("Find file record in db fail!");
+    let mut output = Vec::new();
+    let (mut moved, mut missing) = (0, 0);
+    for (citation, attachment) in records.iter().filter(|x| !is_content_addressed(&x.1)) {
+        let file = File::new(&attachment.name, &attachment.object_type);
+        if !file.path().exists() { missing += 1; continue }
+        let extension = file.path().extension().map(|x| x.to_string_lossy().to_lowercase());
+        let name = storage_name(conn, citation, &attachment.role, extension.as_deref(), &attachment.object_type,
+                                Some(&attachment.name));
+        if name == attachment.name { continue }
+        let target = File::new(&name, &attachment.object_type);
+        output.push(format!("{}: {} -> {}", citation, attachment.name, name));
+        moved += 1;
+        if dry_run { continue }
+        if let Some(folder) = target.path().parent() {
+            create_dir_all(folder).unwrap_or_else(|_| panic!("Failed to create folder {}", folder.to_string_lossy()));
+        }
+        rename(file.path(), target.path()).unwrap_or_else(
+            |_| panic!("Failed to move {} to {}", file.path().to_string_lossy(), target.path().to_string_lossy()));
+        conn.rename_file(citation, &attachment.object_type, &attachment.name, &name)
+            .unwrap_or_else(|_| panic!("Failed to update file record of {}", citation));
+        remove_empty_folders(file.path(), &handler(&attachment.object_type).folder);
+    }
+    if missing > 0 { output.push(format!("{} files are missing, see bibrs fsck", missing)); }
+    output.push(match (moved, dry_run) {
+        (0, _) => "All files already follow the naming templates.".to_owned(),
+        (_, true) => format!("{} files would be moved, run without --dry-run to move them", moved),
+        (_, false) => format!("Moved {} files", moved),
+    });
+    output.join("\n")
+}
@@ -0,0 +1,192 @@
+use std::collections::{HashSet, HashMap};
+use std::fs::{metadata, rename};
+use std::iter::once;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::config::{Config, CONFIG};
+use crate::database::{SqliteBibDB, attachment::Attachment};
+use crate::file::{File, BibFile, handler, file_hash, content_name};
+use super::trash::escape_name;
+
+/// whether the file is named after its recorded hash, then a changed content is a damaged file
+pub(super) fn is_content_addressed(attachment: &Attachment) -> bool {
+    let extension = attachment.name.rsplit_once('.').map(|x| x.1);
+    attachment.hash.as_ref().map_or(false, |hash|
+        content_name(hash, extension, &attachment.object_type) == attachment.name)
+}
+
+/// files in the folder and its subfolders
+fn folder_files(folder: &Path) -> Vec<PathBuf> {
+    let entries = match folder.read_dir() {
+        Ok(x) => x,
+        Err(_) => return Vec::new(),
+    };
+    let mut files = Vec::new();
+    for path in entries.filter_map(Result::ok).map(|x| x.path()) {
+        if path.is_dir() { files.extend(folder_files(&path)) } else if path.is_file() { files.push(path) }
+    }
+    files
+}
+
+/// Name in the trash folder of an orphaned file, with its subfolder escaped like trashed files, and
+/// numbered when an earlier orphan has the name
+fn orphan_name(file_type: &str, relative: &str, taken: impl Fn(&str) -> bool) -> String {
+    (1..).map(|n| match n {
+        1 => escape_name(&format!("orphan-{}", file_type), relative),
+        n => escape_name(&format!("orphan-{}-{}", file_type, n), relative),
+    }).find(|x| !taken(x)).unwrap()
+}
+
+/// Files recorded by the other libraries, the main one included, which can share the folders
+fn other_library_files() -> HashSet<PathBuf> {
+    let main = Config::load(None, None);
+    let mut files = HashSet::new();
+    let names = once(None).chain(main.libraries.keys().map(|x| Some(x.as_str())));
+    for name in names.filter(|x| *x != CONFIG.library.as_deref()) {
+        let config = Config::load(None, name);
+        // opening a missing database would create an empty one without tables
+        if !config.database.exists() { continue }
+        let conn = SqliteBibDB::new(Some(config.database.clone()));
+        if !conn.is_set_up() { continue }
+        for (_, attachment) in conn.all_attachments().expect("Find file record in db fail!") {
+            files.insert(File::in_folder(&attachment.name, config.handler(&attachment.object_type)).path);
+        }
+    }
+    files
+}
+
+fn section(output: &mut Vec<String>, title: &str, lines: Vec<String>) {
+    if lines.is_empty() { return }
+    output.push(format!("{} ({}):", title, lines.len()));
+    output.extend(lines.into_iter().map(|x| format!("\t{}", x)));
+}
+
+/// Check the files of the library against their records: missing files, files in the pdf, comment
+/// and attachment folders that no entry refers to, pdf and attachment files whose content differs
+/// from the recorded hash, and entries without pdf. Comment files are notes and change, so their
+/// content is not checked.
+/// Files recorded by another library sharing a folder are not orphaned.
+/// With repair, records of missing files are dropped, orphaned files are moved to the trash
+/// folder, and hashes are recorded for files without one and updated for changed files that are
+/// not in the content-addressed store. Damaged files in the store are only reported.
+/// With dates, entries from before dates were recorded are dated by the modification time of their
+/// oldest file, without any of the repairs.
+pub fn fsck(conn: &SqliteBibDB, repair: bool, dates: bool) -> String {
+    let records = conn.all_attachments().expect("Find file record in db fail!");
+    let mut known: HashSet<PathBuf> = HashSet::new();
+    let mut checked: HashSet<(String, String)> = HashSet::new();
+    let (mut missing, mut changed, mut damaged, mut unhashed) = (Vec::new(), Vec::new(), Vec::new(), 0);
+    let mut oldest: HashMap<&str, i64> = HashMap::new();
+    for (citation, attachment) in records.iter() {
+        let file = File::new(&attachment.name, &attachment.object_type);
+        known.insert(file.path().to_path_buf());
+        if !file.path().exists() {
+            missing.push(format!("{}: {} {}", citation, attachment.role, file.path().to_string_lossy()));
+            if repair {
+                conn.remove_file_record(citation, &attachment.name, &attachment.object_type)
+                    .expect("Failed to remove file record!");
+            }
+            continue
+        }
+        if let Ok(modified) = metadata(file.path()).and_then(|x| x.modified()) {
+            let modified = modified.duration_since(UNIX_EPOCH).map(|x| x.as_secs() as i64).unwrap_or_default();
+            let time = oldest.entry(citation).or_insert(modified);
+            *time = (*time).min(modified);
+        }
+        // files shared in the store are read once
+        if attachment.object_type == "comment" ||
+                !checked.insert((attachment.name.clone(), attachment.object_type.clone())) { continue }
+        let hash = file_hash(file.path())
+            .unwrap_or_else(|_| panic!("Cannot read {}", file.path().to_string_lossy()));
+        let size = metadata(file.path()).map(|x| x.len() as i64).unwrap_or_default();
+        match attachment.hash {
+            Some(ref recorded) if *recorded == hash => continue,
+            Some(_) if is_content_addressed(attachment) => {
+                damaged.push(format!("{}: {}", citation, file.path().to_string_lossy()));
+                continue
+            },
+            Some(_) => changed.push(format!("{}: {}", citation, file.path().to_string_lossy())),
+            None => unhashed += 1,
+        }
+        if repair {
+            conn.set_file_hash(&attachment.name, &attachment.object_type, size, &hash)
+                .expect("Failed to record file hash!");
+        }
+    }
+    // a file of another library in a shared folder is not an orphan
+    known.extend(other_library_files());
+    let mut orphans = Vec::new();
+    for file_type in ["pdf", "comment", "attachment"].iter() {
+        for path in folder_files(&handler(file_type).folder) {
+            if known.contains(&path) { continue }
+            orphans.push(path.to_string_lossy().into_owned());
+            if repair {
+                let relative = path.strip_prefix(&handler(file_type).folder).unwrap_or(path.as_path()).to_string_lossy();
+                let target = CONFIG.trash.join(orphan_name(file_type, &relative, |x| CONFIG.trash.join(x).exists()));
+                rename(&path, &target).unwrap_or_else(
+                    |_| panic!("Failed to move file {} to trash", path.to_string_lossy()));
+            }
+        }
+    }
+    let undated = conn.undated_items().expect("Failed to read dates!");
+    if dates {
+        for citation in undated.iter() {
+            if let Some(time) = oldest.get(citation.as_str()) {
+                conn.backfill_dates(citation, *time).expect("Failed to record dates!");
+            }
+        }
+    }
+    let no_pdf = conn.citations_without_pdf().expect("Find file record in db fail!");
+    let mut output = Vec::new();
+    let problems = missing.len() + orphans.len() + changed.len() + damaged.len();
+    section(&mut output, if repair { "Missing files, records dropped" } else { "Missing files" }, missing);
+    section(&mut output, if repair { "Orphaned files, moved to trash" } else { "Orphaned files" }, orphans);
+    section(&mut output, if repair { "Changed files, hash updated" } else { "Changed files" }, changed);
+    section(&mut output, "Damaged files in the content-addressed store", damaged);
+    section(&mut output, "Entries without pdf", no_pdf);
+    if unhashed > 0 {
+        output.push(format!("{} files {}", unhashed, if repair { "hashed" } else { "without hash" }));
+    }
+    if !undated.is_empty() {
+        let dated = undated.iter().filter(|x| oldest.contains_key(x.as_str())).count();
+        output.push(if dates {
+            format!("{} entries without date added, {} dated by their files", undated.len(), dated)
+        } else {
+            format!("{} entries without date added, {} can be dated by their files with --dates", undated.len(), dated)
+        });
+    }
+    output.push(match (problems, repair) {
+        (0, _) => "No problems found.".to_owned(),
+        (_, false) => format!("{} problems found, fix them with --repair", problems),
+        (_, true) => format!("{} problems found and repaired where possible", problems),
+    });
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_addressed() {
+        let mut attachment = Attachment{name: "sur2005".to_owned(), object_type: "pdf".to_owned(),
+                                        role: "main".to_owned(), label: None, size: None, hash: None};
+        assert!(!is_content_addressed(&attachment));
+        attachment.hash = Some("ab12".to_owned());
+        assert!(!is_content_addressed(&attachment));
+        attachment.name = "ab12".to_owned();
+        assert!(is_content_addressed(&attachment));
+        attachment.name = "ab12.zip".to_owned();
+        attachment.object_type = "attachment".to_owned();
+        assert!(is_content_addressed(&attachment));
+    }
+
+    #[test]
+    fn test_orphan_name() {
+        let first = orphan_name("pdf", "2005/sur.pdf", |_| false);
+        assert_eq!(first, "orphan-pdf-2005%2Fsur.pdf");
+        assert_ne!(orphan_name("pdf", "2006/sur.pdf", |_| false), first);
+        assert_eq!(orphan_name("pdf", "2005/sur.pdf", |x| x == first), "orphan-pdf-2-2005%2Fsur.pdf");
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+use std::fs::read_to_string;
+
+use itertools::Itertools;
+use crate::database::{SqliteBibDB, BibDataBase, fulltext::FulltextHit};
+use crate::file::{File, BibFile, file_hash};
+use crate::formatter::LabeledPrint;
+use crate::pdf;
+use super::build_filter;
+
+/// most pages listed for one search
+const HIT_LIMIT: i64 = 50;
+
+/// Text of a pdf by page, or of a comment file as a single page. Files that cannot be read give no
+/// pages, so that they are not tried again until they change.
+fn file_pages(file: &File, file_type: &str) -> Vec<String> {
+    match file_type {
+        "pdf" => pdf::page_texts(file.path()).unwrap_or_else(|err| {
+            eprintln!("Cannot read text of {}: {}", file.path().to_string_lossy(), err);
+            Vec::new()
+        }),
+        _ => read_to_string(file.path()).map(|x| vec![x]).unwrap_or_default(),
+    }
+}
+
+/// Bring the full-text index up to date with the attached pdf and comment files. Files are keyed
+/// by the hash of their content, so only new or changed files are read.
+pub fn index(conn: &SqliteBibDB) -> String {
+    let files = conn.all_files().expect("Failed to read file records!");
+    let (mut read, mut unchanged, mut missing) = (0, 0, 0);
+    for (citation, name, file_type) in files.iter().filter(|x| x.2 == "pdf" || x.2 == "comment") {
+        let file = File::new(name, file_type);
+        let hash = match file_hash(file.path()) {
+            Ok(x) => x,
+            Err(_) => { missing += 1; continue },
+        };
+        if conn.fulltext_hash(citation, name).expect("Failed to read index!").as_ref() == Some(&hash) {
+            unchanged += 1;
+            continue
+        }
+        if !conn.is_indexed(&hash).expect("Failed to read index!") {
+            conn.index_pages(&hash, &file_pages(&file, file_type))
+                .unwrap_or_else(|_| panic!("Failed to index {}", file.path().to_string_lossy()));
+            read += 1;
+        }
+        conn.link_fulltext(citation, name, &hash).expect("Failed to update index!");
+    }
+    let pruned = conn.prune_fulltext().expect("Failed to update index!");
+    format!("Indexed {} files, {} unchanged, {} missing, {} dropped", read, unchanged, missing, pruned)
+}
+
+/// Entries with the phrase in their attached files, with the page and the text around it. Search
+/// conditions on authors, keywords and collection narrow the results down.
+pub fn search_fulltext(conn: &SqliteBibDB, phrase: &str, authors: Vec<String>, keywords: Vec<String>,
+                       collection: Option<&str>, expand: bool) -> String {
+    if phrase.trim().is_empty() { return "Search for a phrase in the attached files!".to_owned() }
+    let allowed: Option<HashSet<String>> = if authors.is_empty() && keywords.is_empty() && collection.is_none() {
+        None
+    } else {
+        let filter = match build_filter(conn, &authors, &keywords, collection, expand) {
+            Ok(x) => x,
+            Err(x) => return x,
+        };
+        Some(conn.search_by(&filter).expect("Search Fail!").into_iter().map(|x| x.citation).collect())
+    };
+    let query = format!("\"{}\"", phrase.replace('"', "\"\""));
+    // the limit is only applied after the search conditions, to keep the hits of the entries they allow
+    let limit = if allowed.is_some() { -1 } else { HIT_LIMIT };
+    let hits = conn.search_fulltext(&query, limit).expect("Search Fail!").into_iter()
+        .filter(|x| allowed.as_ref().map_or(true, |allowed| allowed.contains(&x.citation))).take(HIT_LIMIT as usize);
+    // entries in the order of their best page
+    let mut groups: Vec<(String, Vec<FulltextHit>)> = Vec::new();
+    for hit in hits {
+        match groups.iter_mut().find(|x| x.0 == hit.citation) {
+            Some(group) => group.1.push(hit),
+            None => groups.push((hit.citation.clone(), vec![hit])),
+        }
+    }
+    if groups.is_empty() {
+        return format!("\"{}\" not found in attached files, run bibrs index if files were added", phrase)
+    }
+    let mut output = Vec::new();
+    for (citation, hits) in groups {
+        let entry = conn.get_item(&citation).unwrap_or_else(|_| panic!("Cannot find entry {}", citation));
+        output.push(entry.labeled_to_str(&authors));
+        output.extend(hits.iter().sorted_by_key(|x| x.page).map(|x| format!("\t{}", x.labeled_to_str(&authors))));
+    }
+    output.join("\n")
+}
@@ -0,0 +1,20 @@
+use crate::database::{SqliteBibDB, history::HistoryRecord};
+use crate::formatter::{added_str, deleted_str};
+
+fn record_to_str(record: &HistoryRecord) -> String {
+    let mut output = format!("{} {} {}", record.changed_at, record.user.as_deref().unwrap_or("?"), record.operation);
+    if let Some(ref field) = record.field { output.push_str(&format!(" {}:", field)); }
+    if let Some(ref old_value) = record.old_value { output.push_str(&format!(" {}", deleted_str(old_value))); }
+    if let Some(ref new_value) = record.new_value { output.push_str(&format!(" {}", added_str(new_value))); }
+    output
+}
+
+/// changes made to an entry, oldest first, with removed values crossed out and new values highlighted
+pub fn log(conn: &SqliteBibDB, citation: &str) -> String {
+    let history = conn.get_history(citation).expect("Failed to read history!");
+    if history.is_empty() {
+        format!("No history recorded for {}", citation)
+    } else {
+        history.iter().map(record_to_str).collect::<Vec<String>>().join("\n")
+    }
+}
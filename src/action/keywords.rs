@@ -1,54 +1,172 @@
 use core::{fmt, panic};
 use std::{collections::HashSet, fmt::Display};
 use itertools::Itertools;
-use termion::{color, style};
-use crate::{database::{SqliteBibDB, BibDataBase}, model::Entry};
-
-macro_rules! fg {
-    ($col:ident, $content:expr) => {
-        format!("{}{}{}", color::Fg(color::$col), $content, color::Fg(color::Reset))
-    }
-}
+use crate::{database::{SqliteBibDB, BibDataBase, SearchFilter, operation::Operation}, model::Entry};
+use crate::formatter::{ToString, added_str, deleted_str, inherited_str};
 
 pub struct AlteredKeywords {
     kept: HashSet<String>,
     added: HashSet<String>,
-    deleted: HashSet<String>
+    deleted: HashSet<String>,
+    /// implied by the assigned keywords through the taxonomy
+    inherited: HashSet<String>,
+}
+
+fn resolve(conn: &SqliteBibDB, terms: HashSet<String>) -> HashSet<String> {
+    conn.resolve_keywords(&terms.into_iter().collect::<Vec<String>>()).expect("Failed to read keyword aliases!")
+        .into_iter().collect()
 }
 
 /// add or delete keywords
 /// returns a tuple (PaperItem, Added Keywords, Deleted Keywords, Kept Keywords)
 pub fn keywords(conn: &SqliteBibDB, citation: &str, add: HashSet<String>, del: HashSet<String>) 
     -> (Entry, AlteredKeywords) {
+    let (new_entry, alteration, operation) = alter(conn, citation, add, del);
+    if let Some(operation) = operation {
+        conn.record_operation(&operation).expect("Failed to update operation journal!");
+    }
+    (new_entry, alteration)
+}
+
+/// add or delete keywords, with the change to record for undo if anything changed
+fn alter(conn: &SqliteBibDB, citation: &str, add: HashSet<String>, del: HashSet<String>)
+    -> (Entry, AlteredKeywords, Option<Operation>) {
     let old_entry = conn.get_item(citation).unwrap_or_else(|_| panic!("Cannot find entry {}", &citation));
-    if !add.is_empty() {
-        let add_new: Vec<&String> = add.difference(&old_entry.keywords).collect();
-        if !add_new.is_empty() { conn.add_keywords(&old_entry.citation, &add_new)
-            .unwrap_or_else(|_| panic!("Failed to add keywords to {}", &old_entry.citation));
-        }
-    };
-    if !del.is_empty() {
-        let del_exist: Vec<&String> = del.intersection(&old_entry.keywords).collect();
-        if !del_exist.is_empty() { conn.del_keywords(&old_entry.citation, &del_exist)
-            .unwrap_or_else(|_| panic!("Failed to delete keywords from {}", &old_entry.citation));
-        }
-    };
+    let add = resolve(conn, add);
+    let del = resolve(conn, del);
+    let add_new: Vec<&String> = add.difference(&old_entry.keywords).collect();
+    if !add_new.is_empty() { conn.add_keywords(&old_entry.citation, &add_new)
+        .unwrap_or_else(|_| panic!("Failed to add keywords to {}", &old_entry.citation));
+    }
+    let del_exist: Vec<&String> = del.intersection(&old_entry.keywords).collect();
+    if !del_exist.is_empty() { conn.del_keywords(&old_entry.citation, &del_exist)
+        .unwrap_or_else(|_| panic!("Failed to delete keywords from {}", &old_entry.citation));
+    }
+    let operation = if !add_new.is_empty() || !del_exist.is_empty() {
+        Some(Operation::Keywords{citation: old_entry.citation.clone(),
+            added: add_new.into_iter().cloned().collect(), deleted: del_exist.into_iter().cloned().collect()})
+    } else { None };
     let new_entry = conn.get_item(citation).unwrap();
     let alteration = AlteredKeywords{
         kept: new_entry.keywords.intersection(&old_entry.keywords).map(|x| x.to_owned()).collect(),
         added: new_entry.keywords.difference(&old_entry.keywords).map(|x| x.to_owned()).collect(),
-        deleted: old_entry.keywords.difference(&new_entry.keywords).map(|x| x.to_owned()).collect()
+        deleted: old_entry.keywords.difference(&new_entry.keywords).map(|x| x.to_owned()).collect(),
+        inherited: conn.keyword_ancestors(&new_entry.keywords.iter().collect::<Vec<&String>>())
+            .expect("Failed to read keyword taxonomy!"),
     };
-    (new_entry, alteration)
+    (new_entry, alteration, operation)
+}
+
+/// every keyword with the number of entries tagged with it
+pub fn list(conn: &SqliteBibDB) -> String {
+    let counts = conn.keyword_counts().expect("Failed to read keywords!");
+    if counts.is_empty() { return "No keywords.".to_owned() }
+    counts.iter().map(|(text, count)| format!("{:>5}  {}", count, text)).join("\n")
+}
+
+pub fn rename(conn: &SqliteBibDB, old: &str, new: &str) -> String {
+    let fail = "Failed to read keywords!";
+    if conn.keyword_id(old).expect(fail).is_none() { return format!("Cannot find keyword {}", old) }
+    // renaming to a keyword that exists merges the two
+    let operation = if conn.keyword_id(new).expect(fail).is_some() {
+        Operation::MergeKeywords{target: new.to_owned(), sources: vec![old.to_owned()]}
+    } else { Operation::RenameKeyword{from: old.to_owned(), to: new.to_owned()} };
+    let count = conn.rename_keyword(old, new).unwrap_or_else(|_| panic!("Failed to rename keyword {}", old));
+    conn.record_operation(&operation).expect("Failed to update operation journal!");
+    format!("Renamed keyword {} to {} on {} entries", old, new, count)
+}
+
+/// merge the sources into the target keyword, sources become aliases of the target
+pub fn merge(conn: &SqliteBibDB, target: &str, sources: &[String]) -> String {
+    let count = conn.merge_keywords(target, sources)
+        .unwrap_or_else(|_| panic!("Failed to merge keywords into {}", target));
+    conn.record_operation(&Operation::MergeKeywords{target: target.to_owned(), sources: sources.to_vec()})
+        .expect("Failed to update operation journal!");
+    format!("Merged {} into {}, {} entries retagged", sources.join(", "), target, count)
+}
+
+/// add aliases to the keyword, or list all aliases if no keyword is given
+pub fn alias(conn: &SqliteBibDB, keyword: Option<&str>, aliases: &[String]) -> String {
+    match keyword {
+        Some(keyword) => {
+            for alias in aliases.iter() {
+                conn.add_alias(keyword, alias).unwrap_or_else(|_| panic!("Failed to add alias {}", alias));
+            }
+            format!("{} now stands for {}", aliases.join(", "), keyword)
+        },
+        None => {
+            let aliases = conn.list_aliases().expect("Failed to read keyword aliases!");
+            if aliases.is_empty() { return "No aliases.".to_owned() }
+            aliases.iter().map(|(alias, text)| format!("{} -> {}", alias, text)).join("\n")
+        }
+    }
+}
+
+pub fn unalias(conn: &SqliteBibDB, aliases: &[String]) -> String {
+    let count: usize = aliases.iter().map(|x| conn.remove_alias(x)
+        .unwrap_or_else(|_| panic!("Failed to remove alias {}", x))).sum();
+    format!("Removed {} aliases", count)
+}
+
+/// put the keyword under the parent in the taxonomy, or at the top level if no parent is given
+pub fn parent(conn: &SqliteBibDB, keyword: &str, parent: Option<&str>) -> String {
+    match (conn.set_keyword_parent(keyword, parent), parent) {
+        (Ok(_), Some(parent)) => format!("{} now implies {}", keyword, parent),
+        (Ok(_), None) => format!("{} is now a top level keyword", keyword),
+        (Err(_), _) => format!("Cannot put {} under {}", keyword, parent.unwrap_or_default()),
+    }
+}
+
+fn subtree(conn: &SqliteBibDB, parent: Option<i64>, depth: usize, output: &mut Vec<String>) {
+    for (id, text, item_no) in conn.keyword_children(parent).expect("Failed to read keyword taxonomy!") {
+        output.push(format!("{}{} ({})", "    ".repeat(depth), text, item_no));
+        subtree(conn, Some(id), depth + 1, output);
+    }
+}
+
+/// the keyword taxonomy as an indented tree, with the number of entries tagged directly
+pub fn tree(conn: &SqliteBibDB) -> String {
+    let mut output = Vec::new();
+    subtree(conn, None, 0, &mut output);
+    if output.is_empty() { "No keywords.".to_owned() } else { output.join("\n") }
+}
+
+/// add and delete keywords on every entry found by the search
+pub fn tag(conn: &SqliteBibDB, authors: Vec<String>, keywords: Vec<String>, collection: Option<&str>,
+           add: HashSet<String>, del: HashSet<String>) -> String {
+    if authors.is_empty() && keywords.is_empty() && collection.is_none() {
+        return "Select entries by author last names, keywords and/or collection!".to_owned();
+    }
+    let collection = match collection {
+        Some(path) => match conn.find_collection(path).expect("Search Fail!") {
+            Some(id) => Some(id),
+            None => return format!("Cannot find collection {}", path),
+        },
+        None => None,
+    };
+    let entries = conn.search_by(&SearchFilter{authors, keywords, collection, ..Default::default()}).expect("Search Fail!");
+    if entries.is_empty() { return "No entries found.".to_owned() }
+    let mut changes = Vec::new();
+    let output = entries.iter().map(|entry| {
+        let (new_entry, altered, operation) = alter(conn, &entry.citation, add.clone(), del.clone());
+        changes.extend(operation);
+        format!("{}\n\t{}", new_entry.to_str(), altered)
+    }).join("\n");
+    if !changes.is_empty() {
+        conn.record_operation(&Operation::Tag{changes}).expect("Failed to update operation journal!");
+    }
+    output
 }
 
 impl Display for AlteredKeywords {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut output: Vec<String> = Vec::new();
         output.push(self.kept.iter().sorted().join(", "));
-        output.push(self.added.iter().sorted().map(|x| fg!(Blue, x)).join(", "));
-        output.push(self.deleted.iter().sorted().map(|x|
-                fg!(Red, format!("{}{}{}", style::CrossedOut, x, style::Reset))).join(", "));
+        output.push(self.added.iter().sorted().map(added_str).join(", "));
+        output.push(self.deleted.iter().sorted().map(deleted_str).join(", "));
+        if !self.inherited.is_empty() {
+            output.push(self.inherited.iter().sorted().map(inherited_str).join(", "));
+        }
         write!(f, "Keywords: {}", output.join(" | "))
     }
 }
@@ -0,0 +1,102 @@
+use std::fs::{copy as copy_file, create_dir_all, metadata};
+
+use crate::config::{Config, CONFIG};
+use crate::database::{SqliteBibDB, BibDataBase, attachment::Attachment};
+use crate::file::{File, BibFile, file_hash};
+use super::journal_or_add;
+use super::attach::candidate_name;
+use super::sync::copy_journal;
+
+/// name of the main library on the command line
+const MAIN: &str = "main";
+
+/// the main library and the named ones, with their databases, the one in use marked by *
+pub fn list() -> String {
+    let main = Config::load(None, None);
+    let mark = |name: Option<&str>| if name == CONFIG.library.as_deref() { "*" } else { " " };
+    let mut output = vec![format!("{} {}\t{}", mark(None), MAIN, main.database.to_string_lossy())];
+    for (name, library) in main.libraries.iter() {
+        output.push(format!("{} {}\t{}", mark(Some(name)), name, library.database.to_string_lossy()));
+    }
+    output.join("\n")
+}
+
+/// Copy a file into the folder of the other library, under its name there unless another file
+/// has it. A file already there, like in a folder both libraries share, is not copied again.
+fn copy_attachment(target: &SqliteBibDB, config: &Config, citation: &str, attachment: &Attachment)
+        -> Result<String, String> {
+    let source = File::new(&attachment.name, &attachment.object_type);
+    if !source.path().exists() {
+        return Err(format!("{}: missing {}, see bibrs fsck", citation, source.path().to_string_lossy()))
+    }
+    let hash = file_hash(source.path()).map_err(|err| format!("Cannot read {}: {}", source.path().to_string_lossy(), err))?;
+    let (stem, extension) = match attachment.name.rsplit_once('.') {
+        Some((stem, ext)) if attachment.object_type == "attachment" => (stem, Some(ext)),
+        _ => (attachment.name.as_str(), None),
+    };
+    let handler = config.handler(&attachment.object_type);
+    let name = (1..).map(|n| candidate_name(stem, extension, &attachment.object_type, n)).find(|x| {
+        let path = File::in_folder(x, handler).path;
+        if path.exists() { file_hash(&path).ok().as_ref() == Some(&hash) }
+        else { !target.file_name_taken(x, &attachment.object_type).expect("Find file record in db fail!") }
+    }).unwrap();
+    let file = File::in_folder(&name, handler);
+    if !file.path().exists() {
+        if let Some(folder) = file.path().parent() {
+            create_dir_all(folder).unwrap_or_else(|_| panic!("Failed to create folder {}", folder.to_string_lossy()));
+        }
+        copy_file(source.path(), file.path()).unwrap_or_else(
+            |_| panic!("Failed to copy {} to {}", source.path().to_string_lossy(), file.path().to_string_lossy()));
+    }
+    let size = metadata(file.path()).map(|x| x.len() as i64).ok();
+    target.add_attachment(citation, &Attachment{name: name.clone(), size, hash: Some(hash), ..attachment.clone()})
+        .expect("Failed to add file record!");
+    Ok(name)
+}
+
+/// Copy entries with their keywords, extra fields and files to another library, main for the
+/// main library. Entries the other library already has, under the citation or as the same
+/// paper, are left out.
+pub fn copy(conn: &SqliteBibDB, citations: &[String], name: &str) -> String {
+    let library = if name == MAIN { None } else { Some(name) };
+    if library == CONFIG.library.as_deref() { return format!("Already in library {}", name) }
+    let config = Config::load(None, None);
+    if let Some(library) = library {
+        if !config.libraries.contains_key(library) {
+            return format!("No library {}, the libraries are: {}", library,
+                           config.libraries.keys().cloned().collect::<Vec<String>>().join(", "))
+        }
+    }
+    let config = Config::load(None, library);
+    // opening a missing database would create an empty one without tables
+    let target = if config.database.exists() { Some(SqliteBibDB::new(Some(config.database.clone()))) } else { None };
+    let target = match target.filter(|x| x.is_set_up()) {
+        Some(x) => x,
+        None => return format!("Library {} has no database at {}, set it up with bibrs --library {} init", name,
+                               config.database.to_string_lossy(), name),
+    };
+    let (mut output, mut copied) = (Vec::new(), 0);
+    for citation in citations.iter() {
+        let entry = match conn.get_item(citation) {
+            Ok(x) => x,
+            Err(_) => { output.push(format!("Cannot find entry with citation = {}", citation)); continue },
+        };
+        if target.get_item(citation).is_ok() {
+            output.push(format!("{} is already in library {}", citation, name));
+            continue
+        }
+        if let Some(duplicate) = target.find_duplicate(&entry).expect("Search Fail!") {
+            output.push(format!("{} is already in library {} as {}", citation, name, duplicate));
+            continue
+        }
+        copy_journal(&target, conn, &entry);
+        let journal_id = journal_or_add(&target, &entry);
+        target.add_item(&entry, journal_id).unwrap_or_else(|_| panic!("Failed to add entry {}!", citation));
+        for attachment in conn.get_attachments(citation).expect("Find file record in db fail!").iter() {
+            if let Err(err) = copy_attachment(&target, &config, citation, attachment) { output.push(err); }
+        }
+        copied += 1;
+    }
+    output.push(format!("Copied {} entries to library {}", copied, name));
+    output.join("\n")
+}
@@ -0,0 +1,44 @@
+use crate::fg;
+use crate::database::{SqliteBibDB, BibDataBase, history::entry_diff};
+use crate::formatter::ToString;
+use crate::validate::{self, Problem, Severity};
+use super::save_update;
+
+fn problem_to_str(problem: &Problem) -> String {
+    let severity = match problem.severity {
+        Severity::Error => fg!(Red, problem.severity),
+        Severity::Warning => fg!(Yellow, problem.severity),
+        Severity::Info => problem.severity.to_string(),
+    };
+    format!("\t{} {}: {}{}", severity, problem.field, problem.message, if problem.fixable { " (fixable)" } else { "" })
+}
+
+/// List the problems of the given entries, or of the whole library if none is given. With fix, the
+/// safe corrections are saved first, each as an update that can be undone.
+pub fn lint(conn: &SqliteBibDB, ids: &[String], fix: bool) -> String {
+    let citations = if ids.is_empty() { conn.citations().expect("Failed to read library!") } else { ids.to_vec() };
+    let mut output = Vec::new();
+    let (mut problem_count, mut fixed_count) = (0, 0);
+    for citation in citations.iter() {
+        let mut entry = match conn.get_item(citation) {
+            Ok(x) => x,
+            Err(_) => { output.push(format!("Cannot find entry with citation = {}", citation)); continue },
+        };
+        if fix {
+            let fixed = validate::fix(&entry);
+            if !entry_diff(&entry, &fixed).is_empty() {
+                save_update(conn, &fixed);
+                fixed_count += 1;
+                entry = fixed;
+            }
+        }
+        let problems = validate::validate(&entry);
+        if problems.is_empty() { continue }
+        problem_count += problems.len();
+        output.push(format!("{}: {}", citation, entry.to_str()));
+        output.extend(problems.iter().map(problem_to_str));
+    }
+    let summary = format!("{} problems in {} entries", problem_count, citations.len());
+    output.push(if fix { format!("{}, fixed {} entries", summary, fixed_count) } else { summary });
+    output.join("\n")
+}
@@ -0,0 +1,34 @@
+use crate::database::{SqliteBibDB, BibDataBase};
+use crate::formatter::ToString;
+use crate::model::{Reading, ReadingStatus};
+use crate::util::now;
+
+/// Set the reading status, priority and rating of an entry, a priority or rating of 0 removes it.
+/// Without any of them the current state is shown.
+pub fn mark(conn: &SqliteBibDB, id: &str, status: Option<&str>, priority: Option<i32>, rating: Option<i32>) -> String {
+    if conn.get_item(id).is_err() { return format!("Cannot find entry with citation = {}", id) }
+    let status = match status {
+        Some(x) => match ReadingStatus::parse(x) {
+            Some(status) => Some(status),
+            None => return format!("Unknown reading status {}, use unread, reading, read or skimmed", x),
+        },
+        None => None,
+    };
+    if rating.map_or(false, |x| !(0..=5).contains(&x)) { return "Rating goes from 1 to 5, 0 removes it".to_owned() }
+    if priority.map_or(false, |x| x < 0) { return "Priority goes from 1 for the most urgent, 0 removes it".to_owned() }
+    let current = conn.get_reading(id).expect("Failed to read reading status!");
+    if status.is_none() && priority.is_none() && rating.is_none() {
+        return match current {
+            Some(reading) => format!("{}: {}", id, reading.to_str()),
+            None => format!("{} is not marked, mark it with unread to put it in the reading queue", id),
+        }
+    }
+    let now = now();
+    let mut reading = current.unwrap_or_else(|| Reading::new(now));
+    if let Some(status) = status { reading.set_status(status, now); }
+    if let Some(priority) = priority { reading.priority = Some(priority).filter(|x| *x > 0); }
+    if let Some(rating) = rating { reading.rating = Some(rating).filter(|x| *x > 0); }
+    reading.marked_at = now;
+    conn.set_reading(id, &reading).unwrap_or_else(|_| panic!("Failed to mark {}!", id));
+    format!("{}: {}", id, reading.to_str())
+}
@@ -0,0 +1,78 @@
+use std::fs::{read_to_string, OpenOptions};
+use std::io::Write;
+
+use crate::database::{SqliteBibDB, BibDataBase};
+use crate::file::{File, BibFile};
+use crate::pdf::{self, Annotation};
+use super::comment_file;
+
+/// one line per annotation, so that extracted ones can be recognized in the comment file
+fn note_line(annotation: &Annotation) -> String {
+    match annotation.color {
+        Some(ref color) => format!("p. {} ({}, {}): {}", annotation.page, annotation.kind, color, annotation.text),
+        None => format!("p. {} ({}): {}", annotation.page, annotation.kind, annotation.text),
+    }
+}
+
+/// lines of the annotations not yet in the comment file
+fn new_notes(existing: &str, annotations: &[Annotation]) -> Vec<String> {
+    let mut notes: Vec<String> = Vec::new();
+    for line in annotations.iter().map(note_line) {
+        if !existing.lines().any(|x| x.trim() == line) && !notes.contains(&line) { notes.push(line); }
+    }
+    notes
+}
+
+/// Print the comment file of the entry. With extract, the highlights, underlines and notes in its
+/// pdf files are first appended to the comment file, leaving out the ones extracted before. The
+/// comment file is only created by extracting.
+pub fn notes(conn: &SqliteBibDB, id: &str, extract: bool) -> String {
+    let entry = match conn.get_item(id) {
+        Ok(x) => x,
+        Err(_) => return format!("Cannot find entry with citation = {}", id),
+    };
+    if !extract {
+        let files = conn.get_files(id).expect("Find file record in db fail!");
+        return match files.iter().find(|x| x.1 == "comment") {
+            Some((file_name, _)) => read_to_string(File::new(file_name, "comment").path()).unwrap_or_default(),
+            None => format!("No notes on {}, extract them from its pdf with --extract", id),
+        }
+    }
+    let comment = comment_file(conn, &entry);
+    let mut existing = read_to_string(comment.path()).unwrap_or_default();
+    let mut annotations = Vec::new();
+    for (file_name, _) in conn.get_files(id).expect("Find file record in db fail!").iter().filter(|x| x.1 == "pdf") {
+        let pdf_file = File::new(file_name, "pdf");
+        match pdf::annotations(pdf_file.path()) {
+            Ok(x) => annotations.extend(x),
+            Err(err) => eprintln!("Cannot read annotations of {}: {}", pdf_file.path().to_string_lossy(), err),
+        }
+    }
+    let notes = new_notes(&existing, &annotations);
+    if !notes.is_empty() {
+        let addition = format!("{}{}\n", if existing.ends_with('\n') || existing.is_empty() { "" } else { "\n" },
+                               notes.join("\n"));
+        OpenOptions::new().append(true).create(true).open(comment.path())
+            .and_then(|mut x| x.write_all(addition.as_bytes()))
+            .unwrap_or_else(|_| panic!("Failed to write to {}", comment.path().to_string_lossy()));
+        existing.push_str(&addition);
+    }
+    eprintln!("Extracted {} new of {} annotations", notes.len(), annotations.len());
+    existing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_notes() {
+        let highlight = Annotation{page: 3, kind: "highlight".to_owned(), color: Some("yellow".to_owned()),
+                                   text: "orientation columns".to_owned()};
+        let note = Annotation{page: 5, kind: "text".to_owned(), color: None,
+                              text: "compare with Hubel 1962".to_owned()};
+        assert_eq!(note_line(&highlight), "p. 3 (highlight, yellow): orientation columns");
+        let existing = "% Title\n% Author\n% 2005\np. 3 (highlight, yellow): orientation columns\n";
+        assert_eq!(new_notes(existing, &[highlight, note]), vec!["p. 5 (text): compare with Hubel 1962"]);
+    }
+}
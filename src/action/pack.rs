@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+use std::env::temp_dir;
+use std::fs;
+use std::io::{copy, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+use zip::{ZipArchive, ZipWriter, write::FileOptions};
+use crate::config::CONFIG;
+use crate::database::{SqliteBibDB, BibDataBase, attachment::{Attachment, default_role}};
+use crate::file::{File, BibFile};
+use crate::formatter::csl::csl_json;
+use crate::model::Entry;
+use crate::reader::bibtex::parse_entries;
+use crate::reader::crossref::read_csl;
+use crate::reader::pandoc::read_pandoc;
+use super::{build_filter, journal_or_add};
+use super::attach::{store, storage_name};
+
+const MANIFEST: &str = "files.json";
+
+/// A file in the archive, listed in files.json
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Member {
+    citation: String,
+    path: String,
+    object_type: String,
+    role: String,
+    label: Option<String>,
+}
+
+/// Path in the archive, in the folder of the file type and after the citation, with the role
+/// unless it is the default one
+fn member_path(citation: &str, attachment: &Attachment, extension: Option<&str>, n: usize) -> String {
+    let mut stem = citation.to_owned();
+    if attachment.role != default_role(&attachment.object_type) { stem = format!("{}-{}", stem, attachment.role); }
+    if n > 1 { stem = format!("{}-{}", stem, n); }
+    match extension {
+        Some(ext) => format!("{}/{}.{}", attachment.object_type, stem, ext),
+        None => format!("{}/{}", attachment.object_type, stem),
+    }
+}
+
+/// The file of an archive without files.json, matched to the longest citation its name starts with
+fn guess_member(path: &str, citations: &[String]) -> Option<Member> {
+    let (object_type, name) = path.split_once('/')?;
+    if !["pdf", "comment", "attachment"].contains(&object_type) { return None }
+    let stem = name.rsplit_once('.').map_or(name, |x| x.0);
+    let citation = citations.iter().filter(|x| stem == x.as_str() || stem.starts_with(&format!("{}-", x)))
+        .max_by_key(|x| x.len())?;
+    let role = stem[citation.len()..].trim_start_matches('-');
+    let role = role.trim_end_matches(|x: char| x.is_ascii_digit()).trim_end_matches('-');
+    Some(Member{citation: citation.clone(), path: path.to_owned(), object_type: object_type.to_owned(),
+                role: if role.is_empty() { default_role(object_type) } else { role }.to_owned(), label: None})
+}
+
+/// citations of the sources, each a citation or a file citing entries, and of the search results
+fn select(conn: &SqliteBibDB, sources: &[String], authors: &[String], keywords: &[String],
+          collection: Option<&str>) -> Result<Vec<String>, String> {
+    let mut citations: Vec<String> = Vec::new();
+    for source in sources.iter() {
+        if PathBuf::from(source).exists() {
+            citations.extend(read_pandoc(&source.into())
+                .unwrap_or_else(|_| panic!("Failed to read file for citation: {}", source)));
+        } else {
+            citations.push(source.clone());
+        }
+    }
+    if !authors.is_empty() || !keywords.is_empty() || collection.is_some() {
+        let filter = build_filter(conn, authors, keywords, collection, false)?;
+        citations.extend(conn.search_by(&filter).expect("Search Fail!").into_iter().map(|x| x.citation));
+    }
+    let mut seen = HashSet::new();
+    citations.retain(|x| seen.insert(x.clone()));
+    Ok(citations)
+}
+
+/// name and content of the library file of an archive, BibTeX with the titles or CSL-JSON
+fn library_file(entries: &[Entry], csl: bool, utf8: bool) -> (&'static str, String) {
+    if csl { return ("library.json", csl_json(entries)) }
+    ("library.bib", entries.iter().map(|x| x.titled_bib_str(utf8)).collect::<Vec<String>>().join("\n"))
+}
+
+/// Write a zip archive with the BibTeX, or CSL-JSON, of the selected entries and their attached
+/// files renamed after the citations. files.json lists the files with their roles and labels.
+pub fn pack(conn: &SqliteBibDB, sources: &[String], authors: Vec<String>, keywords: Vec<String>,
+            collection: Option<&str>, output: &Path, csl: bool) -> String {
+    let citations = match select(conn, sources, &authors, &keywords, collection) {
+        Ok(x) => x,
+        Err(x) => return x,
+    };
+    let mut report = Vec::new();
+    let entries: Vec<Entry> = citations.iter().filter_map(|x| match conn.get_item(x) {
+        Ok(entry) => Some(entry),
+        Err(_) => { report.push(format!("Entry not found for {}!", x)); None },
+    }).collect();
+    if entries.is_empty() { report.push("No entries to pack.".to_owned()); return report.join("\n") }
+    let archive = fs::File::create(output)
+        .unwrap_or_else(|_| panic!("Cannot create {}", output.to_string_lossy()));
+    let mut zip = ZipWriter::new(archive);
+    let options = FileOptions::default();
+    let (library_name, library) = library_file(&entries, csl, CONFIG.bibtex_utf8);
+    zip.start_file(library_name, options).expect("Failed to write archive!");
+    zip.write_all(library.as_bytes()).expect("Failed to write archive!");
+    let (mut manifest, mut used) = (Vec::new(), HashSet::new());
+    for entry in entries.iter() {
+        for attachment in conn.get_attachments(&entry.citation).expect("Find file record in db fail!") {
+            let file = File::new(&attachment.name, &attachment.object_type);
+            if !file.path().exists() {
+                report.push(format!("{}: missing {}, see bibrs fsck", entry.citation, file.path().to_string_lossy()));
+                continue
+            }
+            let extension = file.path().extension().map(|x| x.to_string_lossy().to_lowercase());
+            let path = (1..).map(|n| member_path(&entry.citation, &attachment, extension.as_deref(), n))
+                .find(|x| !used.contains(x)).unwrap();
+            used.insert(path.clone());
+            zip.start_file(path.as_str(), options).expect("Failed to write archive!");
+            copy(&mut fs::File::open(file.path()).expect("Failed to read attached file!"), &mut zip)
+                .expect("Failed to write archive!");
+            manifest.push(Member{citation: entry.citation.clone(), path, object_type: attachment.object_type,
+                                 role: attachment.role, label: attachment.label});
+        }
+    }
+    zip.start_file(MANIFEST, options).expect("Failed to write archive!");
+    zip.write_all(serde_json::to_string_pretty(&manifest).unwrap().as_bytes()).expect("Failed to write archive!");
+    zip.finish().expect("Failed to write archive!");
+    report.push(format!("Packed {} entries and {} files into {}", entries.len(), manifest.len(),
+                        output.to_string_lossy()));
+    report.join("\n")
+}
+
+fn read_text<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<String> {
+    let mut content = String::new();
+    archive.by_name(name).ok()?.read_to_string(&mut content).ok()?;
+    Some(content)
+}
+
+/// the entries in the library.bib or library.json of the archive
+fn library_entries<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Vec<Entry>, String> {
+    if let Some(content) = read_text(archive, "library.bib") {
+        let (entries, warnings) = parse_entries(&content);
+        for warning in warnings.iter() { eprintln!("Warning: {}", warning); }
+        Ok(entries)
+    } else if let Some(content) = read_text(archive, "library.json") {
+        match serde_json::from_str::<Vec<Value>>(&content) {
+            Ok(items) => Ok(items.iter().filter_map(read_csl).collect()),
+            Err(err) => Err(format!("Cannot read library.json: {}", err)),
+        }
+    } else {
+        Err("No library.bib or library.json".to_owned())
+    }
+}
+
+/// the citation, or the first with a letter suffix that is not in use
+fn free_citation(conn: &SqliteBibDB, citation: &str) -> String {
+    std::iter::once(citation.to_owned()).chain((b'a'..=b'z').map(|x| format!("{}{}", citation, x as char)))
+        .find(|x| conn.get_item(x).is_err())
+        .unwrap_or_else(|| panic!("No free citation left for {}", citation))
+}
+
+/// Import an archive written by pack. Entries already in the library are not added again but get
+/// the files they don't have yet, a citation in use gets a letter suffix. Comment files are only
+/// imported for new entries, so that existing notes are kept.
+pub fn unpack(conn: &SqliteBibDB, path: &Path) -> String {
+    let archive = match fs::File::open(path) {
+        Ok(x) => x,
+        Err(err) => return format!("Cannot open {}: {}", path.to_string_lossy(), err),
+    };
+    let mut archive = match ZipArchive::new(archive) {
+        Ok(x) => x,
+        Err(err) => return format!("Cannot read {}: {}", path.to_string_lossy(), err),
+    };
+    let entries = match library_entries(&mut archive) {
+        Ok(x) => x,
+        Err(x) => return format!("{} in {}", x, path.to_string_lossy()),
+    };
+    let mut output = Vec::new();
+    let (mut citations, mut added) = (HashMap::new(), HashSet::new());
+    for mut entry in entries {
+        let packed = entry.citation.clone();
+        if let Some(existing) = conn.find_duplicate(&entry).expect("Search Fail!") {
+            output.push(format!("{} is already in the library as {}", packed, existing));
+            citations.insert(packed, existing);
+            continue
+        }
+        entry.citation = free_citation(conn, &packed);
+        let journal_id = journal_or_add(conn, &entry);
+        conn.add_item(&entry, journal_id).unwrap_or_else(|_| panic!("Failed to add entry {}!", entry.citation));
+        output.push(if entry.citation == packed { format!("Added {}", packed) }
+                    else { format!("Added {} as {}", packed, entry.citation) });
+        added.insert(entry.citation.clone());
+        citations.insert(packed, entry.citation);
+    }
+    let members: Vec<Member> = match read_text(&mut archive, MANIFEST) {
+        Some(content) => serde_json::from_str(&content).unwrap_or_else(|_| panic!("Cannot read {}", MANIFEST)),
+        None => {
+            let packed: Vec<String> = citations.keys().cloned().collect();
+            archive.file_names().filter_map(|x| guess_member(x, &packed)).collect()
+        },
+    };
+    let folder = temp_dir().join("bibrs-unpack");
+    fs::create_dir_all(&folder).unwrap_or_else(|_| panic!("Failed to create folder {}", folder.to_string_lossy()));
+    let mut stored = 0;
+    for member in members.iter() {
+        let citation = match citations.get(&member.citation) {
+            Some(x) => x,
+            None => continue,
+        };
+        if member.object_type == "comment" && !added.contains(citation) { continue }
+        let mut packed_file = match archive.by_name(&member.path) {
+            Ok(x) => x,
+            Err(_) => { output.push(format!("{} is listed but not in the archive", member.path)); continue },
+        };
+        if member.object_type == "comment" {
+            let mut content = String::new();
+            packed_file.read_to_string(&mut content).unwrap_or_else(|_| panic!("Cannot read {}", member.path));
+            let name = storage_name(conn, citation, "notes", None, "comment", None);
+            let comment_file = File::new(&name, "comment");
+            if let Some(parent) = comment_file.path().parent() { fs::create_dir_all(parent).unwrap(); }
+            fs::write(comment_file.path(), content).unwrap();
+            conn.add_file(citation, &name, "comment").expect("Failed to add file record!");
+            stored += 1;
+            continue
+        }
+        let temp = folder.join(Path::new(&member.path).file_name().unwrap());
+        copy(&mut packed_file, &mut fs::File::create(&temp).expect("Failed to extract file!"))
+            .unwrap_or_else(|_| panic!("Cannot read {}", member.path));
+        match store(conn, citation, &temp, &member.role, member.label.as_deref()) {
+            Ok(_) => stored += 1,
+            Err(err) => output.push(err),
+        }
+        fs::remove_file(&temp).ok();
+    }
+    fs::remove_dir(&folder).ok();
+    output.push(format!("Imported {} entries and {} files from {}", added.len(), stored, path.to_string_lossy()));
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_member_path() {
+        let mut attachment = Attachment{name: "2005/sur-patterning".to_owned(), object_type: "pdf".to_owned(),
+                                        role: "main".to_owned(), label: None, size: None, hash: None};
+        assert_eq!(member_path("sur2005", &attachment, Some("pdf"), 1), "pdf/sur2005.pdf");
+        attachment.role = "preprint".to_owned();
+        assert_eq!(member_path("sur2005", &attachment, Some("pdf"), 2), "pdf/sur2005-preprint-2.pdf");
+        let citations = vec!["sur2005".to_owned(), "sur2005a".to_owned()];
+        let member = guess_member("pdf/sur2005-preprint-2.pdf", &citations).unwrap();
+        assert_eq!((member.citation.as_str(), member.role.as_str()), ("sur2005", "preprint"));
+        let member = guess_member("comment/sur2005a.txt", &citations).unwrap();
+        assert_eq!((member.citation.as_str(), member.role.as_str()), ("sur2005a", "notes"));
+        assert!(guess_member("library.bib", &citations).is_none());
+        assert!(guess_member("pdf/hubel1962.pdf", &citations).is_none());
+    }
+
+    #[test]
+    fn test_library_round_trip() {
+        let conn = SqliteBibDB::in_memory();
+        for entry in read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib")).iter() {
+            conn.add_item(entry, None).unwrap();
+        }
+        let entries: Vec<Entry> = conn.citations().unwrap().iter().map(|x| conn.get_item(x).unwrap()).collect();
+        for csl in [false, true].iter() {
+            let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+            let (name, library) = library_file(&entries, *csl, false);
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(library.as_bytes()).unwrap();
+            let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+            let target = SqliteBibDB::in_memory();
+            for entry in library_entries(&mut archive).unwrap().iter() { target.add_item(entry, None).unwrap(); }
+            for entry in entries.iter() {
+                let title = target.get_item(&entry.citation).unwrap().title;
+                // CSL-JSON titles are plain text, without braces and LaTeX commands
+                if *csl { assert!(!title.is_empty()) } else { assert_eq!(title, entry.title) }
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+use crate::database::{SqliteBibDB, BibDataBase, relation::Relation};
+use crate::formatter::ToString;
+use crate::identifier::Identifier;
+use crate::model::Entry;
+use crate::reader::bibtex::parse_entries;
+use crate::reader::crossref::read_csl;
+
+fn describe(conn: &SqliteBibDB, citation: &str) -> String {
+    let entry = conn.get_item(citation).unwrap_or_else(|_| panic!("Cannot find entry {}", citation));
+    format!("{}: {}", citation, entry.to_str())
+}
+
+fn parse(relation: &str) -> Result<(Relation, bool), String> {
+    Relation::parse(relation).ok_or_else(|| format!(
+        "Unknown relation {}, use cites, cited-by, erratum-of, preprint-of or commentary-on", relation))
+}
+
+/// Relate the entry to the others, like bibrs link add sur2005 cites hubel1962. With a reversed
+/// relation like cited-by the others relate to the entry.
+pub fn add(conn: &SqliteBibDB, citation: &str, relation: &str, others: &[String]) -> String {
+    let (relation, reversed) = match parse(relation) { Ok(x) => x, Err(x) => return x };
+    if conn.get_item(citation).is_err() { return format!("Cannot find entry with citation = {}", citation) }
+    let mut output = Vec::new();
+    for other in others.iter() {
+        if other == citation { output.push(format!("Cannot relate {} to itself", citation)); continue }
+        if conn.get_item(other).is_err() {
+            output.push(format!("Cannot find entry with citation = {}", other));
+            continue
+        }
+        let (from, to) = if reversed { (other.as_str(), citation) } else { (citation, other.as_str()) };
+        conn.add_relation(from, relation, to)
+            .unwrap_or_else(|_| panic!("Failed to relate {} to {}!", from, to));
+    }
+    output.push(list(conn, citation));
+    output.join("\n")
+}
+
+/// drop the relations of the entry to the others
+pub fn remove(conn: &SqliteBibDB, citation: &str, relation: &str, others: &[String]) -> String {
+    let (relation, reversed) = match parse(relation) { Ok(x) => x, Err(x) => return x };
+    let mut output = Vec::new();
+    for other in others.iter() {
+        let (from, to) = if reversed { (other.as_str(), citation) } else { (citation, other.as_str()) };
+        if !conn.remove_relation(from, relation, to).unwrap_or_else(|_| panic!("Failed to unrelate {}!", from)) {
+            output.push(format!("{} is not {} {}", from, relation.as_str(), to));
+        }
+    }
+    output.push(list(conn, citation));
+    output.join("\n")
+}
+
+/// the entries related to the entry, what it cites first and then what cites it
+pub fn list(conn: &SqliteBibDB, citation: &str) -> String {
+    if conn.get_item(citation).is_err() { return format!("Cannot find entry with citation = {}", citation) }
+    let relations = conn.get_relations(citation).expect("Failed to read relations!");
+    if relations.is_empty() { return format!("{} is not related to other entries", citation) }
+    relations.iter().map(|x| format!("{} {}", x.label(), describe(conn, &x.other))).collect::<Vec<String>>().join("\n")
+}
+
+/// the item of a reference, by identifier or else by title and year
+fn find_reference(conn: &SqliteBibDB, reference: &Entry) -> Option<String> {
+    for identifier in Identifier::from_entry(reference) {
+        if let Some(citation) = conn.find_identifier(&identifier).expect("Search Fail!") { return Some(citation) }
+    }
+    conn.find_title(&reference.title, reference.year).expect("Search Fail!")
+}
+
+/// Record the entry as citing the references listed in a .bib or CSL-JSON file, those in the
+/// library found by identifier or else by title and year. References not in the library are listed.
+pub fn import(conn: &SqliteBibDB, citation: &str, path: &Path) -> String {
+    if conn.get_item(citation).is_err() { return format!("Cannot find entry with citation = {}", citation) }
+    let content = match fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(err) => return format!("Cannot read {}: {}", path.to_string_lossy(), err),
+    };
+    let references: Vec<Entry> = if path.extension().map_or(false, |x| x == "json") {
+        match serde_json::from_str::<Value>(&content) {
+            Ok(Value::Array(items)) => items.iter().filter_map(read_csl).collect(),
+            Ok(item) => read_csl(&item).into_iter().collect(),
+            Err(err) => return format!("Cannot read {}: {}", path.to_string_lossy(), err),
+        }
+    } else {
+        let (entries, warnings) = parse_entries(&content);
+        for warning in warnings.iter() { eprintln!("Warning: {}", warning); }
+        entries
+    };
+    let (mut found, mut missing) = (0, Vec::new());
+    for reference in references.iter() {
+        match find_reference(conn, reference) {
+            Some(other) if other == citation => (),
+            Some(other) => {
+                conn.add_relation(citation, Relation::Cites, &other)
+                    .unwrap_or_else(|_| panic!("Failed to relate {} to {}!", citation, other));
+                found += 1;
+            },
+            None => missing.push(format!("\t{}", reference.to_str())),
+        }
+    }
+    let mut output = vec![format!("{} cites {} of the {} references in the library", citation, found,
+                                  references.len())];
+    if !missing.is_empty() {
+        output.push(format!("Not in the library ({}), add them with bibrs add to link them:", missing.len()));
+        output.append(&mut missing);
+    }
+    output.join("\n")
+}
+
+/// a relation as (from, relation, to)
+type Edge = (String, Relation, String);
+
+/// Relations up to the depth, found breadth first from the entry
+/// Returns:
+///     (citations by distance, relations)
+fn neighborhood(conn: &SqliteBibDB, citation: &str, depth: usize) -> (Vec<String>, Vec<Edge>) {
+    let mut nodes = vec![citation.to_owned()];
+    let mut edges: Vec<Edge> = Vec::new();
+    let mut seen: HashSet<Edge> = HashSet::new();
+    let mut current = 0;
+    for _ in 0..depth {
+        let end = nodes.len();
+        for idx in current..end {
+            let node = nodes[idx].clone();
+            for relation in conn.get_relations(&node).expect("Failed to read relations!") {
+                let edge = if relation.incoming { (relation.other.clone(), relation.relation, node.clone()) }
+                           else { (node.clone(), relation.relation, relation.other.clone()) };
+                if seen.insert(edge.clone()) { edges.push(edge); }
+                if !nodes.contains(&relation.other) { nodes.push(relation.other); }
+            }
+        }
+        current = end;
+    }
+    (nodes, edges)
+}
+
+fn tree(conn: &SqliteBibDB, citation: &str, depth: usize, level: usize, shown: &mut HashSet<String>,
+        output: &mut Vec<String>) {
+    if level >= depth { return }
+    for relation in conn.get_relations(citation).expect("Failed to read relations!") {
+        let expand = shown.insert(relation.other.clone());
+        output.push(format!("{}{} {}", "    ".repeat(level + 1), relation.label(), describe(conn, &relation.other)));
+        if expand { tree(conn, &relation.other, depth, level + 1, shown, output); }
+    }
+}
+
+fn dot_str(input: &str) -> String {
+    format!("\"{}\"", input.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// the graph in the DOT language of graphviz, each entry labeled with its first author and year
+fn dot(conn: &SqliteBibDB, citation: &str, depth: usize) -> String {
+    let (nodes, edges) = neighborhood(conn, citation, depth);
+    let mut output = vec![format!("digraph {} {{", dot_str(citation))];
+    for node in nodes.iter() {
+        let entry = conn.get_item(node).unwrap_or_else(|_| panic!("Cannot find entry {}", node));
+        let author = entry.authors.first().or_else(|| entry.editors.first())
+            .map(|x| x.last_name.chars().filter(|x| *x != '{' && *x != '}').collect::<String>()).unwrap_or_default();
+        let style = if node == citation { ", style=bold" } else { "" };
+        output.push(format!("    {} [label={}{}];", dot_str(node),
+                            dot_str(&format!("{}\n{} {}", node, author, entry.year)), style));
+    }
+    for (from, relation, to) in edges.iter() {
+        let label = if *relation == Relation::Cites { String::new() }
+                    else { format!(" [label={}]", dot_str(relation.as_str())) };
+        output.push(format!("    {} -> {}{};", dot_str(from), dot_str(to), label));
+    }
+    output.push("}".to_owned());
+    output.join("\n")
+}
+
+/// The entries related to the entry up to the depth, as an indented tree, or as a graph in the DOT
+/// language to draw with graphviz, like bibrs graph ID --format dot | dot -Tsvg > graph.svg
+pub fn graph(conn: &SqliteBibDB, citation: &str, depth: usize, format: &str) -> String {
+    if conn.get_item(citation).is_err() { return format!("Cannot find entry with citation = {}", citation) }
+    match format {
+        "text" => {
+            let mut output = vec![describe(conn, citation)];
+            let mut shown: HashSet<String> = vec![citation.to_owned()].into_iter().collect();
+            tree(conn, citation, depth, 0, &mut shown, &mut output);
+            output.join("\n")
+        },
+        "dot" => dot(conn, citation, depth),
+        _ => format!("Unknown format {}, use text or dot", format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_graph() {
+        let conn = SqliteBibDB::in_memory();
+        for entry in read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib")).iter() {
+            conn.add_item(entry, None).unwrap();
+        }
+        conn.add_relation("einstein", Relation::Cites, "latexcompanion").unwrap();
+        conn.add_relation("knuthwebsite", Relation::CommentaryOn, "latexcompanion").unwrap();
+        let (nodes, edges) = neighborhood(&conn, "einstein", 1);
+        assert_eq!((nodes.len(), edges.len()), (2, 1));
+        let (nodes, edges) = neighborhood(&conn, "einstein", 2);
+        assert_eq!(nodes, vec!["einstein", "latexcompanion", "knuthwebsite"]);
+        assert_eq!(edges[1], ("knuthwebsite".to_owned(), Relation::CommentaryOn, "latexcompanion".to_owned()));
+        let graph = dot(&conn, "einstein", 2);
+        assert!(graph.starts_with("digraph \"einstein\" {"));
+        assert!(graph.contains("    \"einstein\" -> \"latexcompanion\";"));
+        assert!(graph.contains("    \"knuthwebsite\" -> \"latexcompanion\" [label=\"commentary on\"];"));
+    }
+}
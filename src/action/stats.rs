@@ -0,0 +1,102 @@
+use serde_json::{json, Map, Value};
+
+use crate::database::SqliteBibDB;
+use crate::formatter::ToString;
+
+/// One part of the report, a count for each name
+struct Section {
+    /// key in the JSON object and first column of the CSV
+    id: &'static str,
+    title: &'static str,
+    rows: Vec<(String, i64)>,
+}
+
+fn sections(conn: &SqliteBibDB, top: i64) -> Vec<Section> {
+    let fail = "Failed to read the library!";
+    let keywords = conn.keyword_counts().expect(fail);
+    let summary = vec![
+        ("entries".to_owned(), conn.count_items().expect(fail)),
+        ("keywords".to_owned(), keywords.iter().filter(|x| x.1 > 0).count() as i64),
+        ("without pdf".to_owned(), conn.citations_without_pdf().expect(fail).len() as i64),
+        ("without doi".to_owned(), conn.count_without_doi().expect(fail)),
+        ("without date added".to_owned(), conn.undated_items().expect(fail).len() as i64),
+    ];
+    let authors = conn.top_authors(top).expect(fail).into_iter().map(|(person, count)| (person.to_str(), count))
+        .collect();
+    let coauthors = conn.top_coauthors(top).expect(fail).into_iter()
+        .map(|(first, second, count)| (format!("{} & {}", first.to_str(), second.to_str()), count)).collect();
+    vec![
+        Section{id: "summary", title: "Library", rows: summary},
+        Section{id: "years", title: "Entries by year", rows: conn.count_by_year().expect(fail)},
+        Section{id: "types", title: "Entries by type", rows: conn.count_by_type().expect(fail)},
+        Section{id: "journals", title: "Top journals", rows: conn.top_journals(top).expect(fail)},
+        Section{id: "authors", title: "Top authors", rows: authors},
+        Section{id: "coauthors", title: "Top co-author pairs", rows: coauthors},
+        Section{id: "keywords", title: "Top keywords", rows: keywords.into_iter().filter(|x| x.1 > 0)
+            .take(top as usize).collect()},
+        Section{id: "growth", title: "Entries by the end of each month", rows: conn.growth().expect(fail)},
+    ]
+}
+
+fn table(sections: &[Section]) -> String {
+    sections.iter().filter(|x| !x.rows.is_empty()).map(|section| {
+        let mut lines = vec![format!("{}:", section.title)];
+        lines.extend(section.rows.iter().map(|(name, count)| format!("{:>5}  {}", count, name)));
+        lines.join("\n")
+    }).collect::<Vec<String>>().join("\n\n")
+}
+
+fn json(sections: &[Section]) -> String {
+    let mut output = Map::new();
+    for section in sections.iter() {
+        output.insert(section.id.to_owned(), Value::Array(section.rows.iter()
+            .map(|(name, count)| json!({"name": name, "count": count})).collect()));
+    }
+    serde_json::to_string_pretty(&Value::Object(output)).unwrap()
+}
+
+/// a CSV field, quoted if it has a comma, quote or line break
+fn csv_field(input: &str) -> String {
+    if input.contains(&[',', '"', '\n'][..]) { format!("\"{}\"", input.replace('"', "\"\"")) }
+    else { input.to_owned() }
+}
+
+fn csv(sections: &[Section]) -> String {
+    let mut lines = vec!["section,name,count".to_owned()];
+    for section in sections.iter() {
+        lines.extend(section.rows.iter().map(|(name, count)| format!("{},{},{}", section.id, csv_field(name), count)));
+    }
+    lines.join("\n")
+}
+
+/// Summary of the library: entries by year and type, the top journals, authors, co-author pairs and
+/// keywords, entries without pdf, DOI or date added, and how the library grew, as a plain table,
+/// JSON or CSV
+pub fn stats(conn: &SqliteBibDB, top: i64, format: &str) -> String {
+    let sections = sections(conn, top);
+    match format {
+        "table" => table(&sections),
+        "json" => json(&sections),
+        "csv" => csv(&sections),
+        _ => format!("Unknown format {}, use table, json or csv", format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        let sections = vec![
+            Section{id: "summary", title: "Library", rows: vec![("entries".to_owned(), 3)]},
+            Section{id: "journals", title: "Top journals", rows: vec![("Nature, London".to_owned(), 2)]},
+            Section{id: "growth", title: "Entries by the end of each month", rows: vec![]},
+        ];
+        assert_eq!(table(&sections), "Library:\n    3  entries\n\nTop journals:\n    2  Nature, London");
+        assert_eq!(csv(&sections), "section,name,count\nsummary,entries,3\njournals,\"Nature, London\",2");
+        let parsed: Value = serde_json::from_str(&json(&sections)).unwrap();
+        assert_eq!(parsed["journals"][0]["count"], json!(2));
+        assert_eq!(parsed["growth"], json!([]));
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use inquire::Select;
+use crate::database::{SqliteBibDB, BibDataBase, history::entry_diff, journal::Journal, operation::Operation,
+                      sync::LogRecord};
+use crate::model::Entry;
+use super::{journal_or_add, save_update};
+
+/// how a value that differs between the libraries is merged
+#[derive(Debug, PartialEq)]
+enum Merge { Local, Other, Conflict }
+
+/// Three-way merge of a value with its value in the common past. Without a known common value,
+/// a value only one library has is taken.
+fn merge_value(base: Option<&Option<String>>, local: &Option<String>, other: &Option<String>) -> Merge {
+    match base {
+        Some(base) if base == local => Merge::Other,
+        Some(base) if base == other => Merge::Local,
+        Some(_) => Merge::Conflict,
+        None if other.is_none() => Merge::Local,
+        None if local.is_none() => Merge::Other,
+        None => Merge::Conflict,
+    }
+}
+
+/// The history of an entry in both libraries, split into the common past and the changes each
+/// library made since they were copied apart
+struct Histories<'a> {
+    common: Vec<&'a LogRecord>,
+    local: Vec<&'a LogRecord>,
+    other: Vec<&'a LogRecord>,
+}
+
+impl<'a> Histories<'a> {
+    fn new(local: &[&'a LogRecord], other: &[&'a LogRecord]) -> Self {
+        let local_set: HashSet<&LogRecord> = local.iter().cloned().collect();
+        let other_set: HashSet<&LogRecord> = other.iter().cloned().collect();
+        Histories{
+            common: local.iter().filter(|x| other_set.contains(*x)).cloned().collect(),
+            local: local.iter().filter(|x| !other_set.contains(*x)).cloned().collect(),
+            other: other.iter().filter(|x| !local_set.contains(*x)).cloned().collect(),
+        }
+    }
+
+    /// whether the entry is from the common past, and not added to both libraries separately
+    fn shared(&self) -> bool {
+        self.common.iter().any(|x| x.operation == "add") ||
+            !self.local.iter().chain(self.other.iter()).any(|x| x.operation == "add")
+    }
+
+    /// value of an edited field in the common past, None if not known
+    fn base(&self, field: &str) -> Option<Option<String>> {
+        let is_edit = |x: &&&LogRecord| x.operation == "edit" && x.field.as_deref() == Some(field);
+        if let Some(last) = self.common.iter().rev().find(is_edit) { return Some(last.new_value.clone()) }
+        if !self.shared() { return None }
+        // not edited in the common past, the first edit since has the value it had
+        self.local.iter().chain(self.other.iter()).filter(is_edit).min_by_key(|x| x.changed_at)
+            .map(|x| x.old_value.clone())
+    }
+}
+
+fn keyword_list(value: &Option<String>) -> Vec<&str> {
+    value.as_deref().map(|x| x.split(", ").collect()).unwrap_or_default()
+}
+
+/// whether the changes removed the keyword
+fn removed_keyword(changes: &[&LogRecord], keyword: &str) -> bool {
+    changes.iter().any(|x| matches!(x.field.as_deref(), Some("keyword") | Some("keywords")) &&
+        keyword_list(&x.old_value).contains(&keyword) && !keyword_list(&x.new_value).contains(&keyword))
+}
+
+/// Keywords are merged as a set, a keyword only one library has stays unless the other library
+/// removed it
+fn merge_keywords(histories: &Histories, local: &HashSet<String>, other: &HashSet<String>) -> HashSet<String> {
+    let mut merged = local.clone();
+    for keyword in other.difference(local) {
+        if !removed_keyword(&histories.local, keyword) { merged.insert(keyword.clone()); }
+    }
+    for keyword in local.difference(other) {
+        if removed_keyword(&histories.other, keyword) { merged.remove(keyword); }
+    }
+    merged
+}
+
+/// take the value of a field of entry_diff from the other version
+fn take_field(entry: &mut Entry, other: &Entry, field: &str) {
+    match field {
+        "entry_type" => entry.entry_type = other.entry_type.clone(),
+        "title" => entry.title = other.title.clone(),
+        "booktitle" => entry.booktitle = other.booktitle.clone(),
+        "year" => entry.year = other.year,
+        "month" => entry.month = other.month,
+        "chapter" => entry.chapter = other.chapter,
+        "edition" => entry.edition = other.edition,
+        "volume" => entry.volume = other.volume,
+        "number" => entry.number = other.number,
+        "pages" => entry.pages = other.pages.clone(),
+        "journal" => entry.journal = other.journal.clone(),
+        "author" => entry.authors = other.authors.clone(),
+        "editor" => entry.editors = other.editors.clone(),
+        "keywords" => entry.keywords = other.keywords.clone(),
+        _ => match other.extra_fields.get(field) {
+            Some(value) => { entry.extra_fields.insert(field.to_owned(), value.clone()); },
+            None => { entry.extra_fields.remove(field); },
+        },
+    }
+}
+
+/// ask which version of a field changed in both libraries to keep, None to decide later
+fn ask(citation: &str, field: &str, local: &Option<String>, other: &Option<String>) -> Option<Merge> {
+    let show = |x: &Option<String>| x.clone().unwrap_or_else(|| "(empty)".to_owned());
+    let options = vec![format!("keep here: {}", show(local)), format!("take other: {}", show(other)),
+                       "decide later".to_owned()];
+    let answer = Select::new(&format!("{}: {} changed in both libraries", citation, field), options.clone())
+        .prompt().ok()?;
+    match options.iter().position(|x| *x == answer) {
+        Some(0) => Some(Merge::Local),
+        Some(1) => Some(Merge::Other),
+        _ => None,
+    }
+}
+
+/// the journal of the entry is copied from the other library with its abbreviations
+pub(super) fn copy_journal(conn: &SqliteBibDB, other: &SqliteBibDB, entry: &Entry) {
+    let name = match entry.journal { Some(ref x) => x, None => return };
+    if conn.journal_id(name).expect("Failed to read journals!").is_some() { return }
+    if let Some(journal) = other.journal_by_name(name).expect("Failed to read journals!") {
+        conn.add_journal(Journal{id: None, ..journal}).expect("Failed to add journal!");
+    }
+}
+
+struct Merger<'a> {
+    conn: &'a SqliteBibDB,
+    other: &'a SqliteBibDB,
+    prompt: bool,
+    output: Vec<String>,
+    conflicts: Vec<String>,
+}
+
+impl<'a> Merger<'a> {
+    /// Merge the fields, keywords and files of an entry both libraries have. The changes of the
+    /// other library become part of the local history, except edits of conflicting fields.
+    fn merge_entry(&mut self, citation: &str, other_citation: &str, histories: &Histories) {
+        let local = self.conn.get_item(citation).unwrap_or_else(|_| panic!("Cannot find entry {}", citation));
+        let theirs = self.other.get_item(other_citation)
+            .unwrap_or_else(|_| panic!("Cannot find entry {} in the other library", other_citation));
+        let mut merged = local.clone();
+        let mut unresolved: Vec<String> = Vec::new();
+        for (field, local_value, other_value) in entry_diff(&local, &theirs) {
+            if field == "keywords" { continue }
+            let resolution = match merge_value(histories.base(&field).as_ref(), &local_value, &other_value) {
+                Merge::Conflict if self.prompt => ask(citation, &field, &local_value, &other_value)
+                    .unwrap_or(Merge::Conflict),
+                x => x,
+            };
+            match resolution {
+                Merge::Local => (),
+                Merge::Other => take_field(&mut merged, &theirs, &field),
+                Merge::Conflict => {
+                    self.conflicts.push(format!("{}: {} is {} here and {} in the other library", citation, field,
+                        local_value.as_deref().unwrap_or("empty"), other_value.as_deref().unwrap_or("empty")));
+                    unresolved.push(field);
+                },
+            }
+        }
+        merged.keywords = merge_keywords(histories, &local.keywords, &theirs.keywords);
+        let changes = entry_diff(&local, &merged);
+        if !changes.is_empty() {
+            copy_journal(self.conn, self.other, &merged);
+            save_update(self.conn, &merged);
+            self.output.push(format!("{}: merged {}", citation,
+                changes.iter().map(|x| x.0.as_str()).collect::<Vec<&str>>().join(", ")));
+        }
+        self.merge_files(citation, other_citation, histories);
+        let imported: Vec<&LogRecord> = histories.other.iter().filter(|x| x.operation != "edit" ||
+            !unresolved.iter().any(|field| x.field.as_ref() == Some(field))).cloned().collect();
+        self.conn.import_history(citation, &imported).expect("Failed to update history!");
+    }
+
+    /// files attached in the other library are added, files the other library removed are dropped
+    fn merge_files(&mut self, citation: &str, other_citation: &str, histories: &Histories) {
+        let local = self.conn.get_attachments(citation).expect("Find file record in db fail!");
+        let theirs = self.other.get_attachments(other_citation).expect("Find file record in db fail!");
+        let removed = |changes: &[&LogRecord], name: &str, object_type: &str| changes.iter().any(|x|
+            x.operation == "remove_file" && x.field.as_deref() == Some(object_type) &&
+            x.old_value.as_deref() == Some(name));
+        let same = |x: &crate::database::attachment::Attachment, y: &crate::database::attachment::Attachment|
+            (x.name == y.name && x.object_type == y.object_type) || (x.hash.is_some() && x.hash == y.hash);
+        for attachment in theirs.iter() {
+            if local.iter().any(|x| same(x, attachment)) ||
+                removed(&histories.local, &attachment.name, &attachment.object_type) { continue }
+            self.conn.add_attachment(citation, attachment).expect("Failed to add file record!");
+            self.output.push(format!("{}: added {} {}", citation, attachment.role, attachment.name));
+        }
+        for attachment in local.iter() {
+            if theirs.iter().any(|x| same(x, attachment)) ||
+                !removed(&histories.other, &attachment.name, &attachment.object_type) { continue }
+            self.conn.remove_file_record(citation, &attachment.name, &attachment.object_type)
+                .expect("Failed to remove file record!");
+            self.output.push(format!("{}: dropped {} {}", citation, attachment.role, attachment.name));
+        }
+    }
+
+    /// add an entry of the other library with its files and history
+    fn add_entry(&mut self, citation: &str, histories: &Histories) {
+        let entry = self.other.get_item(citation)
+            .unwrap_or_else(|_| panic!("Cannot find entry {} in the other library", citation));
+        if let Some(duplicate) = self.conn.find_duplicate(&entry).expect("Search Fail!") {
+            self.conflicts.push(format!("{}: new in the other library, but the same paper as {} here",
+                                        citation, duplicate));
+            return
+        }
+        copy_journal(self.conn, self.other, &entry);
+        let journal_id = journal_or_add(self.conn, &entry);
+        self.conn.add_item(&entry, journal_id).unwrap_or_else(|_| panic!("Failed to add entry {}!", citation));
+        for attachment in self.other.get_attachments(citation).expect("Find file record in db fail!").iter() {
+            self.conn.add_attachment(citation, attachment).expect("Failed to add file record!");
+        }
+        self.conn.import_history(citation, &histories.other).expect("Failed to update history!");
+        self.output.push(format!("{}: added", citation));
+    }
+
+    fn trash_entry(&mut self, citation: &str) {
+        let trash_id = self.conn.trash_item(citation)
+            .unwrap_or_else(|_| panic!("Failed to delete existing entry {}!", citation));
+        self.conn.record_operation(&Operation::Delete{citation: citation.to_owned(), trash_id})
+            .expect("Failed to update operation journal!");
+        self.output.push(format!("{}: deleted, as in the other library", citation));
+    }
+
+    /// whether to keep an entry one library deleted and the other changed
+    fn keep_changed(&mut self, citation: &str, message: &str) -> bool {
+        if self.prompt {
+            let options = vec!["keep the entry".to_owned(), "delete it".to_owned()];
+            if let Ok(answer) = Select::new(&format!("{}: {}", citation, message), options.clone()).prompt() {
+                return answer == options[0]
+            }
+        }
+        self.conflicts.push(format!("{}: {}, kept", citation, message));
+        true
+    }
+}
+
+/// Citations renamed by one library since the copy, as (before, after). The entry has the old
+/// citation in the original library and only there.
+fn renames(log: &[(String, LogRecord)], renamed: &HashSet<String>, original: &HashSet<String>)
+        -> Vec<(String, String)> {
+    log.iter().filter(|(item, record)| record.operation == "rename" && renamed.contains(item) &&
+                                       !original.contains(item))
+        .filter_map(|(item, record)| record.old_value.as_ref()
+            .filter(|x| original.contains(*x) && !renamed.contains(*x))
+            .map(|x| (x.clone(), item.clone())))
+        .collect()
+}
+
+fn by_item(log: &[(String, LogRecord)]) -> HashMap<&str, Vec<&LogRecord>> {
+    let mut output: HashMap<&str, Vec<&LogRecord>> = HashMap::new();
+    for (item, record) in log.iter() { output.entry(item.as_str()).or_default().push(record); }
+    output
+}
+
+/// Three-way merge of another copy of the library, like a copy left by a file sync conflict, into
+/// this one. The history log tells the common past of the two copies from the changes each made
+/// since. Changes made in only one copy are merged, fields changed in both are asked for, or with
+/// prompt false reported and left for a later sync. The other library is not changed.
+pub fn sync(conn: &SqliteBibDB, path: &Path, prompt: bool) -> String {
+    if !path.is_file() { return format!("Cannot find library {}", path.to_string_lossy()) }
+    let other = SqliteBibDB::new(Some(path.to_path_buf()));
+    let other_log = other.history_log().expect("Failed to read history!");
+    let other_citations: HashSet<String> = other.citations().expect("Search Fail!").into_iter().collect();
+    let mut state = Merger{conn, other: &other, prompt, output: Vec::new(), conflicts: Vec::new()};
+    // renames in the other library are followed, renames here pair the entry with its old citation
+    let local_citations: HashSet<String> = conn.citations().expect("Search Fail!").into_iter().collect();
+    for (from, to) in renames(&other_log, &other_citations, &local_citations) {
+        conn.rename(&from, &to).unwrap_or_else(|_| panic!("Failed to rename {} to {}", from, to));
+        conn.record_operation(&Operation::Rename{from: from.clone(), to: to.clone()})
+            .expect("Failed to update operation journal!");
+        state.output.push(format!("{}: renamed to {}, as in the other library", from, to));
+    }
+    let local_log = conn.history_log().expect("Failed to read history!");
+    let local_citations: HashSet<String> = conn.citations().expect("Search Fail!").into_iter().collect();
+    let mut pairs: HashMap<String, String> = renames(&local_log, &local_citations, &other_citations).into_iter()
+        .map(|(from, to)| (to, from)).collect();
+    for citation in local_citations.intersection(&other_citations) { pairs.insert(citation.clone(), citation.clone()); }
+    let (local_items, other_items) = (by_item(&local_log), by_item(&other_log));
+    let empty = Vec::new();
+    let histories = |local: &str, theirs: &str| Histories::new(local_items.get(local).unwrap_or(&empty),
+                                                               other_items.get(theirs).unwrap_or(&empty));
+    let deleted = |changes: &[&LogRecord]| changes.iter().any(|x| x.operation == "delete");
+    let edited = |changes: &[&LogRecord]| changes.iter().any(|x| x.operation != "add" && x.operation != "delete");
+    let mut citations: Vec<&String> = local_citations.union(&other_citations).collect();
+    citations.sort();
+    let paired: HashSet<&String> = pairs.values().collect();
+    for citation in citations {
+        if let Some(other_citation) = pairs.get(citation) {
+            state.merge_entry(citation, other_citation, &histories(citation, other_citation));
+        } else if local_citations.contains(citation) {
+            let history = histories(citation, citation);
+            if !deleted(&history.other) { continue }
+            if !edited(&history.local) || !state.keep_changed(citation, "deleted in the other library but changed here") {
+                state.trash_entry(citation);
+            }
+        } else if !paired.contains(citation) {
+            let history = histories(citation, citation);
+            if !deleted(&history.local) {
+                state.add_entry(citation, &history);
+            } else if edited(&history.other) && state.keep_changed(citation, "deleted here but changed in the other library") {
+                state.add_entry(citation, &history);
+            }
+        }
+    }
+    let Merger{mut output, conflicts, ..} = state;
+    if output.is_empty() && conflicts.is_empty() { return "Libraries are in sync.".to_owned() }
+    if !conflicts.is_empty() {
+        output.push(format!("Conflicts ({}), resolve them and sync again:", conflicts.len()));
+        output.extend(conflicts.into_iter().map(|x| format!("\t{}", x)));
+    }
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(operation: &str, field: &str, old_value: Option<&str>, new_value: Option<&str>, changed_at: i64)
+            -> LogRecord {
+        LogRecord{operation: operation.to_owned(), field: Some(field.to_owned()).filter(|x| !x.is_empty()),
+                  old_value: old_value.map(|x| x.to_owned()), new_value: new_value.map(|x| x.to_owned()),
+                  changed_at, user: None}
+    }
+
+    #[test]
+    fn test_merge_value() {
+        let (a, b) = (Some("a".to_owned()), Some("b".to_owned()));
+        assert_eq!(merge_value(Some(&a), &a, &b), Merge::Other);
+        assert_eq!(merge_value(Some(&a), &b, &a), Merge::Local);
+        assert_eq!(merge_value(Some(&None), &a, &b), Merge::Conflict);
+        assert_eq!(merge_value(None, &a, &None), Merge::Local);
+        assert_eq!(merge_value(None, &a, &b), Merge::Conflict);
+    }
+
+    #[test]
+    fn test_histories() {
+        let add = record("add", "", None, Some("Title"), 1);
+        let common_edit = record("edit", "pages", Some("1-2"), Some("1-3"), 2);
+        let local_edit = record("edit", "volume", Some("3"), Some("4"), 3);
+        let other_edit = record("edit", "pages", Some("1-3"), Some("1-4"), 4);
+        let other_keyword = record("del_keyword", "keyword", Some("cortex"), None, 5);
+        let histories = Histories::new(&[&add, &common_edit, &local_edit],
+                                       &[&add, &common_edit, &other_edit, &other_keyword]);
+        assert!(histories.shared());
+        assert_eq!(histories.base("pages"), Some(Some("1-3".to_owned())));
+        assert_eq!(histories.base("volume"), Some(Some("3".to_owned())));
+        assert_eq!(histories.base("month"), None);
+        let local: HashSet<String> = ["cortex", "v1"].iter().map(|x| (*x).to_owned()).collect();
+        let other: HashSet<String> = ["review"].iter().map(|x| (*x).to_owned()).collect();
+        let merged = merge_keywords(&histories, &local, &other);
+        assert_eq!(merged, ["v1", "review"].iter().map(|x| (*x).to_owned()).collect::<HashSet<String>>());
+        let separate = Histories::new(&[&add], &[&record("add", "", None, Some("Title"), 6)]);
+        assert!(!separate.shared());
+        assert_eq!(separate.base("pages"), None);
+    }
+}
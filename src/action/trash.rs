@@ -0,0 +1,112 @@
+use std::fs::{create_dir_all, rename, remove_file};
+use std::path::Path;
+
+use crate::config::CONFIG;
+use crate::database::{SqliteBibDB, BibDataBase};
+use crate::file::{File, BibFile, handler};
+use crate::formatter::ToString;
+use crate::util::{now, parse_age};
+
+/// Name in the trash folder of a file kept under a subfolder like 2005/sur-patterning.pdf, with
+/// the slashes escaped so that it can be put back in the same place
+pub(super) fn escape_name(prefix: &str, relative: &str) -> String {
+    format!("{}-{}", prefix, relative.replace('%', "%25").replace('/', "%2F"))
+}
+
+/// Prefix of the names of the files trashed with an entry, with the name of the library as
+/// libraries can share the trash folder
+fn trash_prefix(library: Option<&str>, trash_id: i64) -> String {
+    match library {
+        Some(library) => format!("{}@{}", library, trash_id),
+        None => trash_id.to_string(),
+    }
+}
+
+fn unescape_name(prefix: &str, stored_name: &str) -> String {
+    let prefix = format!("{}-", prefix);
+    stored_name.strip_prefix(&prefix).unwrap_or(stored_name).replace("%2F", "/").replace("%25", "%")
+}
+
+/// move the file of an entry that has just been trashed into the trash folder
+pub fn trash_file(conn: &SqliteBibDB, trash_id: i64, name: &str, file_type: &str) {
+    let file = File::new(name, file_type);
+    if !file.path().exists() { return }
+    let relative = file.path().strip_prefix(&handler(file_type).folder)
+        .unwrap_or_else(|_| Path::new(file.path().file_name().unwrap())).to_string_lossy();
+    let stored_name = escape_name(&trash_prefix(CONFIG.library.as_deref(), trash_id), &relative);
+    rename(file.path(), CONFIG.trash.join(&stored_name)).unwrap_or_else(
+        |_| panic!("Failed to move file {} to trash", file.path().to_string_lossy()));
+    conn.set_trash_file(trash_id, name, &stored_name).expect("Failed to record trashed file!");
+}
+
+pub fn list(conn: &SqliteBibDB) -> String {
+    let trashed = conn.list_trash().expect("Failed to read trash!");
+    if trashed.is_empty() { return "Trash is empty.".to_owned() }
+    let current = now();
+    trashed.iter().map(|x| format!("{:>4}  {:>4}d ago  {}: {}", x.trash_id, (current - x.deleted_at) / 86400,
+                                   x.entry.citation, x.entry.to_str()))
+        .collect::<Vec<String>>().join("\n")
+}
+
+/// put an entry and its files back from the trash
+/// Returns:
+///     message on success, and the reason if the entry cannot be restored
+pub fn restore(conn: &SqliteBibDB, trash_id: i64) -> Result<String, String> {
+    let trashed = match conn.get_trash(trash_id) {
+        Ok(x) => x,
+        Err(_) => return Err(format!("Cannot find entry {} in trash", trash_id)),
+    };
+    if conn.get_item(&trashed.entry.citation).is_ok() {
+        return Err(format!("Cannot restore, citation {} is already in use", trashed.entry.citation));
+    }
+    let entry = conn.restore_trash(trash_id).unwrap_or_else(|_| panic!("Failed to restore entry {}!", trash_id));
+    for file in trashed.files.iter() {
+        if let Some(ref stored_name) = file.stored_name {
+            let target = handler(&file.object_type).folder.join(
+                unescape_name(&trash_prefix(CONFIG.library.as_deref(), trash_id), stored_name));
+            if let Some(folder) = target.parent() {
+                create_dir_all(folder)
+                    .unwrap_or_else(|_| panic!("Failed to create folder {}", folder.to_string_lossy()));
+            }
+            rename(CONFIG.trash.join(stored_name), &target).unwrap_or_else(
+                |_| panic!("Failed to move {} back from trash", stored_name));
+        }
+    }
+    Ok(format!("Restored {}: {}", entry.citation, entry.to_str()))
+}
+
+/// permanently remove entries from the trash, only those older than the given age if any
+pub fn empty(conn: &SqliteBibDB, older_than: Option<&str>) -> String {
+    let cutoff = match older_than {
+        Some(age) => match parse_age(age) {
+            Some(seconds) => now() - seconds,
+            None => return format!("Cannot understand age {}, use something like 30d", age),
+        },
+        None => i64::MAX,
+    };
+    let trash_ids = conn.trash_before(cutoff).expect("Failed to read trash!");
+    for trash_id in trash_ids.iter() {
+        let trashed = conn.get_trash(*trash_id).expect("Failed to read trash!");
+        for stored_name in trashed.files.iter().filter_map(|x| x.stored_name.as_ref()) {
+            let path = CONFIG.trash.join(stored_name);
+            if path.exists() {
+                remove_file(&path).unwrap_or_else(|_| panic!("Failed to remove {}", path.to_string_lossy()));
+            }
+        }
+        conn.purge_trash(*trash_id).unwrap_or_else(|_| panic!("Failed to remove entry {} from trash", trash_id));
+    }
+    format!("Removed {} entries from trash", trash_ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_trash_name() {
+        assert_eq!(escape_name("3", "2005/sur-patterning.pdf"), "3-2005%2Fsur-patterning.pdf");
+        assert_eq!(unescape_name("3", &escape_name("3", "2005/100%/sur.pdf")), "2005/100%/sur.pdf");
+        assert_eq!(unescape_name("3", "3-sur2005.pdf"), "sur2005.pdf");
+        assert_eq!(escape_name(&trash_prefix(Some("lab"), 3), "sur2005.pdf"), "lab@3-sur2005.pdf");
+        assert_ne!(escape_name(&trash_prefix(None, 3), "sur2005.pdf"), "lab@3-sur2005.pdf");
+    }
+}
@@ -0,0 +1,94 @@
+use crate::database::{SqliteBibDB, BibDataBase, operation::Operation};
+use super::trash;
+
+fn revert_keywords(conn: &SqliteBibDB, citation: &str, added: &[String], deleted: &[String]) {
+    if !added.is_empty() { conn.del_keywords(citation, added)
+        .unwrap_or_else(|_| panic!("Failed to delete keywords from {}", citation)); }
+    if !deleted.is_empty() { conn.add_keywords(citation, deleted)
+        .unwrap_or_else(|_| panic!("Failed to add keywords to {}", citation)); }
+}
+
+/// Revert the most recent keyword change, update, rename or delete. A keyword merge cannot be
+/// reverted, it is dropped from the journal with a warning so the next undo reaches the change before it.
+pub fn undo(conn: &SqliteBibDB) -> String {
+    let (id, operation) = match conn.last_operation().expect("Failed to read operation journal!") {
+        Some(x) => x,
+        None => return "Nothing to undo.".to_owned(),
+    };
+    let message = match operation {
+        Operation::Keywords{ref citation, ref added, ref deleted} => {
+            revert_keywords(conn, citation, added, deleted);
+            format!("Reverted keyword change on {}", citation)
+        },
+        Operation::Tag{ref changes} => {
+            for change in changes.iter().rev() {
+                if let Operation::Keywords{citation, added, deleted} = change {
+                    revert_keywords(conn, citation, added, deleted);
+                }
+            }
+            format!("Reverted keyword change on {} entries", changes.len())
+        },
+        Operation::RenameKeyword{ref from, ref to} => {
+            conn.rename_keyword(to, from)
+                .unwrap_or_else(|_| panic!("Failed to rename keyword {} back to {}", to, from));
+            format!("Renamed keyword {} back to {}", to, from)
+        },
+        Operation::MergeKeywords{ref target, ref sources} => format!(
+            "Cannot undo merging {} into {}, they are aliases of {} now. Skipped, undo again for the change before it",
+            sources.join(", "), target, target),
+        Operation::Update{ref citation, trash_id} => {
+            if conn.get_trash(trash_id).is_err() {
+                return format!("The old version of {} is no longer in the trash", citation);
+            }
+            let files = conn.get_attachments(citation).expect("Find file record in db fail!");
+            conn.delete(citation).unwrap_or_else(|_| panic!("Failed to delete existing entry {}!", citation));
+            conn.restore_trash(trash_id).unwrap_or_else(|_| panic!("Failed to restore entry {}!", citation));
+            for attachment in files.iter() {
+                conn.add_attachment(citation, attachment).expect("Failed to add file record!");
+            }
+            format!("Reverted update of {}", citation)
+        },
+        Operation::Rename{ref from, ref to} => {
+            conn.rename(to, from).unwrap_or_else(|_| panic!("Failed to rename {} back to {}", to, from));
+            format!("Renamed {} back to {}", to, from)
+        },
+        Operation::Delete{trash_id, ..} => match trash::restore(conn, trash_id) {
+            Ok(x) => x,
+            Err(x) => return x,
+        },
+    };
+    conn.forget_operation(id).expect("Failed to update operation journal!");
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+    use crate::action::keywords;
+    use crate::reader::bibtex::read_entries;
+    use crate::str_hashset;
+    use super::*;
+
+    #[test]
+    fn test_undo_keywords() {
+        let conn = SqliteBibDB::in_memory();
+        for entry in read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib")).iter() {
+            conn.add_item(entry, None).unwrap();
+        }
+        let before = conn.get_item("latexcompanion").unwrap().keywords;
+        keywords::tag(&conn, vec!["goossens".to_owned()], vec![], None, str_hashset!("cortex"), str_hashset!());
+        assert!(conn.get_item("latexcompanion").unwrap().keywords.contains("cortex"));
+        keywords::rename(&conn, "cortex", "visual cortex");
+        assert!(conn.get_item("latexcompanion").unwrap().keywords.contains("visual cortex"));
+        assert_eq!(undo(&conn), "Renamed keyword visual cortex back to cortex");
+        assert_eq!(undo(&conn), "Reverted keyword change on 1 entries");
+        assert_eq!(conn.get_item("latexcompanion").unwrap().keywords, before);
+        keywords::tag(&conn, vec!["goossens".to_owned()], vec![], None, str_hashset!("cortex"), str_hashset!());
+        keywords::merge(&conn, "cortex", &["v1".to_owned()]);
+        assert!(undo(&conn).starts_with("Cannot undo merging v1 into cortex"));
+        assert_eq!(undo(&conn), "Reverted keyword change on 1 entries");
+        assert_eq!(conn.get_item("latexcompanion").unwrap().keywords, before);
+        assert_eq!(undo(&conn), "Nothing to undo.");
+    }
+}
@@ -1,10 +1,12 @@
+use std::collections::BTreeMap;
+use std::env;
 use std::fs::{File, copy, create_dir};
 use std::io::{stdin, Write, Error as IOError, Read};
 use std::path::PathBuf;
 
 use serde_derive::Deserialize;
 use dirs::{home_dir, config_dir};
-use rusqlite::Connection;
+use rusqlite::{Connection, NO_PARAMS};
 use lazy_static::lazy_static;
 use termion::event::Key;
 use termion::input::TermRead;
@@ -15,6 +17,26 @@ pub struct FileHandler {
     pub folder: PathBuf,
     pub extension: Vec<String>,
     pub opener: String,
+    /// name of new files inside the folder, like {year}/{auth}-{shorttitle}, by citation if None
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// A named library in [libraries.NAME], with its own database and the folders it doesn't share
+/// with the main library
+#[derive(Deserialize, Clone)]
+pub struct Library {
+    pub database: PathBuf,
+    #[serde(default)]
+    pub pdf: Option<FileHandler>,
+    #[serde(default)]
+    pub comment: Option<FileHandler>,
+    #[serde(default)]
+    pub attachment: Option<FileHandler>,
+    #[serde(default)]
+    pub trash: Option<PathBuf>,
+    #[serde(default)]
+    pub content_addressed: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -25,29 +47,97 @@ pub struct Config {
     pub comment: FileHandler,
     pub temp_pdf: FileHandler,
     pub temp_bib: FileHandler,
+    /// supplements, datasets, slides and other files that are not pdf, kept under their full name
+    #[serde(default = "default_attachment")]
+    pub attachment: FileHandler,
+    #[serde(default = "default_trash")]
+    pub trash: PathBuf,
+    /// write non-ASCII letters as they are in bibtex output, instead of LaTeX commands
+    #[serde(default)]
+    pub bibtex_utf8: bool,
+    /// JSON-lines dump of CrossRef or OpenAlex works used by bibrs enrich
+    #[serde(default)]
+    pub metadata_dump: Option<PathBuf>,
+    /// store new pdf and attachment files under the SHA-256 of their content instead of the citation
+    #[serde(default)]
+    pub content_addressed: bool,
+    /// named libraries, chosen with --library or BIBRS_LIBRARY
+    #[serde(default)]
+    pub libraries: BTreeMap<String, Library>,
+    /// name of the library in use, None for the main library
+    #[serde(skip)]
+    pub library: Option<String>,
+}
+
+fn default_trash() -> PathBuf { PathBuf::from("Sync/paper/trash/") }
+
+fn default_attachment() -> FileHandler {
+    FileHandler{folder: PathBuf::from("Sync/paper/attachment/"), extension: Vec::new(), opener: "xdg-open".to_owned(),
+                template: None}
 }
 
 lazy_static!{
     pub static ref DEFAULT_CONFIG: PathBuf = config_dir().unwrap().join("bibrs/bibrs.toml");
 }
 
+/// the config file given by BIBRS_CONFIG, or the one in the config folder
+pub fn config_path() -> PathBuf {
+    env::var_os("BIBRS_CONFIG").map(PathBuf::from).unwrap_or_else(|| DEFAULT_CONFIG.to_path_buf())
+}
+
+/// the library given by BIBRS_LIBRARY, the main library if not set
+pub fn library_name() -> Option<String> { env::var("BIBRS_LIBRARY").ok().filter(|x| !x.is_empty()) }
+
 impl Config {
-    pub fn new(config_path: Option<PathBuf>) -> Config {
+    pub fn new(config_path: Option<PathBuf>) -> Config { Config::load(config_path, None) }
+
+    /// Read the config file, with the database and folders of the named library in place of the
+    /// main ones
+    pub fn load(config_path: Option<PathBuf>, library: Option<&str>) -> Config {
         let mut config_file;
-        let config_path = config_path.unwrap_or_else(|| DEFAULT_CONFIG.to_path_buf());
+        let config_path = config_path.unwrap_or_else(self::config_path);
         config_file = File::open(&config_path).unwrap_or_else(
-            |_| panic!("Specified config file not found at {}!", config_path.to_string_lossy()));
+            |_| panic!("Config file not found at {}! Run bibrs init, or choose one with --config or BIBRS_CONFIG",
+                       config_path.to_string_lossy()));
         let mut config_str = String::new();
         config_file.read_to_string(&mut config_str).expect("Failed to read config");
-        let mut output: Config = toml::from_str(&config_str).unwrap();
+        let mut output: Config = toml::from_str(&config_str)
+            .unwrap_or_else(|err| panic!("Cannot read config file {}: {}", config_path.to_string_lossy(), err));
+        if let Some(name) = library {
+            let library = output.libraries.get(name).cloned().unwrap_or_else(|| panic!(
+                "No library {} in {}, the libraries are: {}", name, config_path.to_string_lossy(),
+                output.libraries.keys().cloned().collect::<Vec<String>>().join(", ")));
+            output.database = library.database;
+            if let Some(pdf) = library.pdf { output.pdf = pdf; }
+            if let Some(comment) = library.comment { output.comment = comment; }
+            if let Some(attachment) = library.attachment { output.attachment = attachment; }
+            if let Some(trash) = library.trash { output.trash = trash; }
+            if let Some(content_addressed) = library.content_addressed { output.content_addressed = content_addressed; }
+            output.library = Some(name.to_owned());
+        }
         output.database = home_dir().unwrap().join(&output.database);
         output.journal_db = home_dir().unwrap().join(&output.journal_db);
         output.pdf.folder = home_dir().unwrap().join(&output.pdf.folder);
         output.comment.folder = home_dir().unwrap().join(&output.comment.folder);
         output.temp_pdf.folder = home_dir().unwrap().join(&output.temp_pdf.folder);
         output.temp_bib.folder = home_dir().unwrap().join(&output.temp_pdf.folder);
+        output.attachment.folder = home_dir().unwrap().join(&output.attachment.folder);
+        output.trash = home_dir().unwrap().join(&output.trash);
+        output.metadata_dump = output.metadata_dump.map(|x| home_dir().unwrap().join(x));
+        for library in output.libraries.values_mut() { library.database = home_dir().unwrap().join(&library.database); }
         output
     }
+
+    /// handler of the folder where files of the type are kept
+    pub fn handler(&self, file_type: &str) -> &FileHandler {
+        match file_type {
+            "pdf" => &self.pdf,
+            "comment" => &self.comment,
+            "attachment" => &self.attachment,
+            "bib" | "temp_bib" | "temp_pdf" => { panic!("use File::temp to create temp files.") },
+            _ => panic!("Wrong file type {}", file_type)
+        }
+    }
 }
 
 fn confirmation() -> Result<bool, std::io::Error> {
@@ -82,17 +172,17 @@ pub fn initialize() {
 
 /// load config from xdg_config, if doesn't exist then copy default config from crate
 fn init_config() -> Result<Config, IOError> {
-    let config_path = DEFAULT_CONFIG.to_path_buf();
+    let config_path = config_path();
     if !config_path.exists() {
-        println!("Moving config file to ~/.config/bibrs.toml");
+        println!("Moving config file to {}", config_path.to_string_lossy());
         copy(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data/bibrs.toml"), &config_path)?;
     }
-    Ok(Config::new(Some(config_path)))
+    Ok(Config::load(Some(config_path), library_name().as_deref()))
 }
 
-/// create pdf and comment folders if they do not exist
+/// create pdf, comment, attachment and trash folders if they do not exist
 fn init_folders(config: &Config) -> Result<(), IOError> {
-    for path in &[&config.pdf.folder, &config.comment.folder] {
+    for path in &[&config.pdf.folder, &config.comment.folder, &config.attachment.folder, &config.trash] {
         let target_path = PathBuf::from(path);
         if target_path.exists() {
             println!("pdf folder exists, not creaeting.");
@@ -104,22 +194,72 @@ fn init_folders(config: &Config) -> Result<(), IOError> {
     Ok(())
 }
 
-/// copy journal database to database location. run full db set up script.
+/// name and up.sql of a migration, the sql is built into the binary
+macro_rules! migrations {
+    ($($name: literal,)*) => {
+        &[$(($name, include_str!(concat!("../migration/", $name, "/up.sql"))),)*]
+    };
+}
+
+/// migrations to set up a new database, in the order they are applied
+pub const MIGRATIONS: &[(&str, &str)] = migrations![
+    "20180516-full-db",
+    "20261019-cascade-delete",
+    "20261019-trash",
+    "20261019-history",
+    "20261019-collections",
+    "20261019-collection-roots",
+    "20261019-keyword-aliases",
+    "20261019-keyword-taxonomy",
+    "20261019-name-parts",
+    "20261019-identifiers",
+    "20261019-fulltext",
+    "20261019-attachments",
+    "20261019-file-hash",
+    "20261019-reading",
+    "20261019-timestamps",
+    "20261019-relations",
+    "20261019-identifier-items",
+];
+
+/// whether the database has the tables of a library, as set up by bibrs init
+pub fn is_set_up(conn: &Connection) -> bool {
+    conn.query_row("SELECT count(*) FROM sqlite_master WHERE type='table' AND name='items'", NO_PARAMS,
+                   |row| row.get::<_, i64>(0)).map_or(false, |x| x > 0)
+}
+
+/// Run the up.sql of the migrations in MIGRATIONS not applied to the database yet. The number of
+/// migrations applied is kept as the user_version of the database, libraries set up before it was
+/// kept count as having the first one.
+/// Returns:
+///     the number of migrations applied before
+pub fn migrate(conn: &Connection) -> Result<usize, IOError> {
+    let mut version = conn.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get::<_, i64>(0))
+        .expect("cannot read database version") as usize;
+    if version == 0 && is_set_up(conn) { version = 1; }
+    if version >= MIGRATIONS.len() { return Ok(version) }
+    let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", NO_PARAMS, |row| row.get(0))
+        .expect("cannot read database settings");
+    // tables are rebuilt in migrations, the foreign keys are only checked again after
+    conn.pragma_update(None, "foreign_keys", &"OFF").expect("cannot set up database");
+    for (idx, (migration, sql_query)) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(&format!("BEGIN;\n{}\nPRAGMA user_version={};\nCOMMIT;", sql_query, idx + 1))
+            .unwrap_or_else(|err| panic!("Error applying migration {}: {}", migration, err));
+    }
+    if foreign_keys { conn.pragma_update(None, "foreign_keys", &"ON").expect("cannot set up database"); }
+    Ok(version)
+}
+
+/// copy journal database to database location. run the migrations it does not have yet.
 fn init_database(config: &Config) -> Result<(), IOError> {
     let journal_db_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data/journal.sqlite");
-    if !journal_db_path.exists() { copy(journal_db_path, &config.journal_db)?; }
-    let mut sql_query = String::new();
-    let sql_migration_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("migration/20180516-full-db/up.sql");
-    let mut f = File::open(&sql_migration_path).unwrap_or_else(|_| panic!("sql migration file at {} not found!",
-            &sql_migration_path.to_string_lossy()));
-    f.read_to_string(&mut sql_query).expect("error reading sql migration file");
+    if !config.journal_db.exists() { copy(journal_db_path, &config.journal_db)?; }
     let conn = Connection::open(&config.database).expect("cannot open database");
-    conn.execute_batch(&sql_query).expect("Error applying migration code");
-    Ok(())
+    migrate(&conn).map(|_| ())
 }
 
 lazy_static! {
-    pub static ref CONFIG: Config = Config::new(None);
+    pub static ref CONFIG: Config = Config::load(None, library_name().as_deref());
 }
 
 #[cfg(test)]
@@ -130,6 +270,35 @@ mod tests {
         let temp_config = Config::new(Some("test/data/bibrs-test.toml".into()));
         assert_eq!(temp_config.comment.extension[0], "txt");
         assert_eq!(temp_config.pdf.folder, PathBuf::from("/home/palpatine/Sync/paper/pdf/"));
+        assert_eq!(temp_config.trash, PathBuf::from("/home/palpatine/Sync/paper/trash/"));
+        assert!(!temp_config.bibtex_utf8);
+        assert!(!temp_config.content_addressed);
+        assert_eq!(temp_config.pdf.template, None);
+        assert_eq!(temp_config.attachment.folder, PathBuf::from("/home/palpatine/Sync/paper/attachment/"));
+        assert_eq!(temp_config.library, None);
+        let lab_config = Config::load(Some("test/data/bibrs-test.toml".into()), Some("lab"));
+        assert_eq!(lab_config.library, Some("lab".to_owned()));
+        assert_eq!(lab_config.database, PathBuf::from("/home/palpatine/Sync/lab/library.sqlite"));
+        assert_eq!(lab_config.pdf.folder, PathBuf::from("/home/palpatine/Sync/lab/pdf/"));
+        assert_eq!(lab_config.comment.folder, temp_config.comment.folder);
+        assert_eq!(lab_config.trash, PathBuf::from("/home/palpatine/Sync/lab/trash/"));
+    }
+
+    #[test]
+    fn test_migrate() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0].1).unwrap();
+        conn.execute("INSERT INTO items (citation, entry_type, title, year) VALUES ('a', 'article', 'A', 2000)",
+                     NO_PARAMS).unwrap();
+        assert!(is_set_up(&conn));
+        assert_eq!(migrate(&conn).unwrap(), 1);
+        assert_eq!(migrate(&conn).unwrap(), MIGRATIONS.len());
+        let version: i64 = conn.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+        let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", NO_PARAMS, |row| row.get(0)).unwrap();
+        assert!(foreign_keys);
+        conn.execute("INSERT INTO extra_fields VALUES ('a', 'note', 'b')", NO_PARAMS).unwrap();
+        assert!(conn.execute("INSERT INTO extra_fields VALUES ('b', 'note', 'b')", NO_PARAMS).is_err());
     }
 }
 
@@ -1,14 +1,27 @@
 pub mod journal;
+pub mod trash;
+pub mod operation;
+pub mod history;
+pub mod collection;
+pub mod keyword;
+pub mod identifier;
+pub mod fulltext;
+pub mod attachment;
+pub mod sync;
+pub mod reading;
+pub mod dates;
+pub mod relation;
+pub mod stats;
 
 use std::str;
 use std::path::PathBuf;
 use std::collections::{HashSet, HashMap};
-use rusqlite::{params, Connection, Result, Row};
+use rusqlite::{params, Connection, Result, Row, OptionalExtension, NO_PARAMS};
 use rusqlite::types::ToSql;
 
 use crate::model::{Entry, Person};
 use crate::entry_type::EntryType;
-use crate::config::CONFIG;
+use crate::config::{self, CONFIG};
 use journal::Journal;
 
 impl From<&Row<'_>> for Person {
@@ -17,7 +30,9 @@ impl From<&Row<'_>> for Person {
             id: row.get_unwrap::<_, Option<i32>>(1),
             last_name: row.get_unwrap(2),
             first_name: row.get_unwrap(3),
-            search_term: row.get_unwrap::<_, String>(4)}
+            search_term: row.get_unwrap::<_, String>(4),
+            prefix: row.get_unwrap(5),
+            suffix: row.get_unwrap(6)}
     }
 }
 
@@ -41,6 +56,9 @@ impl From<&Row<'_>> for Entry {
             keywords: HashSet::new(),
             files: Vec::new(),
             extra_fields: HashMap::new(),
+            reading: None,
+            added_at: row.get_unwrap::<_, Option<i64>>(12),
+            modified_at: row.get_unwrap::<_, Option<i64>>(13),
         }
     }
 }
@@ -49,12 +67,35 @@ pub struct SqliteBibDB {
     conn: Connection,
 }
 
+/// Conditions of a search, entries have to match all of them
+#[derive(Default, Debug)]
+pub struct SearchFilter {
+    /// search terms of last names
+    pub authors: Vec<String>,
+    pub keywords: Vec<String>,
+    /// also match entries tagged with descendants of the keywords in the taxonomy
+    pub expand: bool,
+    /// collection id, subcollections included
+    pub collection: Option<i64>,
+    /// reading status, like unread for the to-read queue
+    pub status: Option<String>,
+    /// lowest rating
+    pub min_rating: Option<i32>,
+    /// unix timestamps bounding the date added, or the date last modified with modified
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub modified: bool,
+}
+
 pub trait BibDataBase {
     fn add_item(&self, entry: &Entry, journal_id: Option<i32>) -> Result<()>;
+    fn update_item(&self, entry: &Entry, journal_id: Option<i32>) -> Result<()>;
     fn get_item(&self, id: &str) -> Result<Entry>;
     fn search(&self, authors: &[String], keywords: &[String]) -> Result<Vec<Entry>>;
+    fn search_by(&self, filter: &SearchFilter) -> Result<Vec<Entry>>;
     fn search_lastname(&self, search_term: &str) -> Result<Vec<Person>>;
     fn delete(&self, id: &str) -> Result<()>;
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
     fn add_keywords<T: AsRef<str>>(&self, citation: &str, terms: &[T]) -> Result<()>;
     fn del_keywords<T: AsRef<str>>(&self, citation: &str, terms: &[T]) -> Result<()>;
     fn get_files(&self, citation: &str) -> Result<Vec<(String, String)>>;
@@ -63,6 +104,10 @@ pub trait BibDataBase {
     fn add_journal(&self, jouranl: Journal) -> Result<i32>;
 }
 
+/// tables with an item_id column referring to items
+const ITEM_TABLES: &[&str] = &["item_persons", "item_keywords", "item_collections", "extra_fields", "identifiers",
+                               "fulltext_files", "files", "reading", "item_relations"];
+
 /// insert a number of question marks
 macro_rules! multi_param {
     ($no:expr) => {{
@@ -89,8 +134,32 @@ impl SqliteBibDB {
         let db_path = inputs.unwrap_or_else(|| CONFIG.database.clone());
         let conn = Connection::open(&db_path).unwrap_or_else(
             |_| panic!("Cannot open sqlite file at {}!", db_path.to_string_lossy()));
+        let version = if config::is_set_up(&conn) {
+            Some(config::migrate(&conn).expect("failed to migrate database"))
+        } else { None };
         conn.pragma_update(None, "foreign_keys", &"ON").unwrap();
-        SqliteBibDB{conn}
+        let db = SqliteBibDB{conn};
+        if let Some(version) = version { db.backfill(version).expect("failed to migrate database"); }
+        db
+    }
+
+    /// Fill in what the migrations applied after the version cannot compute in sql
+    fn backfill(&self, version: usize) -> Result<()> {
+        let applied = |name: &str| config::MIGRATIONS.iter().skip(version).any(|x| x.0 == name);
+        if applied("20261019-timestamps") { self.date_by_files()?; }
+        if applied("20261019-identifier-items") { self.reindex_identifiers()?; }
+        Ok(())
+    }
+
+    /// whether the database has the tables of a library, as set up by bibrs init
+    pub fn is_set_up(&self) -> bool { config::is_set_up(&self.conn) }
+
+    /// empty database with all migrations applied, for tests
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        let db = SqliteBibDB::new(Some(PathBuf::from(":memory:")));
+        config::migrate(&db.conn).expect("failed to migrate in-memory database");
+        db
     }
 
     /// retrieve all authors and editors for one given item id
@@ -98,16 +167,10 @@ impl SqliteBibDB {
     ///     (authors, editors)
     fn get_people(&self, id: &str) -> (Vec<Person>, Vec<Person>)  {
         let mut query = self.conn.prepare_cached(
-            "SELECT is_editor, persons.id, last_name, first_name, search_term FROM item_persons JOIN persons \
-            ON item_persons.person_id=persons.id WHERE item_id=? ORDER BY is_editor, order_seq").unwrap();
-        let people: Vec<(Person, bool)> = query.query_map(&[&id],
-            |row| (Ok((Person {
-                id:row.get_unwrap(1),
-                last_name: row.get_unwrap(2),
-                first_name: row.get_unwrap(3),
-                search_term: row.get_unwrap::<_, String>(4)
-            }, row.get_unwrap(0))))
-        ).unwrap().collect::<Result<Vec<(Person, bool)>>>().unwrap();
+            "SELECT is_editor, persons.id, last_name, first_name, search_term, prefix, suffix FROM item_persons \
+            JOIN persons ON item_persons.person_id=persons.id WHERE item_id=? ORDER BY is_editor, order_seq").unwrap();
+        let people: Vec<(Person, bool)> = query.query_map(&[&id], |row| Ok((Person::from(row), row.get_unwrap(0))))
+            .unwrap().collect::<Result<Vec<(Person, bool)>>>().unwrap();
         type EditorAuthors = (Vec<(Person, bool)>, Vec<(Person, bool)>);
         let (editors, authors): EditorAuthors = people.into_iter().partition(|(_, is_editor)| *is_editor);
         (authors.into_iter().map(|(person, _)| person).collect(), editors.into_iter().map(|(person, _)| person)
@@ -116,14 +179,29 @@ impl SqliteBibDB {
 
     pub fn search_person(&self, person: &Person) -> Result<Person> {
         let mut query = self.conn.prepare_cached(
-            "SELECT id, last_name, first_name, search_term FROM persons WHERE search_term = ? AND first_name = ?;")?;
-        query.query_row(&[&person.search_term, &person.first_name], |row| Ok(Person::from(row)))
+            "SELECT NULL, id, last_name, first_name, search_term, prefix, suffix FROM persons \
+            WHERE search_term = ? AND first_name = ? AND prefix = ? AND suffix = ?;")?;
+        query.query_row(&[&person.search_term, &person.first_name, &person.prefix, &person.suffix],
+                        |row| Ok(Person::from(row)))
     }
 
     pub fn add_person(&self, person: &Person) -> Result<i32> {
         let mut query = self.conn.prepare_cached(
-            "INSERT INTO persons (last_name, first_name, search_term) VALUES (?, ?, ?);")?;
-        query.insert(&[&person.last_name, &person.first_name, &person.search_term]).map(|x| x as i32)
+            "INSERT INTO persons (last_name, first_name, search_term, prefix, suffix) VALUES (?, ?, ?, ?, ?);")?;
+        query.insert(&[&person.last_name, &person.first_name, &person.search_term, &person.prefix, &person.suffix])
+            .map(|x| x as i32)
+    }
+
+    /// id of the journal with exactly this full name
+    pub fn journal_id(&self, name: &str) -> Result<Option<i32>> {
+        self.conn.query_row("SELECT id FROM journals WHERE name=?", &[name], |row| row.get(0)).optional()
+    }
+
+    /// citations of all items in the library
+    pub fn citations(&self) -> Result<Vec<String>> {
+        let mut query = self.conn.prepare_cached("SELECT citation FROM items ORDER BY citation")?;
+        let citations = query.query_map(NO_PARAMS, |row| row.get::<_, String>(0))?.collect::<Result<Vec<String>>>();
+        citations
     }
 
     fn get_keywords(&self, id: &str) -> Vec<String> {
@@ -161,25 +239,34 @@ impl SqliteBibDB {
         (non_existing, ids)
     }
 
-    fn add_extra_fields(&self, citation: &str, extra_fields: &HashMap<String, String>) -> Result<()> {
-        let mut insert_query = self.conn.prepare_cached(
-            "REPLACE INTO extra_fields (item_id, field, value) VALUES (?, ?, ?)")?;
-        for (field, value) in extra_fields.iter() {
-            insert_query.insert(&[citation, field, value])?;
-        };
-        Ok(())
+    /// delete the item and its relations, to be called within a transaction
+    fn remove_item(&self, id: &str) -> Result<()> {
+        for table in ITEM_TABLES.iter() {
+            self.conn.execute(&format!("DELETE FROM \"{}\" WHERE item_id=?", table), &[&id])?;
+        }
+        self.conn.execute("DELETE FROM item_relations WHERE other_id=?", &[&id])?;
+        self.conn.execute("DELETE FROM items WHERE citation=?", &[&id]).map(|_| ())
     }
-}
 
-impl BibDataBase for SqliteBibDB {
-    fn add_item(&self, entry: &Entry, journal_id: Option<i32>) -> Result<()> {
+    /// Insert the item with all its relations, without recording history. Entries carrying their
+    /// dates, like restored or synced ones, keep them, new ones are dated now.
+    fn insert_item(&self, entry: &Entry, journal_id: Option<i32>) -> Result<()> {
         let mut insert_query = self.conn.prepare_cached("
             INSERT INTO items (citation, entry_type, title, booktitle, year, month, chapter, edition,
-                               volume, \"number\", pages, journal_id)
-            VALUES (?,?,?,?,?,?,?,?,?,?,?,?);")?;
+                               volume, \"number\", pages, journal_id, added_at, modified_at)
+            VALUES (?,?,?,?,?,?,?,?,?,?,?,?,
+                    COALESCE(?, strftime('%s', 'now')), COALESCE(?, strftime('%s', 'now')));")?;
         insert_query.query(params![&entry.citation, &entry.entry_type.to_string(), &entry.title, &entry.booktitle,
             &entry.year, &entry.month, &entry.chapter, &entry.edition, &entry.volume, &entry.number, &entry.pages,
-            &journal_id])?;
+            &journal_id, &entry.added_at, &entry.modified_at])?;
+        self.insert_relations(entry)?;
+        for (name, file_type) in entry.files.iter() { self.insert_file(&entry.citation, name, file_type)?; }
+        if let Some(ref reading) = entry.reading { self.insert_reading(&entry.citation, reading)?; }
+        Ok(())
+    }
+
+    /// relate the authors, editors, keywords, extra fields and identifiers of the entry to its item
+    fn insert_relations(&self, entry: &Entry) -> Result<()> {
         let mut insert_relation = self.conn.prepare_cached(
             "INSERT INTO item_persons (item_id, person_id, order_seq, is_editor) VALUES (?, ?, ?, ?);")?;
         for (people, is_editor) in [(&entry.authors, false), (&entry.editors, true)].iter() {
@@ -191,16 +278,86 @@ impl BibDataBase for SqliteBibDB {
                 insert_relation.insert(params![&entry.citation, &author_id, &(order as isize), &is_editor])?;
             }
         }
-        self.add_keywords(&entry.citation, &entry.keywords.iter().cloned().collect::<Vec<String>>())?;
+        self.insert_keywords(&entry.citation, &entry.keywords.iter().cloned().collect::<Vec<String>>())?;
         self.add_extra_fields(&entry.citation, &entry.extra_fields)?;
-        for (name, file_type) in entry.files.iter() { self.add_file(&entry.citation, name, file_type)?; }
+        self.insert_identifiers(entry)
+    }
+
+    /// relate keywords to the item, aliases are resolved to their keywords
+    /// Returns:
+    ///     the resolved keywords
+    fn insert_keywords<T: AsRef<str>>(&self, citation: &str, terms: &[T]) -> Result<Vec<String>> {
+        let terms = self.resolve_keywords(terms)?;
+        let (unexist, unrelated_ids) = self.exist_keywords(&terms);
+        let mut query_insert_key = self.conn.prepare_cached("INSERT INTO keywords (text) VALUES (?)").unwrap();
+        let row_ids: Vec<i64> = unexist.iter().map(|x| query_insert_key.insert(&[x]).unwrap()).collect();
+        let mut query_insert_relation = self.conn.prepare_cached(
+            "INSERT OR IGNORE INTO item_keywords (item_id, keyword_id) VALUES (?, ?)").unwrap();
+        for id in unrelated_ids.iter().chain(row_ids.iter()) { query_insert_relation.execute(params![citation, id])?; }
+        Ok(terms)
+    }
+
+    fn insert_file(&self, citation: &str, name: &str, file_type: &str) -> Result<()> {
+        let mut insert_query = self.conn.prepare_cached(
+            "INSERT INTO files (item_id, name, object_type, role) VALUES (?, ?, ?, ?)")?;
+        insert_query.insert(params![citation, name, file_type, attachment::default_role(file_type)])?;
+        Ok(())
+    }
+
+    fn add_extra_fields(&self, citation: &str, extra_fields: &HashMap<String, String>) -> Result<()> {
+        let mut insert_query = self.conn.prepare_cached(
+            "REPLACE INTO extra_fields (item_id, field, value) VALUES (?, ?, ?)")?;
+        for (field, value) in extra_fields.iter() {
+            insert_query.insert(&[citation, field, value])?;
+        };
         Ok(())
     }
+}
+
+impl BibDataBase for SqliteBibDB {
+    fn add_item(&self, entry: &Entry, journal_id: Option<i32>) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        self.insert_item(entry, journal_id)?;
+        self.log_history(&entry.citation, "add", None, None, Some(&entry.title))?;
+        // logging bumps the modification date, a carried one outlasts it
+        if let Some(modified_at) = entry.modified_at {
+            self.conn.execute("UPDATE items SET modified_at=? WHERE citation=?", params![modified_at, &entry.citation])?;
+        }
+        transaction.commit()
+    }
+
+    /// Replace an existing item with a new version, keeping its file records and collections.
+    /// Changed fields are recorded in the history as edits.
+    fn update_item(&self, entry: &Entry, journal_id: Option<i32>) -> Result<()> {
+        let old_entry = self.get_item(&entry.citation)?;
+        let files = self.get_files(&entry.citation)?;
+        let transaction = self.conn.unchecked_transaction()?;
+        for table in ["item_persons", "item_keywords", "extra_fields", "identifiers"].iter() {
+            self.conn.execute(&format!("DELETE FROM \"{}\" WHERE item_id=?", table), &[&entry.citation])?;
+        }
+        self.conn.execute("
+            UPDATE items
+               SET entry_type=?, title=?, booktitle=?, year=?, month=?, chapter=?, edition=?, volume=?, \"number\"=?,
+                   pages=?, journal_id=?
+             WHERE citation=?", params![&entry.entry_type.to_string(), &entry.title, &entry.booktitle, &entry.year,
+            &entry.month, &entry.chapter, &entry.edition, &entry.volume, &entry.number, &entry.pages, &journal_id,
+            &entry.citation])?;
+        self.insert_relations(entry)?;
+        for (name, file_type) in entry.files.iter() {
+            if !files.contains(&(name.clone(), file_type.clone())) {
+                self.insert_file(&entry.citation, name, file_type)?;
+            }
+        }
+        for (field, old_value, new_value) in history::entry_diff(&old_entry, entry) {
+            self.log_history(&entry.citation, "edit", Some(&field), old_value.as_deref(), new_value.as_deref())?;
+        }
+        transaction.commit()
+    }
 
     fn get_item(&self, id: &str) -> Result<Entry> {
         let mut query = self.conn.prepare_cached("
             SELECT citation, entry_type, title, booktitle, year, month, chapter, edition, volume, \"number\", pages,
-                   journals.name
+                   journals.name, added_at, modified_at
               FROM items
                    LEFT JOIN journals ON items.journal_id=journals.id
              WHERE citation = ?
@@ -213,6 +370,7 @@ impl BibDataBase for SqliteBibDB {
                 entry.editors.extend(editors);
                 entry.keywords.extend(self.get_keywords(id));
                 entry.extra_fields.extend(self.get_extra_fields(id));
+                entry.reading = self.get_reading(id)?;
                 Ok(entry)
             }
         )
@@ -231,96 +389,145 @@ impl BibDataBase for SqliteBibDB {
     }
 
     fn add_file(&self, citation: &str, name: &str, file_type: &str) -> Result<()> {
-        let mut insert_query = self.conn.prepare_cached(
-            "INSERT INTO files (item_id, name, object_type) VALUES (?, ?, ?)")?;
-        insert_query.insert(params![citation, name, file_type])?;
-        Ok(())
+        self.insert_file(citation, name, file_type)?;
+        self.log_history(citation, "add_file", Some(file_type), None, Some(name))
     }
 
+    /// Delete the item and all its relations in one transaction. Persons, keywords and journals
+    /// no longer referenced by any item are removed by triggers.
     fn delete(&self, id: &str) -> Result<()> {
-        let mut query = self.conn.prepare_cached("
-            DELETE i, ip, ik
-              FROM items AS i
-             INNER JOIN item_persons AS ip
-             INNER JOIN item_keywords AS ik
-                ON items.citation=item_persons.item_id
-                ON items.citation=item_keywords.item_id
-             WHERE items.citation=?")?;
-        query.query(&[&id]).map(|_| ())
+        let transaction = self.conn.unchecked_transaction()?;
+        let title: String = self.conn.query_row("SELECT title FROM items WHERE citation=?", &[&id], |row| row.get(0))?;
+        self.remove_item(id)?;
+        self.log_history(id, "delete", None, Some(&title), None)?;
+        transaction.commit()
+    }
+
+    /// Change the citation of an item and of all rows referring to it
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        transaction.pragma_update(None, "defer_foreign_keys", &"ON")?;
+        transaction.execute("UPDATE items SET citation=? WHERE citation=?", &[to, from])?;
+        for table in ITEM_TABLES.iter() {
+            transaction.execute(&format!("UPDATE \"{}\" SET item_id=? WHERE item_id=?", table), &[to, from])?;
+        }
+        transaction.execute("UPDATE item_relations SET other_id=? WHERE other_id=?", &[to, from])?;
+        transaction.execute("UPDATE item_history SET item_id=? WHERE item_id=?", &[to, from])?;
+        self.log_history(to, "rename", Some("citation"), Some(from), Some(to))?;
+        transaction.commit()
     }
 
     fn search(&self, authors: &[String], keywords: &[String]) -> Result<Vec<Entry>> {
+        self.search_by(&SearchFilter{authors: authors.to_vec(), keywords: keywords.to_vec(), ..Default::default()})
+    }
+
+    /// Entries matching all conditions in the filter, ordered by year, by priority when searching
+    /// by reading status, or newest first when searching by dates
+    fn search_by(&self, filter: &SearchFilter) -> Result<Vec<Entry>> {
+        let authors = &filter.authors;
+        let keywords = self.resolve_keywords(&filter.keywords)?;
         let author_no = authors.len() as isize;
         let keyword_no = keywords.len() as isize;
-        let (query_str, terms): (String, Vec<&dyn ToSql>) = if author_no > 0 {
-            if keyword_no > 0 {
-                (format!("
-                    SELECT item_id
-                      FROM item_persons
-                           JOIN persons
-                             ON person_id = persons.id
-                     WHERE search_term IN ({})
-                     GROUP BY item_id
-                    HAVING count(DISTINCT search_term) = ?
-                    INTERSECT
-                    SELECT item_id
+        let mut queries: Vec<String> = Vec::new();
+        let mut terms: Vec<&dyn ToSql> = Vec::new();
+        if author_no > 0 {
+            queries.push(format!("
+                SELECT item_id
+                  FROM item_persons
+                       JOIN persons
+                         ON person_id = persons.id
+                 WHERE search_term IN ({})
+                 GROUP BY item_id
+                HAVING count(DISTINCT search_term) = ?", multi_param!(author_no)));
+            terms.append(&mut build_param!((authors, &author_no)));
+        }
+        if keyword_no > 0 && filter.expand {
+            for keyword in keywords.iter() {
+                queries.push("
+                    SELECT DISTINCT item_id
                       FROM item_keywords
-                           JOIN keywords
-                             ON keyword_id=keywords.id
-                     WHERE keywords.text IN ({})
-                     GROUP BY item_id
-                    HAVING count(*) = ?",
-                    multi_param!(author_no), multi_param!(keyword_no)),
-                 build_param!((authors, &author_no), (keywords, &keyword_no)))
-            } else {
-                (format!(
-                     "SELECT item_id
-                        FROM item_persons
-                             JOIN persons
-                               ON person_id = persons.id
-                       WHERE search_term IN ({})
-                       GROUP BY item_id
-                     HAVING count(DISTINCT search_term) = ?", multi_param!(author_no)),
-                 build_param!((authors, &author_no)))
+                     WHERE keyword_id IN (
+                           WITH RECURSIVE sub(id) AS (
+                                SELECT id FROM keywords WHERE text=?
+                                 UNION
+                                SELECT keywords.id FROM keywords JOIN sub ON keywords.parent_id=sub.id)
+                           SELECT id FROM sub)".to_owned());
+                terms.push(keyword);
             }
         } else if keyword_no > 0 {
-            (format!(
-                "SELECT item_id
-                   FROM item_keywords
-                        JOIN keywords
-                          ON keyword_id=keywords.id
-                  WHERE keywords.text IN ({})
-                  GROUP BY item_id
-                HAVING count(*) = ?", multi_param!(keyword_no)),
-             build_param!((keywords, &keyword_no)))
-        } else { panic!("please search with authors and/or keywords!") };
+            queries.push(format!("
+                SELECT item_id
+                  FROM item_keywords
+                       JOIN keywords
+                         ON keyword_id=keywords.id
+                 WHERE keywords.text IN ({})
+                 GROUP BY item_id
+                HAVING count(*) = ?", multi_param!(keyword_no)));
+            terms.append(&mut build_param!((keywords, &keyword_no)));
+        }
+        if let Some(ref collection_id) = filter.collection {
+            queries.push("
+                SELECT DISTINCT item_id
+                  FROM item_collections
+                 WHERE collection_id IN (
+                       WITH RECURSIVE sub(id) AS (
+                            SELECT ?
+                             UNION
+                            SELECT collections.id FROM collections JOIN sub ON collections.parent_id=sub.id)
+                       SELECT id FROM sub)".to_owned());
+            terms.push(collection_id);
+        }
+        if let Some(ref status) = filter.status {
+            queries.push("SELECT item_id FROM reading WHERE status=?".to_owned());
+            terms.push(status);
+        }
+        if let Some(ref rating) = filter.min_rating {
+            queries.push("SELECT item_id FROM reading WHERE rating >= ?".to_owned());
+            terms.push(rating);
+        }
+        let date_column = if filter.modified { "modified_at" } else { "added_at" };
+        if let Some(ref since) = filter.since {
+            queries.push(format!("SELECT citation FROM items WHERE {} >= ?", date_column));
+            terms.push(since);
+        }
+        if let Some(ref until) = filter.until {
+            queries.push(format!("SELECT citation FROM items WHERE {} < ?", date_column));
+            terms.push(until);
+        }
+        if queries.is_empty() { panic!("please search with authors, keywords, collection, reading status and/or dates!") }
+        let query_str = queries.join("\nINTERSECT\n");
         let mut query = self.conn.prepare_cached(&query_str)?;
         let mut results = query.query_map(&terms, |row| row.get::<_, String>(0))?
             .map(|term| self.get_item(&(term?))).collect::<Result<Vec<Entry>>>()?;
         results.sort_by(|a, b| a.year.cmp(&b.year));
+        // a reading queue goes by priority
+        if filter.status.is_some() {
+            results.sort_by_key(|x| x.reading.as_ref().and_then(|reading| reading.priority).unwrap_or(i32::MAX));
+        } else if filter.since.is_some() || filter.until.is_some() {
+            results.sort_by_key(|x| std::cmp::Reverse(if filter.modified { x.modified_at } else { x.added_at }));
+        }
         Ok(results)
     }
 
     /// get people with the same last name
     fn search_lastname(&self, search_term: &str) -> Result<Vec<Person>> {
-        let mut query = self.conn.prepare_cached("SELECT id, last_name, first_name, search_term FROM persons \
-            WHERE search_term = ? ORDER BY first_name;")?;
+        let mut query = self.conn.prepare_cached("SELECT NULL, id, last_name, first_name, search_term, prefix, \
+            suffix FROM persons WHERE search_term = ? ORDER BY first_name;")?;
         let people = query.query_map(&[&search_term], |row| Ok(Person::from(row)))?;
         people.collect::<Result<Vec<Person>>>()
     }
 
     fn add_keywords<T: AsRef<str>>(&self, citation: &str, terms: &[T]) -> Result<()> {
-        let (unexist, unrelated_ids) = self.exist_keywords(terms);
-        let mut query_insert_key = self.conn.prepare_cached("INSERT INTO keywords (text) VALUES (?)").unwrap();
-        let row_ids: Vec<i64> = unexist.iter().map(|x| query_insert_key.insert(&[x]).unwrap()).collect();
-        let mut query_insert_relation = self.conn.prepare_cached(
-            "INSERT INTO item_keywords (item_id, keyword_id) VALUES (?, ?)").unwrap();
-        for id in unrelated_ids.iter().chain(row_ids.iter()) { query_insert_relation.execute(params![citation, id])?; }
+        let existing = self.get_keywords(citation);
+        for term in self.insert_keywords(citation, terms)?.iter().filter(|x| !existing.contains(x)) {
+            self.log_history(citation, "add_keyword", Some("keyword"), None, Some(term))?;
+        }
         Ok(())
     }
 
     /// Delete keywords associations
     fn del_keywords<T: AsRef<str>>(&self, citation: &str, terms: &[T]) -> Result<()> {
+        let sql_terms: Vec<String> = self.resolve_keywords(terms)?;
         let mut query_del_relation = self.conn.prepare_cached(
             &format!("
                 DELETE FROM item_keywords
@@ -330,11 +537,14 @@ impl BibDataBase for SqliteBibDB {
                        JOIN keywords ON item_keywords.keyword_id=keywords.id
                       WHERE item_keywords.item_id=?
                         AND keywords.text IN ({})
-                 )", multi_param!(terms.len()))).unwrap();
+                 )", multi_param!(sql_terms.len()))).unwrap();
         let mut params: Vec<&dyn ToSql> = vec![&citation];
-        let sql_terms: Vec<String> = terms.iter().map(|x| x.as_ref().to_string()).collect();
         sql_terms.iter().for_each(|x| params.push(x));
+        let existing = self.get_keywords(citation);
         query_del_relation.execute(&params)?;
+        for term in sql_terms.iter().filter(|x| existing.contains(x)) {
+            self.log_history(citation, "del_keyword", Some("keyword"), Some(term), None)?;
+        }
         Ok(())
     }
 
@@ -352,10 +562,9 @@ impl BibDataBase for SqliteBibDB {
         query.query_row(params![name, name, &format!("%{}%", name)], Journal::from_row).map(|x| x.name)
     }
 
-    fn add_journal(&self, journal: Journal) -> Result<String> {
+    fn add_journal(&self, journal: Journal) -> Result<i32> {
         let mut insert = self.conn.prepare_cached("INSERT INTO journals (name, abbr, abbr_no_dot) VALUES (?, ?, ?);")?;
-        insert.insert(&[journal.name, journal.abbr, journal.abbr_no_dot]).map(|x| x as i32);
-        name
+        insert.insert(&[journal.name, journal.abbr, journal.abbr_no_dot]).map(|x| x as i32)
     }
 }
 
@@ -420,4 +629,36 @@ mod tests {
         let entry = conn.get_item("walker1938").unwrap();
         println!("Leftover keywords include: {}", entry.keywords.iter().join(", "));
     }
+    #[test]
+    fn test_delete() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = crate::reader::bibtex::read_entries(
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        let mut entry = entries[1].clone();
+        entry.keywords.insert("typesetting".to_owned());
+        entry.files.push(("latexcompanion".to_owned(), "pdf".to_owned()));
+        conn.add_item(&entry, None).unwrap();
+        conn.add_item(&entries[2], None).unwrap();
+        conn.delete("latexcompanion").expect("can't delete entry");
+        assert!(conn.get_item("latexcompanion").is_err());
+        assert!(conn.get_files("latexcompanion").unwrap().is_empty());
+        assert!(conn.get_extra_fields("latexcompanion").is_empty());
+        assert!(conn.search_lastname("goossens").unwrap().is_empty());
+        assert_eq!(conn.search_lastname("knuth").unwrap().len(), 1);
+        let (missing, _) = conn.exist_keywords(&["typesetting"]);
+        assert_eq!(missing, vec_str!["typesetting"]);
+    }
+    #[test]
+    fn test_rename() {
+        let conn = SqliteBibDB::in_memory();
+        let mut entry = crate::reader::bibtex::read_entries(
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"))[1].clone();
+        entry.files.push(("latexcompanion".to_owned(), "pdf".to_owned()));
+        conn.add_item(&entry, None).unwrap();
+        conn.rename("latexcompanion", "goossens1993").expect("can't rename entry");
+        assert!(conn.get_item("latexcompanion").is_err());
+        let entry = conn.get_item("goossens1993").unwrap();
+        assert_eq!(entry.authors.len(), 3);
+        assert_eq!(conn.get_files("goossens1993").unwrap().len(), 1);
+    }
 }
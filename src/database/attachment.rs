@@ -0,0 +1,141 @@
+use rusqlite::{params, Result, Row, NO_PARAMS};
+
+use super::SqliteBibDB;
+
+/// A file attached to an item
+#[derive(Debug, PartialEq, Clone)]
+pub struct Attachment {
+    /// name in the folder of its object_type, with the extension for attachment files
+    pub name: String,
+    /// pdf, comment or attachment
+    pub object_type: String,
+    /// main, preprint, supplement, dataset, slides, notes and so on
+    pub role: String,
+    pub label: Option<String>,
+    /// size in bytes and SHA-256 of the content when the file was stored or last checked
+    pub size: Option<i64>,
+    pub hash: Option<String>,
+}
+
+/// role of files added without one, the main pdf and the notes in the comment file
+pub fn default_role(object_type: &str) -> &'static str {
+    match object_type {
+        "comment" => "notes",
+        "pdf" => "main",
+        _ => "other",
+    }
+}
+
+impl SqliteBibDB {
+    /// files of an item, main files first then in the order they were attached
+    pub fn get_attachments(&self, citation: &str) -> Result<Vec<Attachment>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT name, object_type, role, label, size, hash
+              FROM files
+             WHERE item_id=?
+             ORDER BY role!='main', rowid")?;
+        let attachments = query.query_map(&[&citation], |row| Ok(Attachment::from(row)))?
+            .collect::<Result<Vec<Attachment>>>();
+        attachments
+    }
+
+    /// every file record in the library with the citation it belongs to
+    pub fn all_attachments(&self) -> Result<Vec<(String, Attachment)>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT name, object_type, role, label, size, hash, item_id
+              FROM files
+             ORDER BY item_id, role!='main', rowid")?;
+        let attachments = query.query_map(NO_PARAMS, |row| Ok((row.get_unwrap(6), Attachment::from(row))))?
+            .collect::<Result<Vec<(String, Attachment)>>>();
+        attachments
+    }
+
+    pub fn add_attachment(&self, citation: &str, attachment: &Attachment) -> Result<()> {
+        self.conn.execute("
+            INSERT INTO files (item_id, name, object_type, role, label, size, hash)
+            VALUES (?, ?, ?, ?, ?, ?, ?)", params![citation, &attachment.name, &attachment.object_type,
+            &attachment.role, &attachment.label, &attachment.size, &attachment.hash])?;
+        self.log_history(citation, "add_file", Some(&attachment.object_type), None, Some(&attachment.name))
+    }
+
+    /// whether any item has a file of the type under this name
+    pub fn file_name_taken(&self, name: &str, object_type: &str) -> Result<bool> {
+        self.conn.query_row("SELECT COUNT(*) FROM files WHERE name=? AND object_type=?", &[name, object_type],
+            |row| row.get::<_, i64>(0)).map(|x| x > 0)
+    }
+
+    /// record the size and hash of a stored file, for every item sharing it
+    pub fn set_file_hash(&self, name: &str, object_type: &str, size: i64, hash: &str) -> Result<()> {
+        self.conn.execute("UPDATE files SET size=?, hash=? WHERE name=? AND object_type=?",
+            params![size, hash, name, object_type]).map(|_| ())
+    }
+
+    /// Change the stored name of a file, the full-text index follows
+    pub fn rename_file(&self, citation: &str, object_type: &str, from: &str, to: &str) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        self.conn.execute("UPDATE files SET name=? WHERE item_id=? AND name=? AND object_type=?",
+            params![to, citation, from, object_type])?;
+        self.conn.execute("UPDATE fulltext_files SET name=? WHERE item_id=? AND name=?", params![to, citation, from])?;
+        self.log_history(citation, "rename_file", Some(object_type), Some(from), Some(to))?;
+        transaction.commit()
+    }
+
+    pub fn remove_file_record(&self, citation: &str, name: &str, object_type: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM files WHERE item_id=? AND name=? AND object_type=?",
+            params![citation, name, object_type])?;
+        self.log_history(citation, "remove_file", Some(object_type), Some(name), None)
+    }
+
+    /// citations of entries without any pdf
+    pub fn citations_without_pdf(&self) -> Result<Vec<String>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT citation
+              FROM items
+             WHERE citation NOT IN (SELECT item_id FROM files WHERE object_type='pdf')
+             ORDER BY citation")?;
+        let citations = query.query_map(NO_PARAMS, |row| row.get(0))?.collect::<Result<Vec<String>>>();
+        citations
+    }
+}
+
+/// from name, object_type, role, label, size and hash
+impl From<&Row<'_>> for Attachment {
+    fn from(row: &Row) -> Self {
+        let object_type: String = row.get_unwrap(1);
+        let role: Option<String> = row.get_unwrap(2);
+        Attachment{name: row.get_unwrap(0), role: role.unwrap_or_else(|| default_role(&object_type).to_owned()),
+                   object_type, label: row.get_unwrap(3), size: row.get_unwrap(4), hash: row.get_unwrap(5)}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::BibDataBase;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_attachments() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        conn.add_item(&entries[0], None).unwrap();
+        let supplement = Attachment{name: "einstein-supplement.zip".to_owned(), object_type: "attachment".to_owned(),
+                                    role: "supplement".to_owned(), label: Some("derivations".to_owned()),
+                                    size: Some(1024), hash: Some("abc".to_owned())};
+        conn.add_attachment("einstein", &supplement).unwrap();
+        conn.add_file("einstein", "einstein", "pdf").unwrap();
+        let attachments = conn.get_attachments("einstein").unwrap();
+        assert_eq!(attachments.iter().map(|x| x.role.as_str()).collect::<Vec<&str>>(), ["main", "supplement"]);
+        assert_eq!(attachments[1], supplement);
+        assert!(conn.file_name_taken("einstein-supplement.zip", "attachment").unwrap());
+        assert!(!conn.file_name_taken("einstein-supplement.zip", "pdf").unwrap());
+        assert_eq!(conn.citations_without_pdf().unwrap(), Vec::<String>::new());
+        conn.rename_file("einstein", "attachment", "einstein-supplement.zip", "1905/einstein-supplement.zip").unwrap();
+        assert!(conn.file_name_taken("1905/einstein-supplement.zip", "attachment").unwrap());
+        conn.rename_file("einstein", "attachment", "1905/einstein-supplement.zip", "einstein-supplement.zip").unwrap();
+        let trash_id = conn.trash_item("einstein").unwrap();
+        conn.restore_trash(trash_id).unwrap();
+        assert_eq!(conn.get_attachments("einstein").unwrap()[1], supplement);
+    }
+}
@@ -0,0 +1,176 @@
+use rusqlite::{params, Error, Result, OptionalExtension};
+
+use super::SqliteBibDB;
+
+/// A collection with the number of items directly in it
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub item_no: i64,
+}
+
+/// split a collection path like thesis/ch3 into collection names
+pub fn split_path(path: &str) -> Vec<&str> {
+    path.split('/').map(|x| x.trim()).filter(|x| !x.is_empty()).collect()
+}
+
+impl SqliteBibDB {
+    fn child_collection(&self, parent: Option<i64>, name: &str) -> Result<Option<i64>> {
+        self.conn.query_row("SELECT id FROM collections WHERE parent_id IS ? AND name=?", params![parent, name],
+            |row| row.get(0)).optional()
+    }
+
+    /// id of the collection at the path, None if any part of the path does not exist
+    pub fn find_collection(&self, path: &str) -> Result<Option<i64>> {
+        let mut parent: Option<i64> = None;
+        for name in split_path(path) {
+            parent = match self.child_collection(parent, name)? {
+                Some(id) => Some(id),
+                None => return Ok(None),
+            };
+        }
+        Ok(parent)
+    }
+
+    /// id of the collection at the path, creating it and its parents if they don't exist
+    pub fn create_collection(&self, path: &str) -> Result<i64> {
+        let mut parent: Option<i64> = None;
+        for name in split_path(path) {
+            parent = Some(match self.child_collection(parent, name)? {
+                Some(id) => id,
+                None => {
+                    self.conn.execute("INSERT INTO collections (name, parent_id) VALUES (?, ?)", params![name, parent])?;
+                    self.conn.last_insert_rowid()
+                }
+            });
+        }
+        parent.ok_or_else(|| Error::InvalidParameterName(path.to_owned()))
+    }
+
+    /// full path of a collection
+    pub fn collection_path(&self, id: i64) -> Result<String> {
+        let mut query = self.conn.prepare_cached("
+            WITH RECURSIVE ancestor(id, name, parent_id, depth) AS (
+                 SELECT id, name, parent_id, 0 FROM collections WHERE id=?
+                  UNION
+                 SELECT collections.id, collections.name, collections.parent_id, depth + 1
+                   FROM collections JOIN ancestor ON collections.id=ancestor.parent_id)
+            SELECT name FROM ancestor ORDER BY depth DESC")?;
+        let names = query.query_map(&[&id], |row| row.get::<_, String>(0))?.collect::<Result<Vec<String>>>()?;
+        Ok(names.join("/"))
+    }
+
+    /// subcollections of a collection, or the top level collections if parent is None
+    pub fn list_collections(&self, parent: Option<i64>) -> Result<Vec<Collection>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT id, name, (SELECT count(*) FROM item_collections WHERE collection_id=collections.id)
+              FROM collections
+             WHERE parent_id IS ?
+             ORDER BY name")?;
+        let collections = query.query_map(&[&parent], |row| Ok(Collection{
+            id: row.get_unwrap(0), name: row.get_unwrap(1), item_no: row.get_unwrap(2)}))?
+            .collect::<Result<Vec<Collection>>>();
+        collections
+    }
+
+    /// citations of items directly in a collection
+    pub fn collection_items(&self, id: i64) -> Result<Vec<String>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT item_id FROM item_collections WHERE collection_id=? ORDER BY item_id")?;
+        let citations = query.query_map(&[&id], |row| row.get::<_, String>(0))?.collect::<Result<Vec<String>>>();
+        citations
+    }
+
+    /// paths of the collections an item is directly in, in the order it was put in them
+    pub fn item_collection_paths(&self, citation: &str) -> Result<Vec<String>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT collection_id FROM item_collections WHERE item_id=? ORDER BY rowid")?;
+        let ids = query.query_map(&[&citation], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<i64>>>()?;
+        ids.into_iter().map(|x| self.collection_path(x)).collect()
+    }
+
+    /// collection ids of the collection and all its subcollections
+    fn collection_tree(&self, id: i64) -> Result<Vec<i64>> {
+        let mut query = self.conn.prepare_cached("
+            WITH RECURSIVE sub(id) AS (
+                 SELECT ?
+                  UNION
+                 SELECT collections.id FROM collections JOIN sub ON collections.parent_id=sub.id)
+            SELECT id FROM sub")?;
+        let ids = query.query_map(&[&id], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<i64>>>();
+        ids
+    }
+
+    /// delete a collection with its subcollections, the items themselves are kept
+    pub fn remove_collection(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM collections WHERE id=?", &[&id]).map(|_| ())
+    }
+
+    /// move and/or rename a collection to a new path, creating the new parents if needed
+    pub fn move_collection(&self, id: i64, to: &str) -> Result<()> {
+        let names = split_path(to);
+        let (name, parent_names) = names.split_last().ok_or_else(|| Error::InvalidParameterName(to.to_owned()))?;
+        let transaction = self.conn.unchecked_transaction()?;
+        let parent = if parent_names.is_empty() { None } else { Some(self.create_collection(&parent_names.join("/"))?) };
+        if let Some(parent_id) = parent {
+            if self.collection_tree(id)?.contains(&parent_id) {
+                return Err(Error::InvalidParameterName(to.to_owned()));
+            }
+        }
+        self.conn.execute("UPDATE collections SET name=?, parent_id=? WHERE id=?", params![name, parent, id])?;
+        transaction.commit()
+    }
+
+    pub fn add_to_collection(&self, id: i64, citation: &str) -> Result<()> {
+        let changed = self.conn.execute("INSERT OR IGNORE INTO item_collections (item_id, collection_id) VALUES (?, ?)",
+            params![citation, id])?;
+        if changed > 0 { self.log_history(citation, "add_collection", Some("collection"), None,
+                                          Some(&self.collection_path(id)?))?; }
+        Ok(())
+    }
+
+    pub fn remove_from_collection(&self, id: i64, citation: &str) -> Result<()> {
+        let changed = self.conn.execute("DELETE FROM item_collections WHERE item_id=? AND collection_id=?",
+            params![citation, id])?;
+        if changed > 0 { self.log_history(citation, "del_collection", Some("collection"),
+                                          Some(&self.collection_path(id)?), None)?; }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::{BibDataBase, SearchFilter};
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_collection() {
+        let conn = SqliteBibDB::in_memory();
+        for entry in read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib")).iter() {
+            conn.add_item(entry, None).unwrap();
+        }
+        let thesis = conn.create_collection("thesis").unwrap();
+        let chapter = conn.create_collection("thesis/ch3").unwrap();
+        assert_eq!(conn.find_collection(" thesis / ch3 ").unwrap(), Some(chapter));
+        assert_eq!(conn.find_collection("thesis/ch4").unwrap(), None);
+        conn.add_to_collection(thesis, "einstein").unwrap();
+        conn.add_to_collection(chapter, "latexcompanion").unwrap();
+        conn.add_to_collection(thesis, "latexcompanion").unwrap();
+        let filter = SearchFilter{collection: Some(thesis), ..Default::default()};
+        assert_eq!(conn.search_by(&filter).unwrap().len(), 2);
+        let filter = SearchFilter{collection: Some(chapter), ..Default::default()};
+        assert_eq!(conn.search_by(&filter).unwrap()[0].citation, "latexcompanion");
+        assert!(conn.move_collection(chapter, "thesis").is_err());
+        conn.move_collection(chapter, "grant2024/aim1").unwrap();
+        assert_eq!(conn.collection_path(chapter).unwrap(), "grant2024/aim1");
+        assert!(conn.move_collection(thesis, "thesis/inner").is_err());
+        let grant = conn.find_collection("grant2024").unwrap().unwrap();
+        assert!(conn.move_collection(grant, "grant2024/aim1/self").is_err());
+        conn.remove_collection(grant).unwrap();
+        assert_eq!(conn.find_collection("grant2024/aim1").unwrap(), None);
+        assert_eq!(conn.get_item("latexcompanion").unwrap().citation, "latexcompanion");
+        assert_eq!(conn.list_collections(None).unwrap().len(), 1);
+    }
+}
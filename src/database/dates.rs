@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fs::metadata;
+use std::time::UNIX_EPOCH;
+
+use rusqlite::{params, Result, NO_PARAMS};
+
+use crate::file::{File, BibFile};
+use super::SqliteBibDB;
+
+impl SqliteBibDB {
+    /// unix timestamp of the start of a local date like 2026-10-19, None if it is not a date
+    pub fn parse_date(&self, date: &str) -> Result<Option<i64>> {
+        self.conn.query_row("SELECT CAST(strftime('%s', date(?), 'utc') AS INTEGER)", &[date.trim()],
+                            |row| row.get(0))
+    }
+
+    /// local date of a unix timestamp, like 2026-10-19
+    pub fn local_date(&self, timestamp: i64) -> Result<String> {
+        self.conn.query_row("SELECT date(?, 'unixepoch', 'localtime')", &[&timestamp], |row| row.get(0))
+    }
+
+    /// citations of the items without date added, kept from before dates were recorded
+    pub fn undated_items(&self) -> Result<Vec<String>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT citation FROM items WHERE added_at IS NULL ORDER BY citation")?;
+        let citations = query.query_map(NO_PARAMS, |row| row.get::<_, String>(0))?.collect::<Result<Vec<String>>>();
+        citations
+    }
+
+    /// date an item from before dates were recorded, the dates it already has are kept
+    pub fn backfill_dates(&self, citation: &str, timestamp: i64) -> Result<()> {
+        self.conn.execute("
+            UPDATE items
+               SET added_at=COALESCE(added_at, ?1), modified_at=COALESCE(modified_at, ?1)
+             WHERE citation=?2", params![timestamp, citation]).map(|_| ())
+    }
+
+    /// date the undated items by the modification time of their oldest file, the number dated
+    pub(super) fn date_by_files(&self) -> Result<usize> {
+        let undated = self.undated_items()?;
+        if undated.is_empty() { return Ok(0) }
+        let mut oldest: HashMap<String, i64> = HashMap::new();
+        for (citation, attachment) in self.all_attachments()? {
+            let file = File::new(&attachment.name, &attachment.object_type);
+            if let Ok(modified) = metadata(file.path()).and_then(|x| x.modified()) {
+                let modified = modified.duration_since(UNIX_EPOCH).map(|x| x.as_secs() as i64).unwrap_or_default();
+                let time = oldest.entry(citation).or_insert(modified);
+                *time = (*time).min(modified);
+            }
+        }
+        let transaction = self.conn.unchecked_transaction()?;
+        let mut dated = 0;
+        for citation in undated.iter() {
+            if let Some(time) = oldest.get(citation) {
+                self.backfill_dates(citation, *time)?;
+                dated += 1;
+            }
+        }
+        transaction.commit()?;
+        Ok(dated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::{BibDataBase, SearchFilter};
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_dates() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        conn.add_item(&entries[0], None).unwrap();
+        let mut old = entries[1].clone();
+        old.added_at = Some(1_000_000_000);
+        old.modified_at = Some(1_000_000_000);
+        conn.add_item(&old, None).unwrap();
+        conn.conn.execute("UPDATE items SET added_at=NULL, modified_at=NULL WHERE citation='einstein'", NO_PARAMS)
+            .unwrap();
+        assert_eq!(conn.undated_items().unwrap(), vec!["einstein"]);
+        conn.backfill_dates("einstein", 1_500_000_000).unwrap();
+        assert!(conn.undated_items().unwrap().is_empty());
+        let saved = conn.get_item("latexcompanion").unwrap();
+        assert_eq!(saved.added_at, Some(1_000_000_000));
+        assert_eq!(saved.modified_at, Some(1_000_000_000));
+        let filter = SearchFilter{since: Some(1_200_000_000), ..Default::default()};
+        let found: Vec<String> = conn.search_by(&filter).unwrap().into_iter().map(|x| x.citation).collect();
+        assert_eq!(found, vec!["einstein"]);
+        let filter = SearchFilter{since: Some(1_200_000_000), modified: true, ..Default::default()};
+        assert_eq!(conn.search_by(&filter).unwrap().len(), 1);
+        assert_eq!(conn.parse_date("nonsense").unwrap(), None);
+        let day = conn.parse_date("2026-10-19").unwrap().unwrap();
+        assert_eq!(conn.local_date(day).unwrap(), "2026-10-19");
+    }
+}
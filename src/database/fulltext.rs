@@ -0,0 +1,118 @@
+use rusqlite::{params, Result, NO_PARAMS, OptionalExtension};
+
+use super::SqliteBibDB;
+
+/// marks around the matched terms in a snippet
+pub const MATCH_START: char = '\u{2}';
+pub const MATCH_END: char = '\u{3}';
+
+/// A page of an attached file matching a full-text search
+pub struct FulltextHit {
+    pub citation: String,
+    /// starting from 1, comment files have a single page
+    pub page: i64,
+    /// text around the match, matched terms between MATCH_START and MATCH_END
+    pub snippet: String,
+}
+
+impl SqliteBibDB {
+    /// every file record in the library
+    /// Returns:
+    ///     Vec<(item_id, name, object_type)>
+    pub fn all_files(&self) -> Result<Vec<(String, String, String)>> {
+        let mut query = self.conn.prepare_cached("SELECT item_id, name, object_type FROM files ORDER BY item_id")?;
+        let files = query.query_map(NO_PARAMS, |row| Ok((row.get_unwrap(0), row.get_unwrap(1), row.get_unwrap(2))))?
+            .collect::<Result<Vec<(String, String, String)>>>();
+        files
+    }
+
+    /// hash of the file content when the file was last indexed
+    pub fn fulltext_hash(&self, item_id: &str, name: &str) -> Result<Option<String>> {
+        self.conn.query_row("SELECT hash FROM fulltext_files WHERE item_id=? AND name=?", params![item_id, name],
+            |row| row.get(0)).optional()
+    }
+
+    /// whether the text of a file with this content is in the index
+    pub fn is_indexed(&self, hash: &str) -> Result<bool> {
+        self.conn.query_row("SELECT COUNT(*) FROM fulltext_documents WHERE hash=?", &[hash],
+            |row| row.get::<_, i64>(0)).map(|x| x > 0)
+    }
+
+    /// Store the text of every page of a file, pages without text are left out
+    pub fn index_pages<T: AsRef<str>>(&self, hash: &str, pages: &[T]) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        self.conn.execute("INSERT INTO fulltext_documents (hash, page_no) VALUES (?, ?)",
+            params![hash, pages.len() as i64])?;
+        let mut query = self.conn.prepare_cached("INSERT INTO fulltext_pages (body, hash, page) VALUES (?, ?, ?)")?;
+        for (idx, text) in pages.iter().enumerate() {
+            if !text.as_ref().trim().is_empty() { query.execute(params![text.as_ref(), hash, idx as i64 + 1])?; }
+        }
+        transaction.commit()
+    }
+
+    /// relate the indexed text to the file of an item
+    pub fn link_fulltext(&self, item_id: &str, name: &str, hash: &str) -> Result<()> {
+        self.conn.execute("REPLACE INTO fulltext_files (item_id, name, hash) VALUES (?, ?, ?)",
+            params![item_id, name, hash]).map(|_| ())
+    }
+
+    /// Drop the text of files no longer attached to any item
+    /// Returns:
+    ///     number of dropped files
+    pub fn prune_fulltext(&self) -> Result<usize> {
+        let transaction = self.conn.unchecked_transaction()?;
+        self.conn.execute("DELETE FROM fulltext_pages WHERE hash NOT IN (SELECT hash FROM fulltext_files)", NO_PARAMS)?;
+        let count = self.conn.execute(
+            "DELETE FROM fulltext_documents WHERE hash NOT IN (SELECT hash FROM fulltext_files)", NO_PARAMS)?;
+        transaction.commit()?;
+        Ok(count)
+    }
+
+    /// pages matching an FTS5 query, best matches first, all of them if the limit is negative
+    pub fn search_fulltext(&self, query: &str, limit: i64) -> Result<Vec<FulltextHit>> {
+        let mut statement = self.conn.prepare_cached("
+            SELECT fulltext_files.item_id, fulltext_pages.page,
+                   snippet(fulltext_pages, 0, char(2), char(3), '…', 16)
+              FROM fulltext_pages
+                   JOIN fulltext_files ON fulltext_pages.hash=fulltext_files.hash
+             WHERE fulltext_pages MATCH ?
+             ORDER BY bm25(fulltext_pages)
+             LIMIT ?")?;
+        let hits = statement.query_map(params![query, limit], |row| Ok(FulltextHit{
+            citation: row.get_unwrap(0),
+            page: row.get_unwrap(1),
+            snippet: row.get_unwrap(2),
+        }))?.collect::<Result<Vec<FulltextHit>>>();
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::{SqliteBibDB, BibDataBase};
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_fulltext() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        conn.add_item(&entries[0], None).unwrap();
+        conn.add_file("einstein", "einstein", "pdf").unwrap();
+        assert!(!conn.is_indexed("abc").unwrap());
+        conn.index_pages("abc", &["Zur Elektrodynamik bewegter Körper", "",
+                                  "the velocity of light in empty space is independent of the motion"]).unwrap();
+        conn.link_fulltext("einstein", "einstein", "abc").unwrap();
+        assert!(conn.is_indexed("abc").unwrap());
+        assert_eq!(conn.fulltext_hash("einstein", "einstein").unwrap(), Some("abc".to_owned()));
+        let hits = conn.search_fulltext("\"velocity of light\"", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].citation.as_str(), hits[0].page), ("einstein", 3));
+        assert!(hits[0].snippet.contains(&format!("{}velocity{}", MATCH_START, MATCH_END)));
+        assert_eq!(conn.prune_fulltext().unwrap(), 0);
+        conn.delete("einstein").unwrap();
+        assert_eq!(conn.prune_fulltext().unwrap(), 1);
+        assert!(conn.search_fulltext("velocity", 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use std::env;
+use std::fmt::Display;
+use itertools::Itertools;
+use rusqlite::{params, Result};
+
+use crate::model::Entry;
+use crate::formatter::bibtex::BibPrint;
+use super::SqliteBibDB;
+
+/// One change to an entry, as recorded in item_history
+pub struct HistoryRecord {
+    pub operation: String,
+    pub field: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    /// local time as YYYY-MM-DD HH:MM:SS
+    pub changed_at: String,
+    pub user: Option<String>,
+}
+
+fn opt_str<T: Display>(value: &Option<T>) -> Option<String> { value.as_ref().map(|x| format!("{}", x)) }
+
+fn non_empty(value: String) -> Option<String> { if value.is_empty() { None } else { Some(value) } }
+
+/// Fields that differ between two versions of an entry
+/// Returns:
+///     Vec<(field, old_value, new_value)>
+pub fn entry_diff(old: &Entry, new: &Entry) -> Vec<(String, Option<String>, Option<String>)> {
+    let mut fields: Vec<(&str, Option<String>, Option<String>)> = vec![
+        ("entry_type", Some(old.entry_type.to_string()), Some(new.entry_type.to_string())),
+        ("title", Some(old.title.clone()), Some(new.title.clone())),
+        ("booktitle", old.booktitle.clone(), new.booktitle.clone()),
+        ("year", Some(old.year.to_string()), Some(new.year.to_string())),
+        ("month", opt_str(&old.month), opt_str(&new.month)),
+        ("chapter", opt_str(&old.chapter), opt_str(&new.chapter)),
+        ("edition", opt_str(&old.edition), opt_str(&new.edition)),
+        ("volume", opt_str(&old.volume), opt_str(&new.volume)),
+        ("number", opt_str(&old.number), opt_str(&new.number)),
+        ("pages", old.pages.clone(), new.pages.clone()),
+        ("journal", old.journal.clone(), new.journal.clone()),
+        ("author", non_empty(old.authors.to_bib()), non_empty(new.authors.to_bib())),
+        ("editor", non_empty(old.editors.to_bib()), non_empty(new.editors.to_bib())),
+        ("keywords", non_empty(old.keywords.to_bib()), non_empty(new.keywords.to_bib())),
+    ];
+    for field in old.extra_fields.keys().chain(new.extra_fields.keys()).map(|x| x.as_str()).unique().sorted() {
+        fields.push((field, old.extra_fields.get(field).cloned(), new.extra_fields.get(field).cloned()));
+    }
+    fields.into_iter().filter(|(_, old_value, new_value)| old_value != new_value)
+        .map(|(field, old_value, new_value)| (field.to_owned(), old_value, new_value)).collect()
+}
+
+impl SqliteBibDB {
+    /// record a change to an item, made by the current $USER, which also dates the item as modified
+    pub(super) fn log_history(&self, item_id: &str, operation: &str, field: Option<&str>, old_value: Option<&str>,
+                              new_value: Option<&str>) -> Result<()> {
+        let mut query = self.conn.prepare_cached("
+            INSERT INTO item_history (item_id, operation, field, old_value, new_value, user)
+            VALUES (?, ?, ?, ?, ?, ?)")?;
+        query.execute(params![item_id, operation, field, old_value, new_value, env::var("USER").ok()])?;
+        self.conn.execute("UPDATE items SET modified_at=strftime('%s', 'now') WHERE citation=?", &[item_id])
+            .map(|_| ())
+    }
+
+    /// all recorded changes of an item, oldest first
+    pub fn get_history(&self, item_id: &str) -> Result<Vec<HistoryRecord>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT operation, field, old_value, new_value, datetime(changed_at, 'unixepoch', 'localtime'), user
+              FROM item_history
+             WHERE item_id=?
+             ORDER BY id")?;
+        let records = query.query_map(&[&item_id], |row| Ok(HistoryRecord{
+            operation: row.get_unwrap(0),
+            field: row.get_unwrap(1),
+            old_value: row.get_unwrap(2),
+            new_value: row.get_unwrap(3),
+            changed_at: row.get_unwrap(4),
+            user: row.get_unwrap(5),
+        }))?.collect::<Result<Vec<HistoryRecord>>>();
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::BibDataBase;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let conn = SqliteBibDB::in_memory();
+        let entry = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"))[1].clone();
+        conn.add_item(&entry, None).unwrap();
+        let mut new_entry = entry.clone();
+        new_entry.title = "The LaTeX Companion".to_owned();
+        new_entry.extra_fields.insert("edition_note".to_owned(), "first".to_owned());
+        conn.update_item(&new_entry, None).unwrap();
+        conn.add_keywords("latexcompanion", &["typesetting"]).unwrap();
+        let history = conn.get_history("latexcompanion").unwrap();
+        let operations = history.iter().map(|x| x.operation.as_str()).collect::<Vec<&str>>();
+        assert_eq!(operations, vec!["add", "edit", "edit", "add_keyword"]);
+        assert_eq!(history[1].field, Some("title".to_owned()));
+        assert_eq!(history[1].new_value, Some("The LaTeX Companion".to_owned()));
+        assert_eq!(history[2].field, Some("edition_note".to_owned()));
+        assert_eq!(history[2].old_value, None);
+        assert_eq!(history[3].new_value, Some("typesetting".to_owned()));
+    }
+
+    #[test]
+    fn test_entry_diff() {
+        let entry = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"))[0].clone();
+        assert!(entry_diff(&entry, &entry).is_empty());
+        let mut other = entry.clone();
+        other.volume = None;
+        assert_eq!(entry_diff(&entry, &other), vec![("volume".to_owned(), Some("322".to_owned()), None)]);
+    }
+}
@@ -0,0 +1,109 @@
+use rusqlite::{params, Result, OptionalExtension, NO_PARAMS};
+
+use crate::identifier::{Identifier, IdType};
+use crate::model::Entry;
+use super::SqliteBibDB;
+
+impl SqliteBibDB {
+    /// index the identifiers found in the fields of the entry, to be called within a transaction
+    pub(super) fn insert_identifiers(&self, entry: &Entry) -> Result<()> {
+        let mut query = self.conn.prepare_cached(
+            "INSERT INTO identifiers (id_type, value, item_id) VALUES (?, ?, ?)")?;
+        for identifier in Identifier::from_entry(entry) {
+            query.execute(params![identifier.id_type.to_string(), identifier.value, entry.citation])?;
+        }
+        Ok(())
+    }
+
+    /// citation of the item with the identifier
+    pub fn find_identifier(&self, identifier: &Identifier) -> Result<Option<String>> {
+        self.conn.query_row("SELECT item_id FROM identifiers WHERE id_type=? AND value=?",
+            params![identifier.id_type.to_string(), identifier.value], |row| row.get(0)).optional()
+    }
+
+    /// Citation of another item sharing an identifier with the entry, the same paper under another key.
+    /// An ISBN only stands for a whole book, chapters and proceedings papers share the one of their volume.
+    pub fn find_duplicate(&self, entry: &Entry) -> Result<Option<String>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT item_id
+              FROM identifiers
+                   JOIN items
+                     ON item_id=citation
+             WHERE id_type=? AND value=? AND item_id<>?
+               AND (id_type<>'isbn' OR entry_type NOT IN ('inbook', 'incollection', 'inproceedings'))
+             LIMIT 1")?;
+        for identifier in Identifier::from_entry(entry) {
+            if identifier.id_type == IdType::Isbn && entry.entry_type.is_part() { continue }
+            let found = query.query_row(params![identifier.id_type.to_string(), identifier.value, entry.citation],
+                |row| row.get(0)).optional()?;
+            if found.is_some() { return Ok(found) }
+        }
+        Ok(None)
+    }
+
+    /// index the identifiers of all entries again, for the ones indexed before all kinds were known
+    pub(super) fn reindex_identifiers(&self) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        self.conn.execute("DELETE FROM identifiers", NO_PARAMS)?;
+        let citations = self.conn.prepare("SELECT citation FROM items")?
+            .query_map(NO_PARAMS, |row| row.get::<_, String>(0))?.collect::<Result<Vec<String>>>()?;
+        for citation in citations.iter() { self.insert_identifiers(&self.get_item(citation)?)?; }
+        transaction.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use rusqlite::NO_PARAMS;
+    use crate::database::{SqliteBibDB, BibDataBase};
+    use crate::entry_type::EntryType;
+    use crate::identifier::Identifier;
+    use crate::reader::bibtex::read_entries;
+
+    #[test]
+    fn test_identifiers() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        let mut entry = entries[0].clone();
+        entry.extra_fields.insert("doi".to_owned(), "http://dx.doi.org/10.1002/andp.19053221004".to_owned());
+        conn.add_item(&entry, None).unwrap();
+        let doi = Identifier::parse("doi:10.1002/ANDP.19053221004").unwrap();
+        assert_eq!(conn.find_identifier(&doi).unwrap(), Some("einstein".to_owned()));
+        let mut duplicate = entry.clone();
+        duplicate.citation = "einstein1905".to_owned();
+        assert_eq!(conn.find_duplicate(&duplicate).unwrap(), Some("einstein".to_owned()));
+        assert_eq!(conn.find_duplicate(&entries[1]).unwrap(), None);
+        // an enriched entry may get the DOI of an entry kept under another key
+        let mut other = entries[1].clone();
+        conn.add_item(&other, None).unwrap();
+        other.extra_fields.insert("doi".to_owned(), "10.1002/andp.19053221004".to_owned());
+        conn.update_item(&other, None).unwrap();
+        conn.delete(&other.citation).unwrap();
+        let mut book = entries[1].clone();
+        book.extra_fields.insert("isbn".to_owned(), "0-306-40615-2".to_owned());
+        let mut chapter = book.clone();
+        chapter.citation = "companion-ch2".to_owned();
+        chapter.entry_type = EntryType::Incollection;
+        conn.add_item(&chapter, None).unwrap();
+        assert_eq!(conn.find_duplicate(&book).unwrap(), None);
+        let mut other_chapter = chapter.clone();
+        other_chapter.citation = "companion-ch3".to_owned();
+        assert_eq!(conn.find_duplicate(&other_chapter).unwrap(), None);
+        conn.add_item(&book, None).unwrap();
+        let mut edition = book.clone();
+        edition.citation = "latexcompanion2".to_owned();
+        assert_eq!(conn.find_duplicate(&edition).unwrap(), Some("latexcompanion".to_owned()));
+        conn.rename("einstein", "einstein1905").unwrap();
+        assert_eq!(conn.find_identifier(&doi).unwrap(), Some("einstein1905".to_owned()));
+        entry.citation = "einstein1905".to_owned();
+        entry.extra_fields.remove("doi");
+        conn.update_item(&entry, None).unwrap();
+        assert_eq!(conn.find_identifier(&doi).unwrap(), None);
+        let isbn = Identifier::parse("isbn:0-306-40615-2").unwrap();
+        conn.conn.execute("DELETE FROM identifiers", NO_PARAMS).unwrap();
+        assert_eq!(conn.find_identifier(&isbn).unwrap(), None);
+        conn.reindex_identifiers().unwrap();
+        assert!(conn.find_identifier(&isbn).unwrap().is_some());
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+use rusqlite::{params, Error, Result, OptionalExtension, NO_PARAMS};
+
+use super::SqliteBibDB;
+
+impl SqliteBibDB {
+    /// Replace aliases by the keywords they stand for, dropping duplicates
+    pub fn resolve_keywords<T: AsRef<str>>(&self, terms: &[T]) -> Result<Vec<String>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT text
+              FROM keyword_aliases
+                   JOIN keywords ON keyword_aliases.keyword_id=keywords.id
+             WHERE alias=?")?;
+        let mut output: Vec<String> = Vec::new();
+        for term in terms.iter() {
+            let resolved = query.query_row(&[term.as_ref()], |row| row.get::<_, String>(0)).optional()?
+                .unwrap_or_else(|| term.as_ref().to_owned());
+            if !output.contains(&resolved) { output.push(resolved); }
+        }
+        Ok(output)
+    }
+
+    pub fn keyword_id(&self, text: &str) -> Result<Option<i64>> {
+        self.conn.query_row("SELECT id FROM keywords WHERE text=?", &[text], |row| row.get(0)).optional()
+    }
+
+    /// id of the keyword, creating it if it doesn't exist
+    fn create_keyword(&self, text: &str) -> Result<i64> {
+        self.conn.execute("INSERT OR IGNORE INTO keywords (text) VALUES (?)", &[text])?;
+        self.conn.query_row("SELECT id FROM keywords WHERE text=?", &[text], |row| row.get(0))
+    }
+
+    fn keyword_items(&self, keyword_id: i64) -> Result<Vec<String>> {
+        let mut query = self.conn.prepare_cached("SELECT item_id FROM item_keywords WHERE keyword_id=?")?;
+        let items = query.query_map(&[&keyword_id], |row| row.get::<_, String>(0))?.collect::<Result<Vec<String>>>();
+        items
+    }
+
+    /// every keyword in use with the number of entries tagged with it, most used first
+    pub fn keyword_counts(&self) -> Result<Vec<(String, i64)>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT text, count(item_id) AS item_no
+              FROM keywords
+                   LEFT JOIN item_keywords ON item_keywords.keyword_id=keywords.id
+             GROUP BY keywords.id
+             ORDER BY item_no DESC, text")?;
+        let counts = query.query_map(NO_PARAMS, |row| Ok((row.get_unwrap(0), row.get_unwrap(1))))?
+            .collect::<Result<Vec<(String, i64)>>>();
+        counts
+    }
+
+    /// Rename a keyword on every entry. If the new name is already a keyword, the two are merged.
+    pub fn rename_keyword(&self, old: &str, new: &str) -> Result<usize> {
+        let old_id = match self.keyword_id(old)? { Some(x) => x, None => return Ok(0) };
+        if self.keyword_id(new)?.is_some() { return self.merge_keywords(new, &[old]) }
+        let transaction = self.conn.unchecked_transaction()?;
+        let items = self.keyword_items(old_id)?;
+        self.conn.execute("UPDATE keywords SET text=? WHERE id=?", params![new, old_id])?;
+        self.conn.execute("DELETE FROM keyword_aliases WHERE alias=?", &[new])?;
+        for item in items.iter() { self.log_history(item, "rename_keyword", Some("keyword"), Some(old), Some(new))?; }
+        transaction.commit()?;
+        Ok(items.len())
+    }
+
+    /// Move every entry tagged with one of the sources to the target keyword, and keep the sources
+    /// as aliases of the target.
+    /// Returns:
+    ///     number of entries retagged
+    pub fn merge_keywords<T: AsRef<str>>(&self, target: &str, sources: &[T]) -> Result<usize> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let target_id = self.create_keyword(target)?;
+        let mut retagged = 0;
+        for source in sources.iter().map(|x| x.as_ref()).filter(|x| *x != target) {
+            if let Some(source_id) = self.keyword_id(source)? {
+                let items = self.keyword_items(source_id)?;
+                self.conn.execute("
+                    INSERT OR IGNORE INTO item_keywords (item_id, keyword_id)
+                    SELECT item_id, ? FROM item_keywords WHERE keyword_id=?", params![target_id, source_id])?;
+                self.conn.execute("UPDATE keyword_aliases SET keyword_id=? WHERE keyword_id=?",
+                    params![target_id, source_id])?;
+                self.conn.execute("UPDATE keywords SET parent_id=? WHERE parent_id=? AND id<>?",
+                    params![target_id, source_id, target_id])?;
+                self.conn.execute("DELETE FROM item_keywords WHERE keyword_id=?", &[&source_id])?;
+                self.conn.execute("DELETE FROM keywords WHERE id=?", &[&source_id])?;
+                for item in items.iter() {
+                    self.log_history(item, "merge_keyword", Some("keyword"), Some(source), Some(target))?;
+                }
+                retagged += items.len();
+            }
+            self.conn.execute("REPLACE INTO keyword_aliases (alias, keyword_id) VALUES (?, ?)",
+                params![source, target_id])?;
+        }
+        transaction.commit()?;
+        Ok(retagged)
+    }
+
+    /// Make the alias stand for the keyword when adding, deleting or searching. An existing keyword
+    /// with the alias as its text is merged into the keyword.
+    pub fn add_alias(&self, keyword: &str, alias: &str) -> Result<()> {
+        self.merge_keywords(keyword, &[alias]).map(|_| ())
+    }
+
+    pub fn remove_alias(&self, alias: &str) -> Result<usize> {
+        self.conn.execute("DELETE FROM keyword_aliases WHERE alias=?", &[alias])
+    }
+
+    /// keyword ids of the keyword and all its descendants
+    fn keyword_subtree(&self, id: i64) -> Result<Vec<i64>> {
+        let mut query = self.conn.prepare_cached("
+            WITH RECURSIVE sub(id) AS (
+                 SELECT ?
+                  UNION
+                 SELECT keywords.id FROM keywords JOIN sub ON keywords.parent_id=sub.id)
+            SELECT id FROM sub")?;
+        let ids = query.query_map(&[&id], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<i64>>>();
+        ids
+    }
+
+    /// Put the keyword under the parent in the taxonomy, or at the top level if parent is None.
+    /// Both keywords are created if needed. A keyword cannot be put under its own descendant.
+    pub fn set_keyword_parent(&self, keyword: &str, parent: Option<&str>) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let keyword_id = self.create_keyword(&self.resolve_keywords(&[keyword])?[0])?;
+        let parent_id = match parent {
+            Some(parent) => {
+                let parent_id = self.create_keyword(&self.resolve_keywords(&[parent])?[0])?;
+                if self.keyword_subtree(keyword_id)?.contains(&parent_id) {
+                    return Err(Error::InvalidParameterName(parent.to_owned()));
+                }
+                Some(parent_id)
+            },
+            None => None,
+        };
+        self.conn.execute("UPDATE keywords SET parent_id=? WHERE id=?", params![parent_id, keyword_id])?;
+        transaction.commit()
+    }
+
+    /// keywords implied by the given ones through the taxonomy, not including the given ones
+    pub fn keyword_ancestors<T: AsRef<str>>(&self, terms: &[T]) -> Result<HashSet<String>> {
+        let mut query = self.conn.prepare_cached("
+            WITH RECURSIVE ancestor(id) AS (
+                 SELECT parent_id FROM keywords WHERE text=? AND parent_id IS NOT NULL
+                  UNION
+                 SELECT keywords.parent_id
+                   FROM keywords JOIN ancestor ON keywords.id=ancestor.id
+                  WHERE keywords.parent_id IS NOT NULL)
+            SELECT text FROM keywords WHERE id IN (SELECT id FROM ancestor)")?;
+        let mut output: HashSet<String> = HashSet::new();
+        for term in terms.iter() {
+            for text in query.query_map(&[term.as_ref()], |row| row.get::<_, String>(0))? { output.insert(text?); }
+        }
+        for term in terms.iter() { output.remove(term.as_ref()); }
+        Ok(output)
+    }
+
+    /// children of a keyword in the taxonomy, or the top level keywords if parent is None
+    /// Returns:
+    ///     Vec<(id, text, number of entries directly tagged)>
+    pub fn keyword_children(&self, parent: Option<i64>) -> Result<Vec<(i64, String, i64)>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT id, text, (SELECT count(*) FROM item_keywords WHERE keyword_id=keywords.id)
+              FROM keywords
+             WHERE parent_id IS ?
+             ORDER BY text")?;
+        let children = query.query_map(&[&parent], |row| Ok((row.get_unwrap(0), row.get_unwrap(1), row.get_unwrap(2))))?
+            .collect::<Result<Vec<(i64, String, i64)>>>();
+        children
+    }
+
+    /// all aliases with the keywords they stand for
+    pub fn list_aliases(&self) -> Result<Vec<(String, String)>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT alias, text
+              FROM keyword_aliases
+                   JOIN keywords ON keyword_aliases.keyword_id=keywords.id
+             ORDER BY text, alias")?;
+        let aliases = query.query_map(NO_PARAMS, |row| Ok((row.get_unwrap(0), row.get_unwrap(1))))?
+            .collect::<Result<Vec<(String, String)>>>();
+        aliases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use itertools::Itertools;
+    use crate::database::{BibDataBase, SearchFilter};
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_keyword_management() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        for entry in entries.iter() { conn.add_item(entry, None).unwrap(); }
+        conn.add_keywords("einstein", &["visual cortex"]).unwrap();
+        conn.add_keywords("latexcompanion", &["visual-cortex", "v1"]).unwrap();
+        conn.add_keywords("knuthwebsite", &["v1"]).unwrap();
+        assert_eq!(conn.keyword_counts().unwrap()[0], ("v1".to_owned(), 2));
+        assert_eq!(conn.merge_keywords("visual cortex", &["visual-cortex", "v1"]).unwrap(), 3);
+        assert_eq!(conn.keyword_counts().unwrap(), vec![("visual cortex".to_owned(), 3)]);
+        assert_eq!(conn.resolve_keywords(&["v1", "visual-cortex", "cortex"]).unwrap(),
+                   vec!["visual cortex".to_owned(), "cortex".to_owned()]);
+        conn.add_keywords("knuthwebsite", &["visual-cortex"]).unwrap();
+        assert_eq!(conn.get_keywords("knuthwebsite"), vec!["visual cortex".to_owned()]);
+        assert_eq!(conn.search(&[], &["v1".to_owned()]).unwrap().len(), 3);
+        assert_eq!(conn.rename_keyword("visual cortex", "visual-cortex").unwrap(), 3);
+        assert_eq!(conn.resolve_keywords(&["v1"]).unwrap(), vec!["visual-cortex".to_owned()]);
+        assert_eq!(conn.list_aliases().unwrap(), vec![("v1".to_owned(), "visual-cortex".to_owned())]);
+        assert_eq!(conn.remove_alias("v1").unwrap(), 1);
+        assert!(conn.list_aliases().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_keyword_taxonomy() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        for entry in entries.iter() { conn.add_item(entry, None).unwrap(); }
+        conn.add_keywords("einstein", &["v1"]).unwrap();
+        conn.add_keywords("latexcompanion", &["visual cortex"]).unwrap();
+        conn.add_keywords("knuthwebsite", &["cortex"]).unwrap();
+        conn.set_keyword_parent("v1", Some("visual cortex")).unwrap();
+        conn.set_keyword_parent("visual cortex", Some("cortex")).unwrap();
+        assert!(conn.set_keyword_parent("cortex", Some("v1")).is_err());
+        let ancestors = conn.keyword_ancestors(&["v1"]).unwrap();
+        assert_eq!(ancestors.into_iter().sorted().collect::<Vec<String>>(), vec!["cortex", "visual cortex"]);
+        assert!(conn.keyword_ancestors(&["v1", "visual cortex"]).unwrap().contains("cortex"));
+        let keywords = vec!["visual cortex".to_owned()];
+        assert_eq!(conn.search_by(&SearchFilter{keywords: keywords.clone(), ..Default::default()}).unwrap().len(), 1);
+        let filter = SearchFilter{keywords, expand: true, ..Default::default()};
+        assert_eq!(conn.search_by(&filter).unwrap().len(), 2);
+        conn.add_keywords("latexcompanion", &["v1"]).unwrap();
+        let filter = SearchFilter{keywords: vec!["cortex".to_owned()], expand: true, ..Default::default()};
+        assert_eq!(conn.search_by(&filter).unwrap().len(), 3);
+        conn.del_keywords("latexcompanion", &["visual cortex"]).unwrap();
+        let top = conn.keyword_children(None).unwrap();
+        assert_eq!(top.iter().map(|x| x.1.as_str()).collect::<Vec<&str>>(), vec!["cortex"]);
+        assert_eq!(conn.keyword_children(Some(top[0].0)).unwrap()[0].1, "visual cortex");
+        conn.set_keyword_parent("v1", None).unwrap();
+        assert!(conn.keyword_ancestors(&["v1"]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use rusqlite::{params, Result, OptionalExtension, NO_PARAMS};
+use rusqlite::Error::ToSqlConversionFailure;
+use serde_derive::{Serialize, Deserialize};
+
+use super::SqliteBibDB;
+
+/// A reversible change to the library, kept in the operations table for undo.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "operation")]
+pub enum Operation {
+    /// keywords added to and deleted from an entry
+    Keywords { citation: String, added: Vec<String>, deleted: Vec<String> },
+    /// entry overwritten by a new version, the old version is kept in the trash
+    Update { citation: String, trash_id: i64 },
+    Rename { from: String, to: String },
+    Delete { citation: String, trash_id: i64 },
+    /// keywords changed on each entry found by bibrs kw tag, one Keywords operation per entry
+    Tag { changes: Vec<Operation> },
+    RenameKeyword { from: String, to: String },
+    /// keywords merged into the target, the sources are only kept as aliases so it cannot be undone
+    MergeKeywords { target: String, sources: Vec<String> },
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Keywords{..} => "keywords",
+            Operation::Update{..} => "update",
+            Operation::Rename{..} => "rename",
+            Operation::Delete{..} => "delete",
+            Operation::Tag{..} => "tag",
+            Operation::RenameKeyword{..} => "rename_keyword",
+            Operation::MergeKeywords{..} => "merge_keywords",
+        }
+    }
+
+    /// citation of the entry the operation works on, or the keyword for changes to a keyword
+    pub fn citation(&self) -> &str {
+        match self {
+            Operation::Keywords{citation, ..} | Operation::Update{citation, ..} |
+                Operation::Delete{citation, ..} => citation,
+            Operation::Rename{to, ..} | Operation::RenameKeyword{to, ..} => to,
+            Operation::Tag{changes} => changes.first().map_or("", |x| x.citation()),
+            Operation::MergeKeywords{target, ..} => target,
+        }
+    }
+}
+
+impl SqliteBibDB {
+    pub fn record_operation(&self, operation: &Operation) -> Result<()> {
+        let payload = serde_json::to_string(operation).map_err(|err| ToSqlConversionFailure(Box::new(err)))?;
+        self.conn.execute("INSERT INTO operations (item_id, operation, payload) VALUES (?, ?, ?)",
+            params![operation.citation(), operation.name(), payload]).map(|_| ())
+    }
+
+    /// the most recent operation and its row id, None if nothing is left to undo
+    pub fn last_operation(&self) -> Result<Option<(i64, Operation)>> {
+        let last = self.conn.query_row("SELECT id, payload FROM operations ORDER BY id DESC LIMIT 1", NO_PARAMS,
+            |row| Ok((row.get_unwrap::<_, i64>(0), row.get_unwrap::<_, String>(1)))).optional()?;
+        match last {
+            Some((id, payload)) => {
+                let operation = serde_json::from_str(&payload)
+                    .map_err(|err| ToSqlConversionFailure(Box::new(err)))?;
+                Ok(Some((id, operation)))
+            },
+            None => Ok(None)
+        }
+    }
+
+    pub fn forget_operation(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM operations WHERE id=?", &[&id]).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_operation() {
+        let conn = SqliteBibDB::in_memory();
+        assert!(conn.last_operation().unwrap().is_none());
+        conn.record_operation(&Operation::Delete{citation: "einstein".to_owned(), trash_id: 3}).unwrap();
+        let rename = Operation::Rename{from: "einstein".to_owned(), to: "einstein1905".to_owned()};
+        conn.record_operation(&rename).unwrap();
+        let (id, operation) = conn.last_operation().unwrap().unwrap();
+        assert_eq!(operation, rename);
+        conn.forget_operation(id).unwrap();
+        let (_, operation) = conn.last_operation().unwrap().unwrap();
+        assert_eq!(operation, Operation::Delete{citation: "einstein".to_owned(), trash_id: 3});
+    }
+}
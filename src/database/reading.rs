@@ -0,0 +1,100 @@
+use rusqlite::{params, Result, Row, OptionalExtension};
+
+use crate::model::{Reading, ReadingStatus};
+use super::SqliteBibDB;
+
+impl From<&Row<'_>> for Reading {
+    fn from(row: &Row) -> Reading {
+        Reading{
+            status: ReadingStatus::parse(&row.get_unwrap::<_, String>(0)).unwrap_or(ReadingStatus::Unread),
+            priority: row.get_unwrap(1),
+            rating: row.get_unwrap(2),
+            marked_at: row.get_unwrap(3),
+            started_at: row.get_unwrap(4),
+            finished_at: row.get_unwrap(5),
+        }
+    }
+}
+
+fn opt_str(value: Option<i32>) -> Option<String> { value.map(|x| x.to_string()) }
+
+impl SqliteBibDB {
+    pub fn get_reading(&self, citation: &str) -> Result<Option<Reading>> {
+        self.conn.query_row("
+            SELECT status, priority, rating, marked_at, started_at, finished_at
+              FROM reading
+             WHERE item_id=?", &[citation], |row| Ok(Reading::from(row))).optional()
+    }
+
+    /// write the reading status of an item, without recording history
+    pub(super) fn insert_reading(&self, citation: &str, reading: &Reading) -> Result<()> {
+        self.conn.execute("
+            REPLACE INTO reading (item_id, status, priority, rating, marked_at, started_at, finished_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)", params![citation, reading.status.as_str(), reading.priority,
+            reading.rating, reading.marked_at, reading.started_at, reading.finished_at]).map(|_| ())
+    }
+
+    /// save the reading status of an item, the changes to status, priority and rating go to the history
+    pub fn set_reading(&self, citation: &str, reading: &Reading) -> Result<()> {
+        let old = self.get_reading(citation)?;
+        let transaction = self.conn.unchecked_transaction()?;
+        self.insert_reading(citation, reading)?;
+        let changes = [
+            ("status", old.as_ref().map(|x| x.status.as_str().to_owned()), Some(reading.status.as_str().to_owned())),
+            ("priority", old.as_ref().and_then(|x| opt_str(x.priority)), opt_str(reading.priority)),
+            ("rating", old.as_ref().and_then(|x| opt_str(x.rating)), opt_str(reading.rating)),
+        ];
+        for (field, old_value, new_value) in changes.iter().filter(|x| x.1 != x.2) {
+            self.log_history(citation, "mark", Some(*field), old_value.as_deref(), new_value.as_deref())?;
+        }
+        transaction.commit()
+    }
+
+    /// keep the reading status of an item going to the trash
+    pub(super) fn trash_reading(&self, trash_id: i64, citation: &str) -> Result<()> {
+        self.conn.execute("
+            INSERT INTO trash_reading (trash_id, status, priority, rating, marked_at, started_at, finished_at)
+            SELECT ?, status, priority, rating, marked_at, started_at, finished_at FROM reading WHERE item_id=?",
+            params![trash_id, citation]).map(|_| ())
+    }
+
+    /// put the reading status of a trashed item back
+    pub(super) fn restore_reading(&self, trash_id: i64, citation: &str) -> Result<()> {
+        self.conn.execute("
+            REPLACE INTO reading (item_id, status, priority, rating, marked_at, started_at, finished_at)
+            SELECT ?, status, priority, rating, marked_at, started_at, finished_at FROM trash_reading WHERE trash_id=?",
+            params![citation, trash_id]).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::BibDataBase;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_reading() {
+        let conn = SqliteBibDB::in_memory();
+        let entry = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"))[0].clone();
+        conn.add_item(&entry, None).unwrap();
+        assert_eq!(conn.get_reading("einstein").unwrap(), None);
+        let mut reading = Reading::new(100);
+        reading.priority = Some(1);
+        conn.set_reading("einstein", &reading).unwrap();
+        reading.set_status(ReadingStatus::Reading, 200);
+        reading.set_status(ReadingStatus::Read, 300);
+        reading.rating = Some(4);
+        conn.set_reading("einstein", &reading).unwrap();
+        let saved = conn.get_item("einstein").unwrap().reading.unwrap();
+        assert_eq!((saved.started_at, saved.finished_at, saved.rating), (Some(200), Some(300), Some(4)));
+        let marks: Vec<String> = conn.get_history("einstein").unwrap().into_iter()
+            .filter(|x| x.operation == "mark").filter_map(|x| x.field).collect();
+        assert_eq!(marks, vec!["status", "priority", "status", "rating"]);
+        let trash_id = conn.trash_item("einstein").unwrap();
+        assert_eq!(conn.get_reading("einstein").unwrap(), None);
+        conn.restore_trash(trash_id).unwrap();
+        assert_eq!(conn.get_reading("einstein").unwrap(), Some(saved));
+    }
+}
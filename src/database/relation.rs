@@ -0,0 +1,170 @@
+use rusqlite::{params, Result};
+
+use crate::metadata::title_key;
+use super::SqliteBibDB;
+
+/// How one item relates to another, read as ITEM relation OTHER
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Relation {
+    Cites,
+    ErratumOf,
+    PreprintOf,
+    CommentaryOn,
+}
+
+impl Relation {
+    /// Read a relation like cites, erratum-of or "preprint of". The reversed relations, like cited-by,
+    /// come with true to swap the two items.
+    pub fn parse(input: &str) -> Option<(Relation, bool)> {
+        let input = input.trim().to_lowercase().replace(&['-', '_'][..], " ");
+        match input.strip_prefix("is ").unwrap_or(input.as_str()) {
+            "cites" => Some((Relation::Cites, false)),
+            "cited by" => Some((Relation::Cites, true)),
+            "erratum" | "erratum of" => Some((Relation::ErratumOf, false)),
+            "preprint" | "preprint of" => Some((Relation::PreprintOf, false)),
+            "commentary" | "commentary on" => Some((Relation::CommentaryOn, false)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Relation::Cites => "cites",
+            Relation::ErratumOf => "erratum of",
+            Relation::PreprintOf => "preprint of",
+            Relation::CommentaryOn => "commentary on",
+        }
+    }
+
+    /// the relation read from the other item, as OTHER inverse ITEM
+    pub fn inverse_str(&self) -> &'static str {
+        match self {
+            Relation::Cites => "cited by",
+            Relation::ErratumOf => "corrected by",
+            Relation::PreprintOf => "published as",
+            Relation::CommentaryOn => "commented on by",
+        }
+    }
+}
+
+/// A relation of an item to another, incoming if the other item is the one relating to it
+#[derive(Debug, PartialEq, Clone)]
+pub struct ItemRelation {
+    pub relation: Relation,
+    pub other: String,
+    pub incoming: bool,
+}
+
+impl ItemRelation {
+    /// the relation as read from the item, like cites or cited by
+    pub fn label(&self) -> &'static str {
+        if self.incoming { self.relation.inverse_str() } else { self.relation.as_str() }
+    }
+}
+
+impl SqliteBibDB {
+    /// relate the item to the other one, false if they are related so already
+    pub fn add_relation(&self, citation: &str, relation: Relation, other: &str) -> Result<bool> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let added = self.conn.execute("
+            INSERT OR IGNORE INTO item_relations (item_id, relation, other_id)
+            VALUES (?, ?, ?)", params![citation, relation.as_str(), other])? > 0;
+        if added { self.log_history(citation, "link", Some(relation.as_str()), None, Some(other))?; }
+        transaction.commit()?;
+        Ok(added)
+    }
+
+    /// drop the relation of the item to the other one, false if there is none
+    pub fn remove_relation(&self, citation: &str, relation: Relation, other: &str) -> Result<bool> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let removed = self.conn.execute("DELETE FROM item_relations WHERE item_id=? AND relation=? AND other_id=?",
+                                        params![citation, relation.as_str(), other])? > 0;
+        if removed { self.log_history(citation, "unlink", Some(relation.as_str()), Some(other), None)?; }
+        transaction.commit()?;
+        Ok(removed)
+    }
+
+    /// relations of the item in both directions, the outgoing ones first
+    pub fn get_relations(&self, citation: &str) -> Result<Vec<ItemRelation>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT relation, other_id, 0 FROM item_relations WHERE item_id=?1
+             UNION ALL
+            SELECT relation, item_id, 1 FROM item_relations WHERE other_id=?1
+             ORDER BY 3, 1, 2")?;
+        let relations = query.query_map(&[&citation], |row| Ok(ItemRelation{
+            relation: Relation::parse(&row.get_unwrap::<_, String>(0)).map(|x| x.0).unwrap_or(Relation::Cites),
+            other: row.get_unwrap(1),
+            incoming: row.get_unwrap(2),
+        }))?.collect::<Result<Vec<ItemRelation>>>();
+        relations
+    }
+
+    /// citation of an item with the same title regardless of case and punctuation, in the same year
+    /// unless the year is 0
+    pub fn find_title(&self, title: &str, year: i32) -> Result<Option<String>> {
+        let key = title_key(title);
+        if key.is_empty() { return Ok(None) }
+        let mut query = self.conn.prepare_cached("SELECT citation, title FROM items WHERE ?1=0 OR year=?1")?;
+        let mut rows = query.query(&[&year])?;
+        while let Some(row) = rows.next()? {
+            if title_key(&row.get_unwrap::<_, String>(1)) == key { return Ok(Some(row.get_unwrap(0))) }
+        }
+        Ok(None)
+    }
+
+    /// keep the relations of an item going to the trash
+    pub(super) fn trash_relations(&self, trash_id: i64, citation: &str) -> Result<()> {
+        self.conn.execute("
+            INSERT INTO trash_item_relations (trash_id, relation, other_id, incoming)
+            SELECT ?1, relation, other_id, 0 FROM item_relations WHERE item_id=?2
+             UNION ALL
+            SELECT ?1, relation, item_id, 1 FROM item_relations WHERE other_id=?2", params![trash_id, citation])
+            .map(|_| ())
+    }
+
+    /// put back the relations of a trashed item to the items still in the library
+    pub(super) fn restore_relations(&self, trash_id: i64, citation: &str) -> Result<()> {
+        self.conn.execute("
+            INSERT OR IGNORE INTO item_relations (item_id, relation, other_id)
+            SELECT CASE incoming WHEN 1 THEN other_id ELSE ?1 END, relation,
+                   CASE incoming WHEN 1 THEN ?1 ELSE other_id END
+              FROM trash_item_relations
+             WHERE trash_id=?2 AND other_id IN (SELECT citation FROM items)", params![citation, trash_id])
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::BibDataBase;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_relations() {
+        assert_eq!(Relation::parse("is-cited-by"), Some((Relation::Cites, true)));
+        assert_eq!(Relation::parse("Preprint_of"), Some((Relation::PreprintOf, false)));
+        assert_eq!(Relation::parse("quotes"), None);
+        let conn = SqliteBibDB::in_memory();
+        for entry in read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib")).iter() {
+            conn.add_item(entry, None).unwrap();
+        }
+        assert!(conn.add_relation("latexcompanion", Relation::Cites, "knuthwebsite").unwrap());
+        assert!(!conn.add_relation("latexcompanion", Relation::Cites, "knuthwebsite").unwrap());
+        assert!(conn.add_relation("einstein", Relation::Cites, "latexcompanion").unwrap());
+        let labels: Vec<(&str, String)> = conn.get_relations("latexcompanion").unwrap().iter()
+            .map(|x| (x.label(), x.other.clone())).collect();
+        assert_eq!(labels, vec![("cites", "knuthwebsite".to_owned()), ("cited by", "einstein".to_owned())]);
+        let trash_id = conn.trash_item("latexcompanion").unwrap();
+        assert!(conn.get_relations("einstein").unwrap().is_empty());
+        conn.restore_trash(trash_id).unwrap();
+        assert_eq!(conn.get_relations("latexcompanion").unwrap().len(), 2);
+        conn.rename("latexcompanion", "goossens1993").unwrap();
+        assert_eq!(conn.get_relations("einstein").unwrap()[0].other, "goossens1993");
+        assert!(conn.remove_relation("einstein", Relation::Cites, "goossens1993").unwrap());
+        let entry = conn.get_item("einstein").unwrap();
+        assert_eq!(conn.find_title(&entry.title.to_uppercase(), entry.year).unwrap(), Some("einstein".to_owned()));
+        assert_eq!(conn.find_title(&entry.title, entry.year + 1).unwrap(), None);
+    }
+}
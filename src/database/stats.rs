@@ -0,0 +1,123 @@
+use rusqlite::{Result, NO_PARAMS};
+
+use crate::model::Person;
+use super::SqliteBibDB;
+
+impl SqliteBibDB {
+    /// run a query returning a name and a count on each row
+    fn counts(&self, sql: &str, limit: i64) -> Result<Vec<(String, i64)>> {
+        let mut query = self.conn.prepare_cached(sql)?;
+        let counts = query.query_map(&[&limit], |row| Ok((row.get_unwrap(0), row.get_unwrap(1))))?
+            .collect::<Result<Vec<(String, i64)>>>();
+        counts
+    }
+
+    pub fn count_items(&self) -> Result<i64> {
+        self.conn.query_row("SELECT count(*) FROM items", NO_PARAMS, |row| row.get(0))
+    }
+
+    /// number of entries in each year, oldest first
+    pub fn count_by_year(&self) -> Result<Vec<(String, i64)>> {
+        self.counts("SELECT CAST(year AS TEXT), count(*) FROM items GROUP BY year ORDER BY year LIMIT ?", -1)
+    }
+
+    /// number of entries of each entry type, most common first
+    pub fn count_by_type(&self) -> Result<Vec<(String, i64)>> {
+        self.counts("SELECT entry_type, count(*) AS item_no FROM items GROUP BY entry_type
+                     ORDER BY item_no DESC, entry_type LIMIT ?", -1)
+    }
+
+    /// journals with the most entries
+    pub fn top_journals(&self, limit: i64) -> Result<Vec<(String, i64)>> {
+        self.counts("
+            SELECT journals.name, count(*) AS item_no
+              FROM items
+                   JOIN journals ON items.journal_id=journals.id
+             GROUP BY journals.id
+             ORDER BY item_no DESC, journals.name
+             LIMIT ?", limit)
+    }
+
+    /// authors with the most entries, editors are not counted
+    pub fn top_authors(&self, limit: i64) -> Result<Vec<(Person, i64)>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT count(*) AS item_no, persons.id, last_name, first_name, search_term, prefix, suffix
+              FROM item_persons
+                   JOIN persons ON item_persons.person_id=persons.id
+             WHERE NOT is_editor
+             GROUP BY persons.id
+             ORDER BY item_no DESC, last_name, first_name
+             LIMIT ?")?;
+        let authors = query.query_map(&[&limit], |row| Ok((Person::from(row), row.get_unwrap(0))))?
+            .collect::<Result<Vec<(Person, i64)>>>();
+        authors
+    }
+
+    /// pairs of authors sharing the most entries
+    pub fn top_coauthors(&self, limit: i64) -> Result<Vec<(Person, Person, i64)>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT first.person_id, second.person_id, count(*) AS item_no
+              FROM item_persons AS first
+                   JOIN item_persons AS second
+                     ON first.item_id=second.item_id AND first.person_id < second.person_id
+             WHERE NOT first.is_editor AND NOT second.is_editor
+             GROUP BY first.person_id, second.person_id
+             ORDER BY item_no DESC
+             LIMIT ?")?;
+        let pairs = query.query_map(&[&limit], |row| Ok((row.get_unwrap(0), row.get_unwrap(1), row.get_unwrap(2))))?
+            .collect::<Result<Vec<(i64, i64, i64)>>>()?;
+        let mut person_query = self.conn.prepare_cached(
+            "SELECT NULL, id, last_name, first_name, search_term, prefix, suffix FROM persons WHERE id=?")?;
+        let mut person = |id: i64| person_query.query_row(&[&id], |row| Ok(Person::from(row)));
+        pairs.into_iter().map(|(first, second, count)| Ok((person(first)?, person(second)?, count))).collect()
+    }
+
+    /// number of entries without a DOI
+    pub fn count_without_doi(&self) -> Result<i64> {
+        self.conn.query_row("
+            SELECT count(*)
+              FROM items
+             WHERE citation NOT IN (SELECT item_id FROM identifiers WHERE id_type='doi')",
+            NO_PARAMS, |row| row.get(0))
+    }
+
+    /// Number of entries in the library by the end of each month they were added in, entries from
+    /// before dates were recorded are left out
+    pub fn growth(&self) -> Result<Vec<(String, i64)>> {
+        let added = self.counts("
+            SELECT strftime('%Y-%m', added_at, 'unixepoch', 'localtime') AS month, count(*)
+              FROM items
+             WHERE added_at IS NOT NULL
+             GROUP BY month
+             ORDER BY month
+             LIMIT ?", -1)?;
+        let mut total = 0;
+        Ok(added.into_iter().map(|(month, count)| { total += count; (month, total) }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::BibDataBase;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let conn = SqliteBibDB::in_memory();
+        for entry in read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib")).iter() {
+            conn.add_item(entry, None).unwrap();
+        }
+        assert_eq!(conn.count_items().unwrap(), 3);
+        assert_eq!(conn.count_by_year().unwrap().iter().map(|x| x.1).sum::<i64>(), 3);
+        assert_eq!(conn.count_by_type().unwrap().iter().map(|x| x.1).sum::<i64>(), 3);
+        assert_eq!(conn.count_without_doi().unwrap(), 2);
+        assert_eq!(conn.growth().unwrap().last().map(|x| x.1), Some(3));
+        let authors = conn.top_authors(1).unwrap();
+        assert_eq!(authors.len(), 1);
+        let pairs = conn.top_coauthors(10).unwrap();
+        assert_eq!(pairs.len(), 3);
+        assert!(pairs.iter().all(|(first, second, count)| first.id < second.id && *count == 1));
+    }
+}
@@ -0,0 +1,79 @@
+use rusqlite::{params, Result, OptionalExtension, NO_PARAMS};
+
+use super::SqliteBibDB;
+use super::journal::Journal;
+
+/// A record of item_history as stored. Records are the same in two libraries when they come
+/// from the time before the libraries were copied apart, renames aside, so the item is not compared.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct LogRecord {
+    pub operation: String,
+    pub field: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    /// seconds since epoch
+    pub changed_at: i64,
+    pub user: Option<String>,
+}
+
+impl SqliteBibDB {
+    /// all recorded changes, by item, oldest first
+    /// Returns:
+    ///     Vec<(item_id, record)>
+    pub fn history_log(&self) -> Result<Vec<(String, LogRecord)>> {
+        let mut query = self.conn.prepare_cached("
+            SELECT item_id, operation, field, old_value, new_value, changed_at, user
+              FROM item_history
+             ORDER BY id")?;
+        let records = query.query_map(NO_PARAMS, |row| Ok((row.get_unwrap(0), LogRecord{
+            operation: row.get_unwrap(1),
+            field: row.get_unwrap(2),
+            old_value: row.get_unwrap(3),
+            new_value: row.get_unwrap(4),
+            changed_at: row.get_unwrap(5),
+            user: row.get_unwrap(6),
+        })))?.collect::<Result<Vec<(String, LogRecord)>>>();
+        records
+    }
+
+    /// copy records from another library into the history of an item, keeping their time and user
+    pub fn import_history(&self, item_id: &str, records: &[&LogRecord]) -> Result<()> {
+        let mut query = self.conn.prepare_cached("
+            INSERT INTO item_history (item_id, operation, field, old_value, new_value, changed_at, user)
+            VALUES (?, ?, ?, ?, ?, ?, ?)")?;
+        for record in records.iter() {
+            query.execute(params![item_id, &record.operation, &record.field, &record.old_value, &record.new_value,
+                                  &record.changed_at, &record.user])?;
+        }
+        Ok(())
+    }
+
+    /// the journal with exactly this full name
+    pub fn journal_by_name(&self, name: &str) -> Result<Option<Journal>> {
+        self.conn.query_row("SELECT id, name, abbr, abbr_no_dot FROM journals WHERE name=?", &[name],
+                            Journal::from_row).optional()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::database::BibDataBase;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_history_log() {
+        let conn = SqliteBibDB::in_memory();
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        conn.add_item(&entries[0], None).unwrap();
+        conn.add_keywords("einstein", &["relativity"]).unwrap();
+        let log = conn.history_log().unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!((log[0].0.as_str(), log[0].1.operation.as_str()), ("einstein", "add"));
+        let other = SqliteBibDB::in_memory();
+        other.import_history("einstein", &log.iter().map(|x| &x.1).collect::<Vec<&LogRecord>>()).unwrap();
+        assert_eq!(other.history_log().unwrap(), log);
+        assert!(conn.journal_by_name("Annalen der Physik").unwrap().is_none());
+    }
+}
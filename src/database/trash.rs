@@ -0,0 +1,209 @@
+use rusqlite::{params, Result, NO_PARAMS};
+
+use crate::model::{Entry, Person};
+use super::{SqliteBibDB, BibDataBase};
+use super::journal::Journal;
+
+pub struct TrashFile {
+    pub name: String,
+    pub object_type: String,
+    /// file name inside the trash folder, None if the file was left in place
+    pub stored_name: Option<String>,
+}
+
+pub struct TrashEntry {
+    pub trash_id: i64,
+    pub deleted_at: i64,
+    pub entry: Entry,
+    pub files: Vec<TrashFile>,
+}
+
+impl SqliteBibDB {
+    /// Copy an item with all its relations into the trash tables, without the file records.
+    /// Returns:
+    ///     trash_id
+    fn copy_to_trash(&self, id: &str, reason: &str) -> Result<i64> {
+        self.conn.execute("
+            INSERT INTO trash_items (reason, citation, entry_type, title, booktitle, year, month, chapter, edition,
+                                     volume, \"number\", pages, journal_name, journal_abbr, journal_abbr_no_dot,
+                                     added_at, modified_at)
+            SELECT ?, citation, entry_type, title, booktitle, year, month, chapter, edition, volume, \"number\",
+                   pages, journals.name, journals.abbr, journals.abbr_no_dot, added_at, modified_at
+              FROM items
+                   LEFT JOIN journals ON items.journal_id=journals.id
+             WHERE citation=?", params![reason, id])?;
+        let trash_id = self.conn.last_insert_rowid();
+        self.conn.execute("
+            INSERT INTO trash_item_persons (trash_id, last_name, first_name, search_term, prefix, suffix, order_seq,
+                                            is_editor)
+            SELECT ?, last_name, first_name, search_term, prefix, suffix, order_seq, is_editor
+              FROM item_persons
+                   JOIN persons ON item_persons.person_id=persons.id
+             WHERE item_id=?", params![trash_id, id])?;
+        self.conn.execute("
+            INSERT INTO trash_item_keywords (trash_id, text)
+            SELECT ?, text
+              FROM item_keywords
+                   JOIN keywords ON item_keywords.keyword_id=keywords.id
+             WHERE item_id=?", params![trash_id, id])?;
+        self.conn.execute("
+            INSERT INTO trash_extra_fields (trash_id, field, value)
+            SELECT ?, field, value FROM extra_fields WHERE item_id=?", params![trash_id, id])?;
+        self.conn.execute("
+            INSERT INTO trash_item_collections (trash_id, collection_id)
+            SELECT ?, collection_id FROM item_collections WHERE item_id=?", params![trash_id, id])?;
+        self.trash_reading(trash_id, id)?;
+        self.trash_relations(trash_id, id)?;
+        Ok(trash_id)
+    }
+
+    /// Move an item into the trash. File records are kept with an empty stored_name until the
+    /// file itself is moved with set_trash_file.
+    pub fn trash_item(&self, id: &str) -> Result<i64> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let title: String = self.conn.query_row("SELECT title FROM items WHERE citation=?", &[&id], |row| row.get(0))?;
+        let trash_id = self.copy_to_trash(id, "delete")?;
+        self.conn.execute("
+            INSERT INTO trash_files (trash_id, name, object_type, role, label, size, hash)
+            SELECT ?, name, object_type, role, label, size, hash FROM files WHERE item_id=?",
+            params![trash_id, id])?;
+        self.remove_item(id)?;
+        self.log_history(id, "delete", None, Some(&title), None)?;
+        transaction.commit()?;
+        Ok(trash_id)
+    }
+
+    /// Keep a copy of the item as it is before it gets overwritten by an update
+    pub fn snapshot_item(&self, id: &str) -> Result<i64> { self.copy_to_trash(id, "update") }
+
+    pub fn set_trash_file(&self, trash_id: i64, name: &str, stored_name: &str) -> Result<()> {
+        self.conn.execute("UPDATE trash_files SET stored_name=? WHERE trash_id=? AND name=?",
+            params![stored_name, trash_id, name]).map(|_| ())
+    }
+
+    pub fn get_trash(&self, trash_id: i64) -> Result<TrashEntry> {
+        let (deleted_at, mut entry) = self.conn.query_row("
+            SELECT citation, entry_type, title, booktitle, year, month, chapter, edition, volume, \"number\", pages,
+                   journal_name, added_at, modified_at, deleted_at
+              FROM trash_items
+             WHERE trash_id=?", &[&trash_id], |row| Ok((row.get_unwrap::<_, i64>(14), Entry::from(row))))?;
+        let mut person_query = self.conn.prepare_cached("
+            SELECT is_editor, NULL, last_name, first_name, search_term, prefix, suffix
+              FROM trash_item_persons
+             WHERE trash_id=?
+             ORDER BY is_editor, order_seq")?;
+        for row in person_query.query_map(&[&trash_id], |row| Ok((row.get_unwrap::<_, bool>(0), Person::from(row))))? {
+            let (is_editor, person) = row?;
+            if is_editor { entry.editors.push(person) } else { entry.authors.push(person) }
+        }
+        let mut keyword_query = self.conn.prepare_cached("SELECT text FROM trash_item_keywords WHERE trash_id=?")?;
+        entry.keywords.extend(keyword_query.query_map(&[&trash_id], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?);
+        let mut field_query = self.conn.prepare_cached(
+            "SELECT field, value FROM trash_extra_fields WHERE trash_id=?")?;
+        entry.extra_fields.extend(field_query.query_map(&[&trash_id], |row| Ok((row.get_unwrap(0), row.get_unwrap(1))))?
+            .collect::<Result<Vec<(String, String)>>>()?);
+        let mut file_query = self.conn.prepare_cached(
+            "SELECT name, object_type, stored_name FROM trash_files WHERE trash_id=?")?;
+        let files = file_query.query_map(&[&trash_id], |row| Ok(TrashFile{
+            name: row.get_unwrap(0), object_type: row.get_unwrap(1), stored_name: row.get_unwrap(2)}))?
+            .collect::<Result<Vec<TrashFile>>>()?;
+        Ok(TrashEntry{trash_id, deleted_at, entry, files})
+    }
+
+    /// deleted entries, most recent first. Snapshots kept for undoing updates are not listed.
+    pub fn list_trash(&self) -> Result<Vec<TrashEntry>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT trash_id FROM trash_items WHERE reason='delete' ORDER BY deleted_at DESC, trash_id DESC")?;
+        let ids = query.query_map(NO_PARAMS, |row| row.get::<_, i64>(0))?.collect::<Result<Vec<i64>>>()?;
+        ids.into_iter().map(|trash_id| self.get_trash(trash_id)).collect()
+    }
+
+    /// trash_ids of everything put in the trash before the unix timestamp
+    pub fn trash_before(&self, timestamp: i64) -> Result<Vec<i64>> {
+        let mut query = self.conn.prepare_cached("SELECT trash_id FROM trash_items WHERE deleted_at < ?")?;
+        let ids = query.query_map(&[&timestamp], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<i64>>>();
+        ids
+    }
+
+    /// Put a trashed entry back into the library together with its file records and their roles,
+    /// and remove it from the trash. The journal is added back if it has been cleaned up in the meantime.
+    pub fn restore_trash(&self, trash_id: i64) -> Result<Entry> {
+        let trashed = self.get_trash(trash_id)?;
+        let mut entry = trashed.entry;
+        entry.files.extend(trashed.files.into_iter().map(|x| (x.name, x.object_type)));
+        let transaction = self.conn.unchecked_transaction()?;
+        let journal_id = match entry.journal {
+            Some(ref name) => {
+                match self.journal_id(name)? {
+                    Some(x) => Some(x),
+                    None => {
+                        let journal = self.conn.query_row("
+                            SELECT NULL, journal_name, journal_abbr, journal_abbr_no_dot
+                              FROM trash_items WHERE trash_id=?", &[&trash_id],
+                            |row| Ok(Journal{id: None, name: row.get_unwrap(1), abbr: row.get_unwrap(2),
+                                             abbr_no_dot: row.get_unwrap(3)}))?;
+                        Some(self.add_journal(journal)?)
+                    }
+                }
+            },
+            None => None
+        };
+        self.insert_item(&entry, journal_id)?;
+        self.conn.execute("
+            UPDATE files
+               SET role=COALESCE((SELECT role FROM trash_files WHERE trash_id=?1 AND trash_files.name=files.name),
+                                 role),
+                   label=(SELECT label FROM trash_files WHERE trash_id=?1 AND trash_files.name=files.name),
+                   size=(SELECT size FROM trash_files WHERE trash_id=?1 AND trash_files.name=files.name),
+                   hash=(SELECT hash FROM trash_files WHERE trash_id=?1 AND trash_files.name=files.name)
+             WHERE item_id=?2", params![trash_id, &entry.citation])?;
+        self.conn.execute("
+            INSERT INTO item_collections (item_id, collection_id)
+            SELECT ?, collection_id
+              FROM trash_item_collections
+             WHERE trash_id=? AND collection_id IN (SELECT id FROM collections)",
+            params![&entry.citation, trash_id])?;
+        self.restore_reading(trash_id, &entry.citation)?;
+        self.restore_relations(trash_id, &entry.citation)?;
+        self.log_history(&entry.citation, "restore", None, None, Some(&entry.title))?;
+        self.purge_trash(trash_id)?;
+        transaction.commit()?;
+        Ok(entry)
+    }
+
+    /// permanently remove an entry from the trash
+    pub fn purge_trash(&self, trash_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM trash_items WHERE trash_id=?", &[&trash_id]).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_trash() {
+        let conn = SqliteBibDB::in_memory();
+        let mut entry = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"))[1].clone();
+        entry.keywords.insert("typesetting".to_owned());
+        entry.files.push(("latexcompanion".to_owned(), "pdf".to_owned()));
+        conn.add_item(&entry, None).unwrap();
+        let trash_id = conn.trash_item("latexcompanion").unwrap();
+        assert!(conn.get_item("latexcompanion").is_err());
+        conn.set_trash_file(trash_id, "latexcompanion", &format!("{}-latexcompanion.pdf", trash_id)).unwrap();
+        let trashed = conn.list_trash().unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].entry.authors[2].last_name, "samarin");
+        assert_eq!(trashed[0].files[0].stored_name, Some(format!("{}-latexcompanion.pdf", trash_id)));
+        let restored = conn.restore_trash(trash_id).unwrap();
+        assert_eq!(restored.citation, "latexcompanion");
+        let entry = conn.get_item("latexcompanion").unwrap();
+        assert!(entry.keywords.contains("typesetting"));
+        assert_eq!(entry.extra_fields.get("publisher").unwrap(), "Addison-Wesley");
+        assert_eq!(conn.get_files("latexcompanion").unwrap().len(), 1);
+        assert!(conn.list_trash().unwrap().is_empty());
+    }
+}
@@ -37,6 +37,11 @@ impl EntryType {
             _ => EntryType::Misc,
         }
     }
+
+    /// part of a book or proceedings, sharing the ISBN of the volume
+    pub fn is_part(&self) -> bool {
+        matches!(self, EntryType::Inbook | EntryType::Incollection | EntryType::Inproceedings)
+    }
 }
 
 impl fmt::Display for EntryType {
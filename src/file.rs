@@ -1,6 +1,10 @@
 use std::process::Command;
 use std::{io::{Result, Error, ErrorKind}, path::{PathBuf, Path}};
 use std::fs::{remove_file, rename, DirEntry};
+use std::io::Read;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use sha2::{Sha256, Digest};
 
 use crate::model::Entry;
 use crate::util::ToTitleCase;
@@ -14,9 +18,11 @@ impl FileHandler {
 
     /// search for a file in self.folder and has file_name while having ext in self.extension
     /// if not found then just use the first one in self.extension
+    /// a handler without extensions keeps files under their full name
     fn search(&self, file_name: &str) -> PathBuf {
         let mut target_path = self.folder.clone();
         target_path.push(file_name);
+        if self.extension.is_empty() { return target_path }
         for ext in self.extension.iter() {
             target_path.set_extension(ext);
             if target_path.exists() {
@@ -70,22 +76,20 @@ impl<'a> BibFile for File<'a> {
     fn remove(self) -> Result<()> { remove_file(self.path) }
 }
 
+/// handler of the folder where files of the type are kept
+pub fn handler(file_type: &str) -> &'static FileHandler { CONFIG.handler(file_type) }
+
 impl<'a> File<'a> {
-    pub fn new(name: &str, file_type: &str) -> Self {
-        let handler = match file_type {
-            "pdf" => &CONFIG.pdf,
-            "comment" => &CONFIG.comment,
-            "bib" | "temp_bib" | "temp_pdf" => { panic!("use File::temp to create temp files.") },
-            _ => panic!("Wrong file type {}", file_type)
-        };
-        File{path: handler.search(name), handler}
-    }
+    pub fn new(name: &str, file_type: &str) -> Self { File::in_folder(name, handler(file_type)) }
+
+    /// file in the folder of a handler, like that of another library
+    pub fn in_folder(name: &str, handler: &'a FileHandler) -> Self { File{path: handler.search(name), handler} }
 
     pub fn temp(file_type: &str) -> Result<Self> { 
         let handler = match file_type {
             "bib" | "temp_bib" => &CONFIG.temp_bib,
             "temp_pdf" => &CONFIG.temp_pdf,
-            "pdf" | "comment" => { return Err(Error::new(ErrorKind::InvalidInput, "use File::New to create none temp files that have specified file names.")) },
+            "pdf" | "comment" | "attachment" => { return Err(Error::new(ErrorKind::InvalidInput, "use File::New to create none temp files that have specified file names.")) },
             _ => panic!("Wrong file type {}", file_type)
         };
         Ok(File{path: handler.search_temp()?, handler})
@@ -99,6 +103,67 @@ impl<'a> File<'a> {
     }
 }
 
+/// sha256 of the file content in hex
+pub fn file_hash(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 65536];
+    loop {
+        let count = file.read(&mut buffer)?;
+        if count == 0 { break }
+        hasher.update(&buffer[..count]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+lazy_static!{
+    static ref PLACEHOLDER_RE: Regex = Regex::new(r"\{(\w+)\}").unwrap();
+}
+
+/// words left out of {shorttitle}
+const STOP_WORDS: &[&str] = &["a", "an", "the", "on", "of", "in", "for", "and", "to", "with", "at", "by", "from"];
+/// longest folder or file name made from a template
+const MAX_PART_LENGTH: usize = 80;
+
+/// words of a value joined by dashes, without punctuation or braces
+fn name_part(value: &str) -> String {
+    value.split(|x: char| !x.is_alphanumeric()).filter(|x| !x.is_empty()).collect::<Vec<&str>>().join("-")
+}
+
+/// Name of a file from a naming template like {year}/{auth}-{shorttitle}, the slashes make
+/// subfolders. Placeholders are citation, year, auth (last name of the first author), title,
+/// shorttitle (first three words that are not articles or prepositions), journal, keyword (first in
+/// alphabetical order), collection (path of the first one) and role. Missing values are left empty.
+pub fn render_template(template: &str, entry: &Entry, role: &str, collection: Option<&str>) -> String {
+    let name = PLACEHOLDER_RE.replace_all(template, |caps: &Captures| match &caps[1] {
+        "citation" => entry.citation.clone(),
+        "year" => entry.year.to_string(),
+        "auth" => entry.authors.first().or_else(|| entry.editors.first())
+            .map(|x| name_part(&x.search_term)).unwrap_or_default(),
+        "title" => name_part(&entry.title),
+        "shorttitle" => name_part(&entry.title).split('-')
+            .filter(|x| !STOP_WORDS.contains(&x.to_lowercase().as_str())).take(3).collect::<Vec<&str>>().join("-"),
+        "journal" => entry.journal.as_deref().map(name_part).unwrap_or_default(),
+        "keyword" => entry.keywords.iter().min().map(|x| name_part(x)).unwrap_or_default(),
+        "collection" => collection.map(|x| x.split('/').map(name_part).collect::<Vec<String>>().join("/"))
+            .unwrap_or_default(),
+        "role" => name_part(role),
+        _ => caps[0].to_owned(),
+    });
+    name.split('/').map(|x| x.trim_matches(|c: char| c == '-' || c == '_' || c.is_whitespace()))
+        .filter(|x| !x.is_empty()).map(|x| x.chars().take(MAX_PART_LENGTH).collect::<String>())
+        .collect::<Vec<String>>().join("/")
+}
+
+/// Name of a file in the content-addressed store, the hash of its content, with the extension for
+/// attachment files as they are kept under their full name
+pub fn content_name(hash: &str, extension: Option<&str>, object_type: &str) -> String {
+    match extension {
+        Some(ext) if object_type == "attachment" => format!("{}.{}", hash, ext),
+        _ => hash.to_owned(),
+    }
+}
+
 impl Entry {
     pub fn to_comment(&self) -> String {
         let author_str = if !self.authors.is_empty() {
@@ -111,3 +176,21 @@ impl Entry {
         format!("% {}\n% {}\n% {}", self.title.to_title(), author_str, self.year)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let mut entry = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib")).remove(0);
+        assert_eq!(render_template("{year}/{auth}-{shorttitle}", &entry, "main", None),
+                   "1905/einstein-Zur-Elektrodynamik-bewegter");
+        assert_eq!(render_template("{keyword}/{citation}-{role}", &entry, "supplement", None), "einstein-supplement");
+        entry.keywords.insert("relativity".to_owned());
+        assert_eq!(render_template("{collection}/{keyword}/{journal}", &entry, "main", Some("physics/classics")),
+                   "physics/classics/relativity/Annalen-der-Physik");
+    }
+}
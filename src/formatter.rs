@@ -1,14 +1,32 @@
 pub mod bibtex;
+pub mod csl;
 
-use termion::color;
-use crate::model::{Entry, Person};
+use std::fmt::Display;
+use termion::{color, style};
+use crate::fg;
+use crate::model::{Entry, Person, Reading, ReadingStatus};
+use crate::database::fulltext::{FulltextHit, MATCH_START, MATCH_END};
+use crate::database::attachment::Attachment;
 use crate::util::ToTitleCase;
 
 pub trait ToString { fn to_str(&self) -> String; }
 
+/// drop the braces that keep a name in one piece, like {World Health Organization}
+fn unbrace(input: &str) -> String { input.chars().filter(|x| *x != '{' && *x != '}').collect() }
+
+impl Person {
+    /// von Last, Jr. as printed after the first name, the suffix is kept as written
+    fn family_str(&self) -> String {
+        let last_name = unbrace(&self.last_name.to_title());
+        let family = if self.prefix.is_empty() { last_name } else { format!("{} {}", self.prefix, last_name) };
+        if self.suffix.is_empty() { family } else { format!("{}, {}", family, self.suffix) }
+    }
+}
+
 impl ToString for Person {
     fn to_str(&self) -> String {
-        format!("{} {}", self.first_name.to_title(), self.last_name.to_title())
+        if self.first_name.is_empty() { return self.family_str() }
+        format!("{} {}", self.first_name.to_title(), self.family_str())
     }
 }
 
@@ -30,8 +48,9 @@ pub trait LabeledPrint { fn labeled_to_str(&self, searched: &[String]) -> String
 
 impl Person {
     pub fn labeled_to_str(&self) -> String {
+        if self.first_name.is_empty() { return fg!(Blue, self.family_str()) }
         format!("{}{}{} {}{}", color::Fg(color::Red), self.first_name.to_title(), color::Fg(color::Blue),
-            self.last_name.to_title(), color::Fg(color::Reset))
+            self.family_str(), color::Fg(color::Reset))
     }
 }
 
@@ -73,9 +92,25 @@ impl ToString for Entry {
     }
 }
 
+/// compact badge of the status, priority and rating, like [x] P1 ★4
+impl ToString for Reading {
+    fn to_str(&self) -> String {
+        let mut output = vec![match self.status {
+            ReadingStatus::Unread => "[ ]",
+            ReadingStatus::Reading => "[~]",
+            ReadingStatus::Read => "[x]",
+            ReadingStatus::Skimmed => "[-]",
+        }.to_owned()];
+        if let Some(priority) = self.priority { output.push(format!("P{}", priority)); }
+        if let Some(rating) = self.rating { output.push(format!("★{}", rating)); }
+        output.join(" ")
+    }
+}
+
 impl LabeledPrint for Entry {
     fn labeled_to_str(&self, searched: &[String]) -> String {
         let mut output: Vec<String> = Vec::new();
+        if let Some(ref reading) = self.reading { output.push(format!("{} ", fg!(Yellow, reading.to_str()))); }
         if !self.authors.is_empty() {
             output.push(self.authors.labeled_to_str(searched));
         } else if !self.editors.is_empty() {
@@ -88,6 +123,28 @@ impl LabeledPrint for Entry {
     }
 }
 
+/// role, then the label if any and the stored file
+impl ToString for Attachment {
+    fn to_str(&self) -> String {
+        let name = if self.object_type == "attachment" { self.name.clone() }
+                   else { format!("{} {}", self.object_type, self.name) };
+        match self.label {
+            Some(ref label) => format!("{:<10} {} ({})", self.role, label, name),
+            None => format!("{:<10} {}", self.role, name),
+        }
+    }
+}
+
+/// the page and the snippet on one line, matched terms in red
+impl LabeledPrint for FulltextHit {
+    fn labeled_to_str(&self, _searched: &[String]) -> String {
+        let snippet = self.snippet.split_whitespace().collect::<Vec<&str>>().join(" ")
+            .replace(MATCH_START, &color::Fg(color::Red).to_string())
+            .replace(MATCH_END, &color::Fg(color::Reset).to_string());
+        format!("p. {}: {}", self.page, snippet)
+    }
+}
+
 impl Entry {
     pub fn generate_citation(&self) -> String {
         let last_name: &str = match self.authors.first().or_else(|| self.editors.first()) {
@@ -98,6 +155,19 @@ impl Entry {
     }
 }
 
+/// mark a value as newly added
+pub fn added_str<T: Display>(value: T) -> String { fg!(Blue, value) }
+
+/// mark a value as removed, crossed out
+pub fn deleted_str<T: Display>(value: T) -> String {
+    fg!(Red, format!("{}{}{}", style::CrossedOut, value, style::Reset))
+}
+
+/// mark a value as implied rather than assigned, dimmed in italic
+pub fn inherited_str<T: Display>(value: T) -> String {
+    format!("{}{}{}", style::Italic, fg!(LightBlack, value), style::Reset)
+}
+
 pub trait TrimStr { fn trim_str(&self) -> String; }
 
 impl TrimStr for String { fn trim_str(&self) -> String {
@@ -114,13 +184,18 @@ pub mod tests {
     #[test]
     fn test_person() {
         let persons = vec![Person{id: None, search_term: "abcd".to_owned(),
-                                  last_name: "âbcd".to_owned(), first_name: "ėfgh".to_owned()},
+                                  last_name: "âbcd".to_owned(), first_name: "ėfgh".to_owned(), ..Default::default()},
                            Person{id: None, search_term: "bbcd".to_owned(),
-                                  last_name: "bcdê".to_owned(), first_name: "ėfgh".to_owned()},
+                                  last_name: "bcdê".to_owned(), first_name: "ėfgh".to_owned(), ..Default::default()},
                            Person{id: None, search_term: "bbc3".to_owned(),
-                                  last_name: "b3dê".to_owned(), first_name: "ėfgh".to_owned()}];
+                                  last_name: "b3dê".to_owned(), first_name: "ėfgh".to_owned(), ..Default::default()}];
         assert_eq!("ėfgh âbcd, ėfgh Bcdê & ėfgh B3dê", persons.to_str());
         assert_eq!("âbcd, ėfgh and Bcdê, ėfgh and B3dê, ėfgh", persons.to_bib());
+        let persons = vec![Person::load("Ludwig van Beethoven"), Person::load("King, Jr., Martin Luther"),
+                           Person::load("{World Health Organization}")];
+        assert_eq!("Ludwig van Beethoven, Martin Luther King, Jr. & World Health Organization", persons.to_str());
+        assert_eq!("van Beethoven, Ludwig and King, Jr., Martin Luther and {World Health Organization}",
+                   persons.to_bib());
     }
     use crate::reader::bibtex;
     use std::path::PathBuf;
@@ -138,10 +213,14 @@ pub mod tests {
             "\n\tjournal = {Annalen der Physik},",
             "\n\tauthor = {Einstein, Albert}\n}"].concat();
         let correct_str = [
-            "Albert Einstein. (1905).{Zur Elektrodynamik bewegter K{\\\"o}rper}. ({German})\n        ",
+            "Albert Einstein. (1905).{Zur Elektrodynamik bewegter Körper}. ({German})\n        ",
             "[{On} The Electrodynamics Of Moving Bodies]. Annalen der Physik"].concat();
         assert_eq!(item[0].to_str(), correct_str);
         assert_eq!(item[0].to_bib(), correct_bib);
+        let mut entry = item[0].clone();
+        entry.authors = vec![Person::load("G{\\\"o}del, Kurt")];
+        assert!(entry.bib_str(true).contains("author = {Gödel, Kurt}"));
+        assert!(entry.to_bib().contains("author = {G{\\\"o}del, Kurt}"));
     }
     #[test]
     fn test_labeled_item() {
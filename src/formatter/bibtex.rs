@@ -3,6 +3,7 @@ use std::collections::HashSet;
 use itertools::Itertools;
 
 use crate::model::{Entry, Person};
+use crate::latex::{escape, VERBATIM_FIELDS};
 use crate::util::ToTitleCase;
 
 pub trait BibPrint {
@@ -13,9 +14,15 @@ impl BibPrint for i32 { fn to_bib(&self) -> String { self.to_string() } }
 
 impl BibPrint for String { fn to_bib(&self) -> String { self.clone() } }
 
+/// von Last, Jr, First with the empty parts left out
 impl BibPrint for Person {
     fn to_bib(&self) -> String {
-        format!("{}, {}", self.last_name.to_title(), self.first_name.to_title())
+        let last_name = self.last_name.to_title();
+        let mut parts = vec![if self.prefix.is_empty() { last_name.to_string() }
+                             else { format!("{} {}", self.prefix, last_name) }];
+        if !self.suffix.is_empty() { parts.push(self.suffix.clone()); }
+        if !self.first_name.is_empty() || !self.suffix.is_empty() { parts.push(self.first_name.to_title().to_string()); }
+        parts.join(", ")
     }
 }
 
@@ -47,19 +54,34 @@ macro_rules! insert_vec {
     )
 }
 
-impl BibPrint for Entry {
-    fn to_bib(&self) -> String {
+impl Entry {
+    /// The entry as a BibTeX record. Non-ASCII letters are written as LaTeX commands unless utf8
+    /// is set, verbatim fields like url are kept as they are.
+    pub fn bib_str(&self, utf8: bool) -> String { self.bib_record(utf8, false) }
+
+    /// The BibTeX record with the title, for files read back by bibrs like pack archives
+    pub fn titled_bib_str(&self, utf8: bool) -> String { self.bib_record(utf8, true) }
+
+    fn bib_record(&self, utf8: bool, title: bool) -> String {
         let mut output: Vec<String> = Vec::new();
         output.push(format!{"@{}{{{}", self.entry_type, self.citation});
 
         let mut fields: Vec<(&str, String)> = Vec::new();
-        fields.push(("year", self.year.to_bib()));
+        if self.year != 0 { fields.push(("year", self.year.to_bib())); }
+        if title && !self.title.is_empty() { fields.push(("title", self.title.clone())); }
         insert_field!(fields, self, booktitle, chapter, edition, month, volume, number, pages, journal);
         insert_vec!(fields, self, {editors, editor}, {authors, author}, {keywords, keyword});
         for (field, value) in self.extra_fields.iter() {fields.push((field, value.clone()))};
 
-        for (field, value) in fields.into_iter() { output.push(format!{",\n\t{} = {{{}}}", field, value})};
+        for (field, value) in fields.into_iter() {
+            let value = if utf8 || VERBATIM_FIELDS.contains(&field) { value } else { escape(&value) };
+            output.push(format!{",\n\t{} = {{{}}}", field, value})
+        };
         output.push("\n}".to_owned());
         output.concat().to_string()
     }
 }
+
+impl BibPrint for Entry {
+    fn to_bib(&self) -> String { self.bib_str(false) }
+}
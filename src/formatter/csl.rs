@@ -0,0 +1,101 @@
+//! Entries as CSL-JSON, the input format of citeproc processors like pandoc and Zotero
+use serde_json::{json, Map, Value};
+
+use crate::entry_type::EntryType;
+use crate::model::{Entry, Person};
+use crate::util::ToTitleCase;
+
+fn csl_type(entry_type: &EntryType) -> &'static str {
+    match entry_type {
+        EntryType::Article => "article-journal",
+        EntryType::Book | EntryType::Booklet | EntryType::Proceedings => "book",
+        EntryType::Inbook | EntryType::Incollection => "chapter",
+        EntryType::Inproceedings => "paper-conference",
+        EntryType::Masterthesis | EntryType::Phdthesis => "thesis",
+        EntryType::Techreport => "report",
+        EntryType::Unpublished => "manuscript",
+        EntryType::Manual | EntryType::Misc => "document",
+    }
+}
+
+/// text without the braces that protect case in bibtex
+fn plain(input: &str) -> String { input.chars().filter(|x| *x != '{' && *x != '}').collect() }
+
+fn csl_person(person: &Person) -> Value {
+    let mut output = Map::new();
+    output.insert("family".to_owned(), json!(plain(&person.last_name.to_title())));
+    if !person.first_name.is_empty() { output.insert("given".to_owned(), json!(plain(&person.first_name.to_title()))); }
+    if !person.prefix.is_empty() { output.insert("non-dropping-particle".to_owned(), json!(person.prefix)); }
+    if !person.suffix.is_empty() { output.insert("suffix".to_owned(), json!(person.suffix)); }
+    Value::Object(output)
+}
+
+/// extra fields under their CSL names
+const CSL_FIELDS: &[(&str, &str)] = &[("doi", "DOI"), ("pmid", "PMID"), ("isbn", "ISBN"), ("issn", "ISSN"),
+    ("url", "URL"), ("publisher", "publisher"), ("address", "publisher-place"), ("note", "note"),
+    ("series", "collection-title"), ("school", "publisher"), ("institution", "publisher")];
+
+/// One CSL-JSON item, with the citation as id
+pub fn csl_item(entry: &Entry) -> Value {
+    let mut output = Map::new();
+    output.insert("id".to_owned(), json!(entry.citation));
+    output.insert("type".to_owned(), json!(csl_type(&entry.entry_type)));
+    output.insert("title".to_owned(), json!(plain(&entry.title)));
+    if !entry.authors.is_empty() {
+        output.insert("author".to_owned(), Value::Array(entry.authors.iter().map(csl_person).collect()));
+    }
+    if !entry.editors.is_empty() {
+        output.insert("editor".to_owned(), Value::Array(entry.editors.iter().map(csl_person).collect()));
+    }
+    if entry.year != 0 {
+        let parts = match entry.month { Some(month) => json!([entry.year, month]), None => json!([entry.year]) };
+        output.insert("issued".to_owned(), json!({"date-parts": [parts]}));
+    }
+    if let Some(container) = entry.journal.as_ref().or_else(|| entry.booktitle.as_ref()) {
+        output.insert("container-title".to_owned(), json!(plain(container)));
+    }
+    let numbers = [("volume", entry.volume), ("issue", entry.number), ("edition", entry.edition),
+                   ("chapter-number", entry.chapter)];
+    for (key, value) in numbers.iter() {
+        if let Some(value) = value { output.insert((*key).to_owned(), json!(value.to_string())); }
+    }
+    if let Some(ref pages) = entry.pages { output.insert("page".to_owned(), json!(pages)); }
+    for (field, key) in CSL_FIELDS.iter() {
+        if let Some(value) = entry.extra_fields.get(*field) {
+            output.entry((*key).to_owned()).or_insert_with(|| json!(plain(value)));
+        }
+    }
+    if !entry.keywords.is_empty() {
+        let mut keywords: Vec<&str> = entry.keywords.iter().map(|x| x.as_str()).collect();
+        keywords.sort_unstable();
+        output.insert("keyword".to_owned(), json!(keywords.join(", ")));
+    }
+    Value::Object(output)
+}
+
+/// a CSL-JSON bibliography, an array of items
+pub fn csl_json(entries: &[Entry]) -> String {
+    serde_json::to_string_pretty(&Value::Array(entries.iter().map(csl_item).collect())).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::reader::bibtex::read_entries;
+    use super::*;
+
+    #[test]
+    fn test_csl_item() {
+        let entries = read_entries(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/data/test.bib"));
+        let item = csl_item(&entries[0]);
+        assert_eq!(item["id"], "einstein");
+        assert_eq!(item["type"], "article-journal");
+        assert_eq!(item["author"][0]["family"], "Einstein");
+        assert_eq!(item["issued"]["date-parts"][0][0], 1905);
+        assert_eq!(item["container-title"], "Annalen der Physik");
+        assert_eq!((item["volume"].as_str(), item["page"].as_str()), (Some("322"), Some("891-921")));
+        let item = csl_item(&entries[1]);
+        assert_eq!(item["type"], "book");
+        assert_eq!(item["author"].as_array().unwrap().len(), 3);
+    }
+}
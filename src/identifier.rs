@@ -0,0 +1,130 @@
+//! Identifiers that stand for one paper whatever its citation: DOI, PubMed ID, arXiv ID and ISBN
+use std::fmt;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::model::Entry;
+use crate::validate::{clean_doi, isbn_valid, DOI_RE};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IdType {
+    Doi,
+    Pmid,
+    Arxiv,
+    Isbn,
+}
+
+impl IdType {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "doi" => Some(IdType::Doi),
+            "pmid" => Some(IdType::Pmid),
+            "arxiv" => Some(IdType::Arxiv),
+            "isbn" => Some(IdType::Isbn),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IdType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printable = match *self {
+            IdType::Doi => "doi",
+            IdType::Pmid => "pmid",
+            IdType::Arxiv => "arxiv",
+            IdType::Isbn => "isbn",
+        };
+        write!(f, "{}", printable)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Identifier {
+    pub id_type: IdType,
+    /// normalized, so that equal identifiers compare equal as text
+    pub value: String,
+}
+
+lazy_static!{
+    static ref ARXIV_RE: Regex = Regex::new(
+        r#"^(?i)(?:arxiv:)?(\d{4}\.\d{4,5}|[a-z\-]+(\.[a-z]{2})?/\d{7})(v\d+)?$"#).unwrap();
+}
+
+/// ISBN-13 digits for both ISBN-10 and ISBN-13
+fn isbn13(input: &str) -> Option<String> {
+    if !isbn_valid(input) { return None }
+    let digits: String = input.chars().filter(|x| x.is_ascii_alphanumeric()).collect();
+    if digits.len() == 13 { return Some(digits) }
+    let body = format!("978{}", &digits[..9]);
+    let sum: u32 = body.chars().enumerate()
+        .map(|(idx, x)| x.to_digit(10).unwrap() * if idx % 2 == 0 { 1 } else { 3 }).sum();
+    Some(format!("{}{}", body, (10 - sum % 10) % 10))
+}
+
+impl Identifier {
+    /// Normalize the value: DOIs lose resolver prefixes and are lowercased, arXiv IDs lose the
+    /// version, ISBN-10 become ISBN-13. None if the value is malformed.
+    pub fn new(id_type: IdType, value: &str) -> Option<Self> {
+        let value = value.trim();
+        let normalized = match id_type {
+            IdType::Doi => Some(clean_doi(value).to_lowercase()).filter(|x| DOI_RE.is_match(x)),
+            IdType::Pmid => Some(value.to_owned()).filter(|x| !x.is_empty() && x.chars().all(|c| c.is_ascii_digit())),
+            IdType::Arxiv => ARXIV_RE.captures(value).map(|caps| caps[1].to_lowercase()),
+            IdType::Isbn => isbn13(value),
+        };
+        normalized.map(|value| Identifier{id_type, value})
+    }
+
+    /// Read a typed identifier like doi:10.1002/andp.19053221004 or pmid:12345
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut parts = input.splitn(2, ':');
+        let id_type = IdType::parse(parts.next()?)?;
+        Identifier::new(id_type, parts.next()?)
+    }
+
+    /// The identifiers in the doi, pmid, eprint and isbn fields of an entry. An eprint counts as an
+    /// arXiv ID unless another archive is given in archiveprefix or eprinttype.
+    pub fn from_entry(entry: &Entry) -> Vec<Self> {
+        let mut output = Vec::new();
+        let fields = &entry.extra_fields;
+        for (field, id_type) in [("doi", IdType::Doi), ("pmid", IdType::Pmid), ("isbn", IdType::Isbn)].iter() {
+            if let Some(identifier) = fields.get(*field).and_then(|x| Identifier::new(*id_type, x)) {
+                output.push(identifier);
+            }
+        }
+        let archive = fields.get("archiveprefix").or_else(|| fields.get("eprinttype"));
+        if archive.map_or(true, |x| x.eq_ignore_ascii_case("arxiv")) {
+            if let Some(identifier) = fields.get("eprint").and_then(|x| Identifier::new(IdType::Arxiv, x)) {
+                output.push(identifier);
+            }
+        }
+        output
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}:{}", self.id_type, self.value) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier() {
+        assert_eq!(Identifier::parse("doi:https://dx.doi.org/10.1002/ANDP.19053221004"),
+                   Some(Identifier{id_type: IdType::Doi, value: "10.1002/andp.19053221004".to_owned()}));
+        assert_eq!(Identifier::parse("arxiv:arXiv:1706.03762v5").unwrap().value, "1706.03762");
+        assert_eq!(Identifier::parse("arxiv:hep-th/9711200").unwrap().value, "hep-th/9711200");
+        assert_eq!(Identifier::parse("isbn:0-306-40615-2").unwrap().value, "9780306406157");
+        assert_eq!(Identifier::parse("pmid:15539593").unwrap().to_string(), "pmid:15539593");
+        assert_eq!(Identifier::parse("pmid:abc"), None);
+        assert_eq!(Identifier::parse("issn:0028-0836"), None);
+        let mut entry = Entry::default();
+        entry.extra_fields.insert("doi".to_owned(), "10.48550/arXiv.1706.03762".to_owned());
+        entry.extra_fields.insert("eprint".to_owned(), "1706.03762".to_owned());
+        assert_eq!(Identifier::from_entry(&entry).len(), 2);
+        entry.extra_fields.insert("eprinttype".to_owned(), "hal".to_owned());
+        assert_eq!(Identifier::from_entry(&entry).len(), 1);
+    }
+}
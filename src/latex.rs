@@ -0,0 +1,174 @@
+//! Conversion between LaTeX accents and special characters, and Unicode
+use unicode_normalization::UnicodeNormalization;
+
+/// combining character for accent commands, like \" or \v
+fn accent_mark(command: &str) -> Option<char> {
+    Some(match command {
+        "`" => '\u{300}',
+        "'" => '\u{301}',
+        "^" => '\u{302}',
+        "~" => '\u{303}',
+        "=" => '\u{304}',
+        "u" => '\u{306}',
+        "." => '\u{307}',
+        "\"" => '\u{308}',
+        "r" => '\u{30a}',
+        "H" => '\u{30b}',
+        "v" => '\u{30c}',
+        "d" => '\u{323}',
+        "c" => '\u{327}',
+        "k" => '\u{328}',
+        "b" => '\u{331}',
+        _ => return None,
+    })
+}
+
+fn accent_command(mark: char) -> Option<&'static str> {
+    ["`", "'", "^", "~", "=", "u", ".", "\"", "r", "H", "v", "d", "c", "k", "b"].iter()
+        .find(|x| accent_mark(x) == Some(mark)).copied()
+}
+
+const SPECIAL_LETTERS: &[(&str, char)] = &[
+    ("ss", 'ß'), ("o", 'ø'), ("O", 'Ø'), ("ae", 'æ'), ("AE", 'Æ'), ("oe", 'œ'), ("OE", 'Œ'),
+    ("aa", 'å'), ("AA", 'Å'), ("l", 'ł'), ("L", 'Ł'), ("i", 'ı'), ("j", 'ȷ')];
+
+/// fields kept as they are written, never converted or escaped
+pub const VERBATIM_FIELDS: &[&str] = &["url", "doi", "eprint", "file"];
+
+/// escaped symbols that are stored as the plain character
+const SYMBOLS: &[char] = &['&', '%'];
+
+fn special_letter(command: &str) -> Option<char> {
+    SPECIAL_LETTERS.iter().find(|(name, _)| *name == command).map(|(_, letter)| *letter)
+}
+
+/// Read the command starting after a backslash at chars[start]
+/// Returns:
+///     (command, index after the command)
+fn read_command(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let first = *chars.get(start)?;
+    if !first.is_ascii_alphabetic() { return Some((first.to_string(), start + 1)) }
+    let end = chars[start..].iter().position(|x| !x.is_ascii_alphabetic()).map_or(chars.len(), |x| start + x);
+    Some((chars[start..end].iter().collect(), end))
+}
+
+/// Read the argument of an accent, either a single letter or a group like {o} or {\i}
+/// Returns:
+///     (letter, index after the argument)
+fn read_argument(chars: &[char], mut start: usize, control_word: bool) -> Option<(char, usize)> {
+    if control_word { while chars.get(start) == Some(&' ') { start += 1; } }
+    match *chars.get(start)? {
+        '{' => {
+            let (letter, end) = match *chars.get(start + 1)? {
+                '\\' => {
+                    let (command, end) = read_command(chars, start + 2)?;
+                    match command.as_str() { "i" => ('i', end), "j" => ('j', end), _ => return None }
+                },
+                '}' => return Some((' ', start + 2)),
+                x => (x, start + 2),
+            };
+            if chars.get(end) == Some(&'}') { Some((letter, end + 1)) } else { None }
+        },
+        '\\' | '}' => None,
+        x => Some((x, start + 1)),
+    }
+}
+
+/// Convert the command after the backslash at chars[start]
+/// Returns:
+///     (converted text, index after the command and its argument)
+fn convert_command(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let (command, end) = read_command(chars, start + 1)?;
+    let control_word = command.chars().all(|x| x.is_ascii_alphabetic());
+    if let Some(mark) = accent_mark(&command) {
+        let (letter, end) = read_argument(chars, end, control_word)?;
+        // \~{} is a plain tilde
+        if letter == ' ' { return if command == "~" || command == "^" { Some((command, end)) } else { None } }
+        return Some(([letter, mark].iter().copied().nfc().collect(), end));
+    }
+    if let Some(letter) = special_letter(&command) {
+        let end = if chars[end..].starts_with(&['{', '}']) { end + 2 }
+                  else if chars.get(end) == Some(&' ') { end + 1 } else { end };
+        return Some((letter.to_string(), end));
+    }
+    match command.chars().next() {
+        Some(symbol) if command.len() == 1 && SYMBOLS.contains(&symbol) => Some((command, end)),
+        _ => None,
+    }
+}
+
+/// Replace LaTeX accents, special letters and escaped symbols by their Unicode characters. Braces
+/// around a single converted character, as in K{\"o}rper, are dropped. Other commands are kept.
+pub fn to_unicode(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut output = String::with_capacity(input.len());
+    let mut idx = 0;
+    while idx < chars.len() {
+        match chars[idx] {
+            '{' if chars.get(idx + 1) == Some(&'\\') => {
+                match convert_command(&chars, idx + 1) {
+                    Some((text, end)) if chars.get(end) == Some(&'}') => { output.push_str(&text); idx = end + 1; },
+                    _ => { output.push('{'); idx += 1; },
+                }
+            },
+            '\\' => match convert_command(&chars, idx) {
+                Some((text, end)) => { output.push_str(&text); idx = end; },
+                None => { output.push('\\'); idx += 1; },
+            },
+            x => { output.push(x); idx += 1; },
+        }
+    }
+    output
+}
+
+/// Write non-ASCII letters back as LaTeX commands like {\"o}, so that the output works in BibTeX
+/// as well as biber. Characters without a LaTeX form are kept as they are.
+pub fn escape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut previous = ' ';
+    for c in input.chars() {
+        if c.is_ascii() {
+            if SYMBOLS.contains(&c) && previous != '\\' { output.push('\\'); }
+            output.push(c);
+        } else if let Some((command, _)) = SPECIAL_LETTERS.iter().find(|(_, letter)| *letter == c) {
+            output.push_str(&format!("{{\\{}}}", command));
+        } else {
+            let decomposed: Vec<char> = c.to_string().nfd().collect();
+            match (decomposed.as_slice(), decomposed.get(1).and_then(|x| accent_command(*x))) {
+                ([letter, _], Some(command)) if letter.is_ascii_alphabetic() => {
+                    let letter = match *letter { 'i' => "\\i".to_owned(), 'j' => "\\j".to_owned(), x => x.to_string() };
+                    let separator = if command.chars().all(|x| x.is_ascii_alphabetic()) { " " } else { "" };
+                    output.push_str(&format!("{{\\{}{}{}}}", command, separator, letter));
+                },
+                _ => output.push(c),
+            }
+        }
+        previous = c;
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_unicode() {
+        assert_eq!(to_unicode("Zur Elektrodynamik bewegter K{\\\"o}rper"), "Zur Elektrodynamik bewegter Körper");
+        assert_eq!(to_unicode("Erd\\H{o}s and G\\\"odel"), "Erdős and Gödel");
+        assert_eq!(to_unicode("Fran\\c cois {\\v{C}}ech \\'{\\i}"), "François Čech í");
+        assert_eq!(to_unicode("Stra\\ss{}e \\o{} Science \\& Nature"), "Straße ø Science & Nature");
+        assert_eq!(to_unicode("The \\LaTeX\\ Companion {ABC}"), "The \\LaTeX\\ Companion {ABC}");
+        assert_eq!(to_unicode("\\~{}uno"), "~uno");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("Körper"), "K{\\\"o}rper");
+        assert_eq!(escape("Erdős Čech í Straße"), "Erd{\\H o}s {\\v C}ech {\\'\\i} Stra{\\ss}e");
+        assert_eq!(escape("Science & Nature, 5\\%"), "Science \\& Nature, 5\\%");
+        for text in ["Zur Elektrodynamik bewegter K{\\\"o}rper", "Fran{\\c c}ois {\\v C}ech"].iter() {
+            assert_eq!(escape(&to_unicode(text)), *text);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 #![feature(trait_alias)]
 #[doc=include_str!("../README.md")]
+use std::env;
 use std::iter::FromIterator;
+use std::path::PathBuf;
 use structopt::StructOpt;
 use crate::formatter::ToString;
 
@@ -10,12 +12,28 @@ mod database;
 mod entry_type;
 mod file;
 mod formatter;
+mod identifier;
+mod latex;
+mod metadata;
 mod model;
+mod pdf;
 mod reader;
 mod util;
+mod validate;
 
 #[derive(StructOpt, Debug, PartialEq)]
 #[structopt(name = "bibrs")]
+struct Opt {
+    #[structopt(long = "library", global = true, help = "named library from the config, main for the main one")]
+    library: Option<String>,
+    #[structopt(long = "config", global = true, parse(from_os_str),
+                help = "config file, instead of BIBRS_CONFIG or ~/.config/bibrs/bibrs.toml")]
+    config: Option<PathBuf>,
+    #[structopt(subcommand)]
+    command: Bibrs,
+}
+
+#[derive(StructOpt, Debug, PartialEq)]
 enum Bibrs {
     #[structopt(name = "s", about = "search")]
     Search {
@@ -23,8 +41,54 @@ enum Bibrs {
         authors: Vec<String>,
         #[structopt(short = "k", long = "keyword")]
         keywords: Vec<String>,
+        #[structopt(short = "c", long = "collection", help = "collection path, subcollections included")]
+        collection: Option<String>,
+        #[structopt(short = "e", long = "expand", help = "also match narrower keywords in the taxonomy")]
+        expand: bool,
+        #[structopt(short = "f", long = "fulltext", help = "phrase in the attached pdf and comment files")]
+        fulltext: Option<String>,
+        #[structopt(long = "status", help = "unread, reading, read or skimmed, by priority")]
+        status: Option<String>,
+        #[structopt(long = "rating", help = "lowest rating")]
+        rating: Option<i32>,
+        #[structopt(long = "since", help = "added on or after a date like 2026-10-19, or within an age like 7d")]
+        since: Option<String>,
+        #[structopt(long = "until", help = "added before a date like 2026-10-19, or before an age like 7d")]
+        until: Option<String>,
+        #[structopt(long = "modified", help = "--since and --until go by the date last modified")]
+        modified: bool,
+    },
+    #[structopt(name = "recent", about = "list the entries added recently, newest first")]
+    Recent {
+        #[structopt(long = "days", default_value = "7")]
+        days: i64,
+        #[structopt(long = "modified", help = "the entries modified recently instead")]
+        modified: bool,
+    },
+    #[structopt(name = "link", about = "relations between entries, like citations")]
+    Link(LinkCommand),
+    #[structopt(name = "graph", about = "show the entries related to an entry, as text or graphviz dot")]
+    Graph {
+        #[structopt()]
+        id: String,
+        #[structopt(long = "depth", default_value = "1")]
+        depth: usize,
+        #[structopt(long = "format", default_value = "text", possible_values = &["text", "dot"])]
+        format: String,
     },
-    #[structopt(name = "o", about = "open pdf files and comments")]
+    #[structopt(name = "stats", about = "summarize the library")]
+    Stats {
+        #[structopt(long = "top", default_value = "10", help = "number of journals, authors and keywords listed")]
+        top: i64,
+        #[structopt(long = "format", default_value = "table", possible_values = &["table", "json", "csv"])]
+        format: String,
+    },
+    #[structopt(name = "get", about = "find an entry by DOI, PubMed ID, arXiv ID or ISBN")]
+    Get {
+        #[structopt(help = "typed identifier, like doi:10.1002/andp.19053221004 or pmid:15539593")]
+        identifier: String,
+    },
+    #[structopt(name = "o", about = "open the pdf, comments, or choose among the attached files")]
     Open {
         #[structopt()]
         id: String,
@@ -33,15 +97,58 @@ enum Bibrs {
         #[structopt(short = "p", long = "pdf")]
         pdf: bool,
     },
+    #[structopt(name = "mark", about = "set the reading status, priority and rating")]
+    Mark {
+        #[structopt()]
+        id: String,
+        #[structopt(help = "unread, reading, read or skimmed")]
+        status: Option<String>,
+        #[structopt(long = "priority", help = "place in the reading queue, 1 is the most urgent, 0 removes it")]
+        priority: Option<i32>,
+        #[structopt(long = "rating", help = "1 to 5, 0 removes it")]
+        rating: Option<i32>,
+    },
+    #[structopt(name = "attach", about = "attach a file, like a supplement, dataset, slides or another pdf")]
+    Attach {
+        #[structopt()]
+        id: String,
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        #[structopt(long = "role", default_value = "supplement",
+                    help = "main, preprint, published, supplement, dataset, slides...")]
+        role: String,
+        #[structopt(long = "label", help = "short description shown when choosing a file to open")]
+        label: Option<String>,
+    },
     #[structopt(name = "a", about = "add entry")]
     Add {
         #[structopt()]
         keywords: Vec<String>,
+        #[structopt(long = "pdf", parse(from_os_str), help = "add from a pdf alone, without bibtex file")]
+        pdf: Option<PathBuf>,
     },
     #[structopt(name = "d", about = "delete entry")]
     Delete {
         #[structopt()]
         id: String,
+        #[structopt(long = "keep-files", help = "keep the pdf and comment files on disk")]
+        keep_files: bool,
+    },
+    #[structopt(name = "r", about = "rename entry")]
+    Rename {
+        #[structopt()]
+        from: String,
+        #[structopt()]
+        to: String,
+    },
+    #[structopt(name = "trash", about = "list, restore or empty deleted entries")]
+    Trash(TrashCommand),
+    #[structopt(name = "undo", about = "revert the last keyword change, update, rename or delete")]
+    Undo,
+    #[structopt(name = "log", about = "show the change history of an entry")]
+    Log {
+        #[structopt()]
+        id: String,
     },
     #[structopt(name = "u", about = "output info")]
     Output {
@@ -51,6 +158,8 @@ enum Bibrs {
         bibtex: bool,
         #[structopt(short = "s", long = "string")]
         simple: bool,
+        #[structopt(short = "c", long = "collection", help = "source is a collection path")]
+        collection: bool,
     },
     #[structopt(name = "k", about = "add or delete keywords")]
     Keywords {
@@ -61,36 +170,334 @@ enum Bibrs {
         #[structopt(short = "d", long = "del")]
         del: Vec<String>,
     },
+    #[structopt(name = "c", about = "organize entries in nested collections")]
+    Collection(CollectionCommand),
+    #[structopt(name = "kw", about = "manage keywords across the library")]
+    KeywordManage(KeywordCommand),
+    #[structopt(name = "lint", about = "check entries for missing fields and malformed values")]
+    Lint {
+        #[structopt(help = "entries to check, the whole library if none")]
+        ids: Vec<String>,
+        #[structopt(long = "fix", help = "save safe corrections, like page ranges and DOI prefixes")]
+        fix: bool,
+    },
+    #[structopt(name = "enrich", about = "fill in missing fields from a metadata dump")]
+    Enrich {
+        #[structopt(help = "entries to enrich, the whole library if none")]
+        ids: Vec<String>,
+        #[structopt(long = "source", parse(from_os_str), help = "JSON-lines dump of CrossRef or OpenAlex works")]
+        source: Option<PathBuf>,
+        #[structopt(long = "apply", help = "save the new fields instead of only listing them")]
+        apply: bool,
+    },
+    #[structopt(name = "notes", about = "show the comment file of an entry")]
+    Notes {
+        #[structopt()]
+        id: String,
+        #[structopt(long = "extract", help = "first append the highlights and notes in the pdf")]
+        extract: bool,
+    },
+    #[structopt(name = "fsck", about = "check attached files against their records")]
+    Fsck {
+        #[structopt(long = "repair", help = "drop records of missing files, move orphaned files to trash and \
+                                             record changed hashes")]
+        repair: bool,
+        #[structopt(long = "dates", help = "date entries without date added by their oldest file")]
+        dates: bool,
+    },
+    #[structopt(name = "files", about = "manage the attached files on disk")]
+    Files(FilesCommand),
+    #[structopt(name = "pack", about = "write entries with their files to a zip archive")]
+    Pack {
+        #[structopt(help = "citations, or files citing entries")]
+        sources: Vec<String>,
+        #[structopt(short = "a", long = "author")]
+        authors: Vec<String>,
+        #[structopt(short = "k", long = "keyword")]
+        keywords: Vec<String>,
+        #[structopt(short = "c", long = "collection")]
+        collection: Option<String>,
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: PathBuf,
+        #[structopt(long = "csl", help = "CSL-JSON instead of BibTeX")]
+        csl: bool,
+    },
+    #[structopt(name = "unpack", about = "import the entries and files of a zip archive written by pack")]
+    Unpack {
+        #[structopt(parse(from_os_str))]
+        archive: PathBuf,
+    },
+    #[structopt(name = "sync", about = "merge the changes made in another copy of the library")]
+    Sync {
+        #[structopt(parse(from_os_str), help = "the other library.sqlite, like a copy left by a sync conflict")]
+        library: PathBuf,
+        #[structopt(long = "report", help = "only report fields changed in both libraries, without asking")]
+        report: bool,
+    },
+    #[structopt(name = "libraries", about = "list the named libraries in the config")]
+    Libraries,
+    #[structopt(name = "cp", about = "copy entries with their files to another library")]
+    Copy {
+        #[structopt(required = true)]
+        ids: Vec<String>,
+        #[structopt(long = "to", help = "name of the library, main for the main one")]
+        to: String,
+    },
+    #[structopt(name = "index", about = "update the full-text index of attached files")]
+    Index,
     #[structopt(name = "init", about = "initialize folders and datebase")]
     Init,
 }
 
+#[derive(StructOpt, Debug, PartialEq)]
+enum TrashCommand {
+    #[structopt(name = "list", about = "list deleted entries")]
+    List,
+    #[structopt(name = "restore", about = "put a deleted entry back")]
+    Restore {
+        #[structopt()]
+        id: i64,
+    },
+    #[structopt(name = "empty", about = "permanently remove deleted entries and their files")]
+    Empty {
+        #[structopt(long = "older-than", help = "only entries deleted before this age, e.g. 30d")]
+        older_than: Option<String>,
+    },
+}
+
+#[derive(StructOpt, Debug, PartialEq)]
+enum CollectionCommand {
+    #[structopt(name = "add", about = "create a collection, and add entries to it")]
+    Add {
+        #[structopt(help = "collection path, like thesis/ch3")]
+        path: String,
+        #[structopt()]
+        ids: Vec<String>,
+    },
+    #[structopt(name = "rm", about = "remove entries from a collection, or delete the collection")]
+    Rm {
+        #[structopt()]
+        path: String,
+        #[structopt()]
+        ids: Vec<String>,
+    },
+    #[structopt(name = "ls", about = "list subcollections and entries")]
+    Ls {
+        #[structopt()]
+        path: Option<String>,
+    },
+    #[structopt(name = "mv", about = "move or rename a collection")]
+    Mv {
+        #[structopt()]
+        from: String,
+        #[structopt()]
+        to: String,
+    },
+}
+
+#[derive(StructOpt, Debug, PartialEq)]
+enum LinkCommand {
+    #[structopt(name = "add", about = "relate an entry to others")]
+    Add {
+        #[structopt()]
+        id: String,
+        #[structopt(help = "cites, cited-by, erratum-of, preprint-of or commentary-on")]
+        relation: String,
+        #[structopt(required = true)]
+        others: Vec<String>,
+    },
+    #[structopt(name = "rm", about = "remove relations of an entry")]
+    Rm {
+        #[structopt()]
+        id: String,
+        #[structopt()]
+        relation: String,
+        #[structopt(required = true)]
+        others: Vec<String>,
+    },
+    #[structopt(name = "ls", about = "list the entries related to an entry")]
+    Ls {
+        #[structopt()]
+        id: String,
+    },
+    #[structopt(name = "import", about = "link an entry to the references in a .bib or CSL-JSON file")]
+    Import {
+        #[structopt()]
+        id: String,
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+}
+
+#[derive(StructOpt, Debug, PartialEq)]
+enum FilesCommand {
+    #[structopt(name = "reorganize", about = "move attached files to the names given by the naming templates")]
+    Reorganize {
+        #[structopt(long = "dry-run", help = "only list the moves")]
+        dry_run: bool,
+    },
+}
+
+#[derive(StructOpt, Debug, PartialEq)]
+enum KeywordCommand {
+    #[structopt(name = "list", about = "list keywords with the number of entries")]
+    List,
+    #[structopt(name = "rename", about = "rename a keyword on every entry")]
+    Rename {
+        #[structopt()]
+        old: String,
+        #[structopt()]
+        new: String,
+    },
+    #[structopt(name = "merge", about = "merge keywords into one, the merged ones become aliases")]
+    Merge {
+        #[structopt()]
+        target: String,
+        #[structopt(required = true)]
+        sources: Vec<String>,
+    },
+    #[structopt(name = "alias", about = "add aliases to a keyword, or list all aliases")]
+    Alias {
+        #[structopt()]
+        keyword: Option<String>,
+        #[structopt()]
+        aliases: Vec<String>,
+    },
+    #[structopt(name = "unalias", about = "remove aliases")]
+    Unalias {
+        #[structopt(required = true)]
+        aliases: Vec<String>,
+    },
+    #[structopt(name = "parent", about = "put a keyword under a broader one, or at the top level")]
+    Parent {
+        #[structopt()]
+        keyword: String,
+        #[structopt()]
+        parent: Option<String>,
+    },
+    #[structopt(name = "tree", about = "show the keyword taxonomy")]
+    Tree,
+    #[structopt(name = "tag", about = "add or delete keywords on every entry found by a search")]
+    Tag {
+        #[structopt(short = "a", long = "author")]
+        authors: Vec<String>,
+        #[structopt(short = "k", long = "keyword")]
+        keywords: Vec<String>,
+        #[structopt(short = "c", long = "collection")]
+        collection: Option<String>,
+        #[structopt(long = "add")]
+        add: Vec<String>,
+        #[structopt(long = "del")]
+        del: Vec<String>,
+    },
+}
+
+fn keyword_arg(input: &str) -> String { input.trim().to_lowercase() }
+
 fn comma_separate_args<T: FromIterator<String>>(input: Vec<String>) -> T {
     input.join(" ").split(',').map(|x| x.trim().to_string().to_lowercase()).filter(|x| !x.is_empty()).collect()
 }
 
 fn main() {
-    let opt = Bibrs::from_args();
+    let Opt{library, config, command: opt} = Opt::from_args();
+    // read by CONFIG when it is first used
+    if let Some(config) = config { env::set_var("BIBRS_CONFIG", config); }
+    if let Some(library) = library { env::set_var("BIBRS_LIBRARY", if library == "main" { "" } else { library.as_str() }); }
     if let Bibrs::Init = opt {
         config::initialize();
         return
     }
     let conn = database::SqliteBibDB::new(None);
     match opt {
-        Bibrs::Search{authors, keywords} =>
-            println!("{}", action::search(&conn, comma_separate_args(authors), comma_separate_args(keywords))),
+        Bibrs::Search{authors, keywords, collection, expand, fulltext: Some(phrase), ..} =>
+            println!("{}", action::fulltext::search_fulltext(&conn, &phrase, comma_separate_args(authors),
+                comma_separate_args(keywords), collection.as_deref(), expand)),
+        Bibrs::Search{authors, keywords, collection, expand, fulltext: None, status, rating, since, until, modified} => {
+            let conditions = action::Conditions{status: status.as_deref(), min_rating: rating, since: since.as_deref(),
+                                                until: until.as_deref(), modified};
+            println!("{}", action::search(&conn, comma_separate_args(authors), comma_separate_args(keywords),
+                                          collection.as_deref(), expand, &conditions))
+        },
+        Bibrs::Recent{days, modified} => println!("{}", action::recent(&conn, days, modified)),
+        Bibrs::Mark{id, status, priority, rating} =>
+            println!("{}", action::mark(&conn, &id, status.as_deref(), priority, rating)),
+        Bibrs::Get{identifier} => println!("{}", action::get(&conn, &identifier)),
         Bibrs::Open{id, comment, pdf} => action::open(&conn, &id, comment, pdf),
-        Bibrs::Add{keywords} => action::add_item(comma_separate_args(keywords)),
-        Bibrs::Delete{id} => action::delete(&conn, &id),
-        Bibrs::Output{source, bibtex, simple} => {
+        Bibrs::Attach{id, file, role, label} =>
+            println!("{}", action::attach(&conn, &id, &file, &role, label.as_deref())),
+        Bibrs::Add{keywords, pdf: Some(pdf)} =>
+            println!("{}", action::add_pdf(&conn, &pdf, comma_separate_args(keywords))),
+        Bibrs::Add{keywords, pdf: None} => println!("{}", action::add_item(&conn, comma_separate_args(keywords))),
+        Bibrs::Delete{id, keep_files} => action::delete(&conn, &id, keep_files),
+        Bibrs::Rename{from, to} => println!("{}", action::rename(&conn, &from, &to)),
+        Bibrs::Trash(command) => match command {
+            TrashCommand::List => println!("{}", action::trash::list(&conn)),
+            TrashCommand::Restore{id} => match action::trash::restore(&conn, id) {
+                Ok(x) | Err(x) => println!("{}", x),
+            },
+            TrashCommand::Empty{older_than} => println!("{}", action::trash::empty(&conn, older_than.as_deref())),
+        },
+        Bibrs::Undo => println!("{}", action::undo(&conn)),
+        Bibrs::Log{id} => println!("{}", action::log(&conn, &id)),
+        Bibrs::Output{source, bibtex, simple, collection: true} => {
+            if bibtex { println!("{}", action::collection::output(&conn, &source, true)); }
+            if simple || !bibtex { println!("{}", action::collection::output(&conn, &source, false)); }
+        },
+        Bibrs::Output{source, bibtex, simple, collection: false} => {
             if bibtex { println!("{}", action::output_bib(&conn, &source)); }
             if simple || !bibtex { println!("{}", action::output_str(&conn, &source)); }
         },
+        Bibrs::Collection(command) => println!("{}", match command {
+            CollectionCommand::Add{path, ids} => action::collection::add(&conn, &path, &ids),
+            CollectionCommand::Rm{path, ids} => action::collection::remove(&conn, &path, &ids),
+            CollectionCommand::Ls{path} => action::collection::list(&conn, path.as_deref()),
+            CollectionCommand::Mv{from, to} => action::collection::move_to(&conn, &from, &to),
+        }),
+        Bibrs::Link(command) => println!("{}", match command {
+            LinkCommand::Add{id, relation, others} => action::relation::add(&conn, &id, &relation, &others),
+            LinkCommand::Rm{id, relation, others} => action::relation::remove(&conn, &id, &relation, &others),
+            LinkCommand::Ls{id} => action::relation::list(&conn, &id),
+            LinkCommand::Import{id, file} => action::relation::import(&conn, &id, &file),
+        }),
+        Bibrs::Stats{top, format} => println!("{}", action::stats(&conn, top, &format)),
+        Bibrs::Graph{id, depth, format} => println!("{}", action::relation::graph(&conn, &id, depth, &format)),
+        Bibrs::KeywordManage(command) => println!("{}", match command {
+            KeywordCommand::List => action::keywords::list(&conn),
+            KeywordCommand::Rename{old, new} => action::keywords::rename(&conn, &keyword_arg(&old), &keyword_arg(&new)),
+            KeywordCommand::Merge{target, sources} => action::keywords::merge(&conn, &keyword_arg(&target),
+                &sources.iter().map(|x| keyword_arg(x)).collect::<Vec<String>>()),
+            KeywordCommand::Alias{keyword, aliases} => action::keywords::alias(&conn,
+                keyword.map(|x| keyword_arg(&x)).as_deref(), &aliases.iter().map(|x| keyword_arg(x)).collect::<Vec<String>>()),
+            KeywordCommand::Unalias{aliases} => action::keywords::unalias(&conn,
+                &aliases.iter().map(|x| keyword_arg(x)).collect::<Vec<String>>()),
+            KeywordCommand::Parent{keyword, parent} => action::keywords::parent(&conn, &keyword_arg(&keyword),
+                parent.map(|x| keyword_arg(&x)).as_deref()),
+            KeywordCommand::Tree => action::keywords::tree(&conn),
+            KeywordCommand::Tag{authors, keywords, collection, add, del} =>
+                action::keywords::tag(&conn, comma_separate_args(authors), comma_separate_args(keywords),
+                                      collection.as_deref(), comma_separate_args(add), comma_separate_args(del)),
+        }),
         Bibrs::Keywords{source, add, del} => {
             let (entry, keywords) = action::keywords(&conn, &source, comma_separate_args(add),
                                                      comma_separate_args(del));
             print!("{}\n\t{}", entry.to_str(), keywords);
         },
+        Bibrs::Lint{ids, fix} => println!("{}", action::lint(&conn, &ids, fix)),
+        Bibrs::Enrich{ids, source, apply} =>
+            println!("{}", action::enrich_from_dump(&conn, source.as_deref(), &ids, apply)),
+        Bibrs::Notes{id, extract} => println!("{}", action::notes(&conn, &id, extract)),
+        Bibrs::Files(command) => println!("{}", match command {
+            FilesCommand::Reorganize{dry_run} => action::files::reorganize(&conn, dry_run),
+        }),
+        Bibrs::Fsck{repair, dates} => println!("{}", action::fsck(&conn, repair, dates)),
+        Bibrs::Pack{sources, authors, keywords, collection, output, csl} =>
+            println!("{}", action::pack(&conn, &sources, comma_separate_args(authors), comma_separate_args(keywords),
+                                        collection.as_deref(), &output, csl)),
+        Bibrs::Unpack{archive} => println!("{}", action::unpack(&conn, &archive)),
+        Bibrs::Sync{library, report} => println!("{}", action::sync(&conn, &library, !report)),
+        Bibrs::Libraries => println!("{}", action::library::list()),
+        Bibrs::Copy{ids, to} => println!("{}", action::library::copy(&conn, &ids, &to)),
+        Bibrs::Index => println!("{}", action::fulltext::index(&conn)),
         Bibrs::Init => (),
     }
 }
@@ -114,12 +521,87 @@ mod tests {
         };
         let opt = Bibrs::from_iter(vec!["bibrs", "s", "-a", "casagrande", "rosa"]);
         match opt {
-            Bibrs::Search{authors, keywords} => {
+            Bibrs::Search{authors, keywords, collection, expand, fulltext, ..} => {
                 assert_eq!(authors, vec!["casagrande", "rosa"]);
                 assert_eq!(keywords, Vec::<&str>::new());
+                assert_eq!(collection, None);
+                assert!(!expand);
+                assert_eq!(fulltext, None);
             },
             _ => panic!("authors not matched"),
         }
+        let opt = Bibrs::from_iter(vec!["bibrs", "s", "--fulltext", "orientation columns", "-a", "hubel"]);
+        assert_eq!(opt, Bibrs::Search{authors: vec!["hubel".to_owned()], keywords: vec![], collection: None,
+            expand: false, fulltext: Some("orientation columns".to_owned()), status: None, rating: None, since: None,
+            until: None, modified: false});
+        let opt = Bibrs::from_iter(vec!["bibrs", "s", "--status", "unread", "-k", "cortex"]);
+        assert_eq!(opt, Bibrs::Search{authors: vec![], keywords: vec!["cortex".to_owned()], collection: None,
+            expand: false, fulltext: None, status: Some("unread".to_owned()), rating: None, since: None, until: None,
+            modified: false});
+        let opt = Bibrs::from_iter(vec!["bibrs", "s", "-k", "cortex", "--since", "2026-10-01", "--modified"]);
+        assert_eq!(opt, Bibrs::Search{authors: vec![], keywords: vec!["cortex".to_owned()], collection: None,
+            expand: false, fulltext: None, status: None, rating: None, since: Some("2026-10-01".to_owned()),
+            until: None, modified: true});
+        let opt = Bibrs::from_iter(vec!["bibrs", "recent", "--days", "30"]);
+        assert_eq!(opt, Bibrs::Recent{days: 30, modified: false});
+        let opt = Bibrs::from_iter(vec!["bibrs", "mark", "sur2005", "read", "--rating", "4"]);
+        assert_eq!(opt, Bibrs::Mark{id: "sur2005".to_owned(), status: Some("read".to_owned()), priority: None,
+            rating: Some(4)});
+        let opt = Bibrs::from_iter(vec!["bibrs", "d", "li2013", "--keep-files"]);
+        assert_eq!(opt, Bibrs::Delete{id: "li2013".to_owned(), keep_files: true});
+        let opt = Bibrs::from_iter(vec!["bibrs", "trash", "empty", "--older-than", "30d"]);
+        assert_eq!(opt, Bibrs::Trash(TrashCommand::Empty{older_than: Some("30d".to_owned())}));
+        let opt = Bibrs::from_iter(vec!["bibrs", "c", "add", "thesis/ch3", "li2013", "sur2005"]);
+        assert_eq!(opt, Bibrs::Collection(CollectionCommand::Add{path: "thesis/ch3".to_owned(),
+            ids: vec!["li2013".to_owned(), "sur2005".to_owned()]}));
+        let opt = Bibrs::from_iter(vec!["bibrs", "link", "add", "sur2005", "cited-by", "li2013"]);
+        assert_eq!(opt, Bibrs::Link(LinkCommand::Add{id: "sur2005".to_owned(), relation: "cited-by".to_owned(),
+            others: vec!["li2013".to_owned()]}));
+        let opt = Bibrs::from_iter(vec!["bibrs", "graph", "sur2005", "--depth", "2", "--format", "dot"]);
+        assert_eq!(opt, Bibrs::Graph{id: "sur2005".to_owned(), depth: 2, format: "dot".to_owned()});
+        let opt = Bibrs::from_iter(vec!["bibrs", "stats", "--format", "csv"]);
+        assert_eq!(opt, Bibrs::Stats{top: 10, format: "csv".to_owned()});
+        let opt = Bibrs::from_iter(vec!["bibrs", "trash", "restore", "12"]);
+        assert_eq!(opt, Bibrs::Trash(TrashCommand::Restore{id: 12}));
+        let opt = Bibrs::from_iter(vec!["bibrs", "kw", "merge", "visual cortex", "v1", "visual-cortex"]);
+        assert_eq!(opt, Bibrs::KeywordManage(KeywordCommand::Merge{target: "visual cortex".to_owned(),
+            sources: vec!["v1".to_owned(), "visual-cortex".to_owned()]}));
+        let opt = Bibrs::from_iter(vec!["bibrs", "kw", "parent", "v1", "visual cortex"]);
+        assert_eq!(opt, Bibrs::KeywordManage(KeywordCommand::Parent{keyword: "v1".to_owned(),
+            parent: Some("visual cortex".to_owned())}));
+        let opt = Bibrs::from_iter(vec!["bibrs", "kw", "tag", "-k", "v1", "--add", "cortex"]);
+        assert_eq!(opt, Bibrs::KeywordManage(KeywordCommand::Tag{authors: vec![], keywords: vec!["v1".to_owned()],
+            collection: None, add: vec!["cortex".to_owned()], del: vec![]}));
+        let opt = Bibrs::from_iter(vec!["bibrs", "get", "doi:10.1002/andp.19053221004"]);
+        assert_eq!(opt, Bibrs::Get{identifier: "doi:10.1002/andp.19053221004".to_owned()});
+        let opt = Bibrs::from_iter(vec!["bibrs", "enrich", "sur2005", "--source", "crossref.jsonl"]);
+        assert_eq!(opt, Bibrs::Enrich{ids: vec!["sur2005".to_owned()], source: Some(PathBuf::from("crossref.jsonl")),
+            apply: false});
+        let opt = Bibrs::from_iter(vec!["bibrs", "a", "--pdf", "sur2005.pdf", "cortex"]);
+        assert_eq!(opt, Bibrs::Add{keywords: vec!["cortex".to_owned()], pdf: Some(PathBuf::from("sur2005.pdf"))});
+        let opt = Bibrs::from_iter(vec!["bibrs", "lint", "--fix"]);
+        assert_eq!(opt, Bibrs::Lint{ids: vec![], fix: true});
+        let opt = Bibrs::from_iter(vec!["bibrs", "attach", "sur2005", "tables.xlsx", "--role", "dataset"]);
+        assert_eq!(opt, Bibrs::Attach{id: "sur2005".to_owned(), file: PathBuf::from("tables.xlsx"),
+            role: "dataset".to_owned(), label: None});
+        let opt = Bibrs::from_iter(vec!["bibrs", "files", "reorganize", "--dry-run"]);
+        assert_eq!(opt, Bibrs::Files(FilesCommand::Reorganize{dry_run: true}));
+        let opt = Bibrs::from_iter(vec!["bibrs", "fsck", "--repair"]);
+        assert_eq!(opt, Bibrs::Fsck{repair: true, dates: false});
+        let opt = Bibrs::from_iter(vec!["bibrs", "fsck", "--dates"]);
+        assert_eq!(opt, Bibrs::Fsck{repair: false, dates: true});
+        let opt = Bibrs::from_iter(vec!["bibrs", "notes", "sur2005", "--extract"]);
+        assert_eq!(opt, Bibrs::Notes{id: "sur2005".to_owned(), extract: true});
+        let opt = Bibrs::from_iter(vec!["bibrs", "pack", "thesis.md", "-c", "thesis", "-o", "thesis.zip", "--csl"]);
+        assert_eq!(opt, Bibrs::Pack{sources: vec!["thesis.md".to_owned()], authors: vec![], keywords: vec![],
+            collection: Some("thesis".to_owned()), output: PathBuf::from("thesis.zip"), csl: true});
+        let opt = Opt::from_iter(vec!["bibrs", "cp", "sur2005", "--to", "lab", "--config", "bibrs.toml"]);
+        assert_eq!(opt, Opt{library: None, config: Some(PathBuf::from("bibrs.toml")),
+            command: Bibrs::Copy{ids: vec!["sur2005".to_owned()], to: "lab".to_owned()}});
+        let opt = Opt::from_iter(vec!["bibrs", "--library", "lab", "s", "-a", "sur"]);
+        assert_eq!(opt.library, Some("lab".to_owned()));
+        let opt = Bibrs::from_iter(vec!["bibrs", "sync", "library (conflicted copy).sqlite", "--report"]);
+        assert_eq!(opt, Bibrs::Sync{library: PathBuf::from("library (conflicted copy).sqlite"), report: true});
     }
 
     #[test]
@@ -0,0 +1,119 @@
+//! Sources of bibliographic metadata to complete entries with
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use serde_json::Value;
+
+use crate::identifier::{Identifier, IdType};
+use crate::model::Entry;
+use crate::reader::crossref::read_record;
+
+pub trait MetadataSource {
+    /// the record of the same paper, found by DOI or else by title and year
+    fn lookup(&self, entry: &Entry) -> Option<Entry>;
+}
+
+/// letters and digits of a title in lowercase, to compare titles regardless of case, braces and
+/// punctuation
+pub fn title_key(title: &str) -> String {
+    title.chars().filter(|x| x.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn dois(entry: &Entry) -> impl Iterator<Item=String> {
+    Identifier::from_entry(entry).into_iter().filter(|x| x.id_type == IdType::Doi).map(|x| x.value)
+}
+
+/// A local dump in JSON lines, like a CrossRef or OpenAlex snapshot, of which the records in use
+/// are held in memory
+#[derive(Default)]
+pub struct JsonLinesSource {
+    records: Vec<Entry>,
+    by_doi: HashMap<String, usize>,
+    by_title: HashMap<(String, i32), usize>,
+}
+
+impl JsonLinesSource {
+    /// Read the dump line by line and keep only the records of the entries, found by DOI or else by
+    /// title and year, as a dump can be larger than memory. Lines that are not JSON are skipped.
+    /// Returns:
+    ///     (source, number of skipped lines)
+    pub fn open(path: &Path, entries: &[Entry]) -> io::Result<(Self, usize)> {
+        let mut wanted = JsonLinesSource::default();
+        for entry in entries.iter() { wanted.insert(entry.clone()); }
+        let mut source = JsonLinesSource::default();
+        let mut skipped = 0;
+        for line in BufReader::new(File::open(path)?).lines() {
+            if !source.read_line(&line?, Some(&wanted)) { skipped += 1; }
+        }
+        Ok((source, skipped))
+    }
+
+    pub fn from_lines<'a, T: Iterator<Item=&'a str>>(lines: T) -> (Self, usize) {
+        let mut source = JsonLinesSource::default();
+        let skipped = lines.filter(|x| !source.read_line(x, None)).count();
+        (source, skipped)
+    }
+
+    /// Keep the record on the line if it is wanted, works without a title
+    /// Returns:
+    ///     false if the line is not a record
+    fn read_line(&mut self, line: &str, wanted: Option<&JsonLinesSource>) -> bool {
+        if line.trim().is_empty() { return true }
+        match serde_json::from_str::<Value>(line).ok().as_ref().and_then(read_record) {
+            Some(entry) => {
+                if wanted.map_or(true, |x| x.lookup(&entry).is_some()) { self.insert(entry); }
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        let idx = self.records.len();
+        for doi in dois(&entry) { self.by_doi.entry(doi).or_insert(idx); }
+        if entry.year != 0 { self.by_title.entry((title_key(&entry.title), entry.year)).or_insert(idx); }
+        self.records.push(entry);
+    }
+}
+
+impl MetadataSource for JsonLinesSource {
+    fn lookup(&self, entry: &Entry) -> Option<Entry> {
+        let idx = match dois(entry).find_map(|x| self.by_doi.get(&x)) {
+            Some(idx) => idx,
+            None if entry.year != 0 => self.by_title.get(&(title_key(&entry.title), entry.year))?,
+            None => return None,
+        };
+        Some(self.records[*idx].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_lines() {
+        let lines = [
+            r#"{"DOI": "10.1126/science.1112070", "title": ["Patterning and plasticity of the cerebral cortex"],
+                "issued": {"date-parts": [[2005]]}, "page": "805-810"}"#.replace('\n', " "),
+            "not json".to_owned(),
+            r#"{"doi": "https://doi.org/10.1002/andp.19053221004", "display_name": "On the electrodynamics",
+                "publication_year": 1905, "biblio": {"volume": "322"}}"#.replace('\n', " ")];
+        let (source, skipped) = JsonLinesSource::from_lines(lines.iter().map(|x| x.as_str()));
+        assert_eq!((source.records.len(), skipped), (2, 1));
+        let mut entry = Entry{title: "Patterning and Plasticity of the {Cerebral} Cortex.".to_owned(), year: 2005,
+                              ..Default::default()};
+        assert_eq!(source.lookup(&entry).unwrap().pages, Some("805-810".to_owned()));
+        entry.year = 2006;
+        assert!(source.lookup(&entry).is_none());
+        entry.extra_fields.insert("doi".to_owned(), "10.1002/ANDP.19053221004".to_owned());
+        assert_eq!(source.lookup(&entry).unwrap().volume, Some(322));
+        let path = std::env::temp_dir().join("bibrs-test-dump.jsonl");
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let (source, skipped) = JsonLinesSource::open(&path, &[entry]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((source.records.len(), skipped), (1, 1));
+        assert_eq!(source.records[0].volume, Some(322));
+    }
+}
@@ -9,6 +9,10 @@ pub struct Person {
     pub last_name: String,
     pub first_name: String,
     pub search_term: String,
+    /// the von part, like "van" or "de la"
+    pub prefix: String,
+    /// the Jr part as written, like "Jr." or "III"
+    pub suffix: String,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -30,6 +34,74 @@ pub struct Entry {
     pub keywords: HashSet<String>,
     pub extra_fields: HashMap<String, String>,
     pub files: Vec<(String, String)>,
+    /// None if never marked
+    pub reading: Option<Reading>,
+    /// unix timestamps, None for entries not in the library or added before dates were kept
+    pub added_at: Option<i64>,
+    pub modified_at: Option<i64>,
+}
+
+/// How far the reader is with an entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadingStatus {
+    Unread,
+    Reading,
+    Read,
+    Skimmed,
+}
+
+impl ReadingStatus {
+    pub fn parse(input: &str) -> Option<ReadingStatus> {
+        match input.trim().to_lowercase().as_str() {
+            "unread" => Some(ReadingStatus::Unread),
+            "reading" => Some(ReadingStatus::Reading),
+            "read" => Some(ReadingStatus::Read),
+            "skimmed" => Some(ReadingStatus::Skimmed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReadingStatus::Unread => "unread",
+            ReadingStatus::Reading => "reading",
+            ReadingStatus::Read => "read",
+            ReadingStatus::Skimmed => "skimmed",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool { matches!(self, ReadingStatus::Read | ReadingStatus::Skimmed) }
+}
+
+/// Reading status of an entry, with its place in the to-read queue and its rating
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub status: ReadingStatus,
+    /// 1 is the most urgent
+    pub priority: Option<i32>,
+    /// 1 to 5
+    pub rating: Option<i32>,
+    /// unix timestamps of the last change, of when reading started and of when it was finished
+    pub marked_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+}
+
+impl Reading {
+    pub fn new(now: i64) -> Self {
+        Reading{status: ReadingStatus::Unread, priority: None, rating: None, marked_at: now, started_at: None,
+                finished_at: None}
+    }
+
+    /// Change the status, the start is kept from the first time reading, the finish from the
+    /// last time it was read or skimmed
+    pub fn set_status(&mut self, status: ReadingStatus, now: i64) {
+        if status == ReadingStatus::Reading && self.started_at.is_none() { self.started_at = Some(now); }
+        if !status.is_finished() { self.finished_at = None; }
+        else if !self.status.is_finished() { self.finished_at = Some(now); }
+        self.status = status;
+        self.marked_at = now;
+    }
 }
 
 fn update_option<T>(this: &mut Option<T>, that: &Option<T>) where T: Clone {
@@ -40,6 +112,8 @@ impl Entry {
     /// Unlike what the operation suggests, it updates the other way: add stuff to self only if self 
     /// does not have it.
     pub fn update(&mut self, other: &Entry) {
+        if self.title.is_empty() { self.title = other.title.clone(); }
+        if self.year == 0 { self.year = other.year; }
         update_option(&mut self.booktitle, &other.booktitle);
         update_option(&mut self.month, &other.month);
         update_option(&mut self.chapter, &other.chapter);
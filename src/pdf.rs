@@ -0,0 +1,257 @@
+//! Metadata of PDF files, from the XMP packet, the Info dictionary and identifiers printed on the
+//! first pages, the text of their pages and the annotations made by readers
+use std::path::Path;
+use lazy_static::lazy_static;
+use lopdf::{Document, Object};
+use regex::Regex;
+
+use crate::identifier::{Identifier, IdType};
+use crate::model::{Entry, Person};
+use crate::reader::bibtex::{load_pages, parse_year};
+
+/// pages searched for a DOI or arXiv ID
+const TEXT_PAGES: u32 = 2;
+
+lazy_static!{
+    static ref DOI_TEXT_RE: Regex = Regex::new(r#"10\.\d{4,9}/[^\s"<>]+"#).unwrap();
+    static ref ARXIV_TEXT_RE: Regex = Regex::new(
+        r#"(?i)arxiv:\s*(\d{4}\.\d{4,5}|[a-z\-]+(\.[a-z]{2})?/\d{7})(v\d+)?"#).unwrap();
+    static ref XMP_TITLE_RE: Regex = Regex::new(r#"(?s)<dc:title>.*?<rdf:li[^>]*>(.*?)</rdf:li>"#).unwrap();
+    static ref XMP_CREATOR_RE: Regex = Regex::new(r#"(?s)<dc:creator>(.*?)</dc:creator>"#).unwrap();
+    static ref XMP_ITEM_RE: Regex = Regex::new(r#"(?s)<rdf:li[^>]*>(.*?)</rdf:li>"#).unwrap();
+}
+
+/// the first DOI in the text, without trailing punctuation
+pub fn find_doi(text: &str) -> Option<String> {
+    DOI_TEXT_RE.find(text).map(|x| x.as_str().trim_end_matches(|c: char| ".,;:)]}".contains(c)).to_owned())
+        .filter(|x| Identifier::new(IdType::Doi, x).is_some())
+}
+
+/// the first arXiv ID in the text, without version
+pub fn find_arxiv(text: &str) -> Option<String> {
+    ARXIV_TEXT_RE.captures(text).and_then(|caps| Identifier::new(IdType::Arxiv, &caps[1])).map(|x| x.value)
+}
+
+fn unescape_xml(input: &str) -> String {
+    input.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'")
+        .replace("&amp;", "&").trim().to_owned()
+}
+
+/// a PRISM property, written either as an element or as an attribute
+fn prism(xmp: &str, name: &str) -> Option<String> {
+    let re = Regex::new(&format!(r#"prism:{}(?:>|=")([^<"]+)"#, name)).unwrap();
+    re.captures(xmp).map(|caps| unescape_xml(&caps[1])).filter(|x| !x.is_empty())
+}
+
+/// PDFs often carry the file name or the program as title
+fn plausible_title(title: &str) -> bool {
+    let lower = title.trim().to_lowercase();
+    !lower.is_empty() && lower != "untitled" && !lower.starts_with("microsoft word") &&
+        ![".pdf", ".doc", ".docx", ".dvi", ".tex", ".ps"].iter().any(|x| lower.ends_with(x))
+}
+
+fn with_identifiers(mut entry: Entry, text: &str) -> Entry {
+    if let Some(doi) = find_doi(text) { entry.extra_fields.insert("doi".to_owned(), doi); }
+    if let Some(arxiv) = find_arxiv(text) {
+        entry.extra_fields.insert("eprint".to_owned(), arxiv);
+        entry.extra_fields.insert("archiveprefix".to_owned(), "arXiv".to_owned());
+    }
+    entry
+}
+
+/// fields in an XMP packet: Dublin Core title and creators, PRISM journal, issue, pages and date
+pub fn read_xmp(xmp: &str) -> Entry {
+    let mut entry = Entry::default();
+    if let Some(title) = XMP_TITLE_RE.captures(xmp).map(|caps| unescape_xml(&caps[1])) {
+        if plausible_title(&title) { entry.title = title; }
+    }
+    if let Some(creators) = XMP_CREATOR_RE.captures(xmp) {
+        entry.authors = XMP_ITEM_RE.captures_iter(&creators[1]).map(|caps| unescape_xml(&caps[1]))
+            .filter(|x| !x.is_empty()).map(|x| Person::load(&x)).collect();
+    }
+    entry.journal = prism(xmp, "publicationName");
+    entry.volume = prism(xmp, "volume").and_then(|x| x.parse().ok());
+    entry.number = prism(xmp, "number").and_then(|x| x.parse().ok());
+    entry.pages = match (prism(xmp, "startingPage"), prism(xmp, "endingPage")) {
+        (Some(start), Some(end)) => Some(load_pages(&format!("{}-{}", start, end))),
+        (start, _) => start,
+    };
+    entry.year = prism(xmp, "coverDate").or_else(|| prism(xmp, "publicationDate"))
+        .and_then(|x| parse_year(&x)).unwrap_or(0);
+    let doi = prism(xmp, "doi").unwrap_or_default();
+    with_identifiers(entry, &format!("{}\n{}", doi, xmp))
+}
+
+/// Text of a PDF string, either UTF-16 with a byte order mark or PDFDocEncoding, read as Latin-1
+fn decode_string(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xfe, 0xff]) {
+        let units: Vec<u16> = bytes[2..].chunks(2).filter(|x| x.len() == 2)
+            .map(|x| u16::from_be_bytes([x[0], x[1]])).collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        bytes.iter().map(|x| *x as char).collect()
+    }
+}
+
+/// follow a reference to its object
+fn resolve<'a>(document: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    match object.as_reference() {
+        Ok(id) => document.get_object(id).ok(),
+        Err(_) => Some(object),
+    }
+}
+
+/// a string in the trailer's Info dictionary
+fn info_string(document: &Document, key: &[u8]) -> Option<String> {
+    let info = resolve(document, document.trailer.get(b"Info").ok()?)?.as_dict().ok()?;
+    match resolve(document, info.get(key).ok()?)? {
+        Object::String(bytes, _) => Some(decode_string(bytes).trim().to_owned()).filter(|x| !x.is_empty()),
+        _ => None,
+    }
+}
+
+/// the XMP packet referred to by the document catalog
+fn xmp_packet(document: &Document) -> Option<String> {
+    let catalog = resolve(document, document.trailer.get(b"Root").ok()?)?.as_dict().ok()?;
+    let stream = resolve(document, catalog.get(b"Metadata").ok()?)?.as_stream().ok()?;
+    let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+    Some(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// Provisional entry of the paper in a PDF, from the XMP packet, then the Info dictionary, then a
+/// DOI or arXiv ID found on the first pages. Fields that are not found are left empty.
+pub fn read_entry(path: &Path) -> lopdf::Result<Entry> {
+    let document = Document::load(path)?;
+    let mut entry = xmp_packet(&document).map(|x| read_xmp(&x)).unwrap_or_default();
+    let mut info = Entry::default();
+    if let Some(title) = info_string(&document, b"Title").filter(|x| plausible_title(x)) { info.title = title; }
+    if let Some(authors) = info_string(&document, b"Author") {
+        let separator = if authors.contains(';') { ';' } else { ',' };
+        info.authors = authors.split(separator).map(|x| x.trim()).filter(|x| !x.is_empty())
+            .map(Person::load).collect();
+    }
+    entry.update(&info);
+    let pages: Vec<u32> = document.get_pages().keys().cloned().take(TEXT_PAGES as usize).collect();
+    let text = document.extract_text(&pages).unwrap_or_default();
+    entry.update(&with_identifiers(Entry::default(), &text));
+    Ok(entry)
+}
+
+/// Text of every page, in order, a page the text cannot be extracted from is left empty
+pub fn page_texts(path: &Path) -> lopdf::Result<Vec<String>> {
+    let document = Document::load(path)?;
+    Ok(document.get_pages().keys().map(|x| document.extract_text(&[*x]).unwrap_or_default()).collect())
+}
+
+/// annotation subtypes that carry reading notes
+const NOTE_SUBTYPES: &[&str] = &["Highlight", "Underline", "Squiggly", "StrikeOut", "Text", "FreeText"];
+
+/// common highlighter colors to name an annotation color after
+const COLOR_NAMES: &[(&str, [f64; 3])] = &[
+    ("yellow", [1.0, 1.0, 0.0]), ("red", [1.0, 0.0, 0.0]), ("green", [0.0, 1.0, 0.0]), ("blue", [0.0, 0.0, 1.0]),
+    ("cyan", [0.0, 1.0, 1.0]), ("magenta", [1.0, 0.0, 1.0]), ("orange", [1.0, 0.5, 0.0]),
+    ("pink", [1.0, 0.75, 0.8]), ("purple", [0.5, 0.0, 0.5]), ("gray", [0.5, 0.5, 0.5])];
+
+/// A note a reader left in the PDF
+#[derive(Debug, PartialEq)]
+pub struct Annotation {
+    /// starting from 1
+    pub page: u32,
+    /// subtype in lowercase, like highlight or text
+    pub kind: String,
+    pub color: Option<String>,
+    /// the note, or the marked text as stored by the viewer
+    pub text: String,
+}
+
+/// Name of the closest common color to a color in gray, RGB or CMYK components
+fn color_name(components: &[f64]) -> Option<String> {
+    let rgb = match components {
+        [gray] => [*gray, *gray, *gray],
+        [r, g, b] => [*r, *g, *b],
+        [c, m, y, k] => [(1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)],
+        _ => return None,
+    };
+    let distance = |x: &[f64; 3]| x.iter().zip(rgb.iter()).map(|(a, b)| (a - b) * (a - b)).sum::<f64>();
+    COLOR_NAMES.iter().min_by(|a, b| distance(&a.1).partial_cmp(&distance(&b.1)).unwrap())
+        .map(|x| x.0.to_owned())
+}
+
+/// Highlights, underlines and notes on every page, in page order. Annotations without text are
+/// left out, as many viewers store only where a highlight is and not the marked text.
+pub fn annotations(path: &Path) -> lopdf::Result<Vec<Annotation>> {
+    let document = Document::load(path)?;
+    let mut output = Vec::new();
+    for (page, page_id) in document.get_pages() {
+        let annots = match document.get_dictionary(page_id).ok().and_then(|x| x.get(b"Annots").ok())
+                .and_then(|x| resolve(&document, x)).and_then(|x| x.as_array().ok()) {
+            Some(x) => x,
+            None => continue,
+        };
+        for annot in annots.iter().filter_map(|x| resolve(&document, x)?.as_dict().ok()) {
+            let kind = match annot.get(b"Subtype").and_then(Object::as_name_str) {
+                Ok(x) if NOTE_SUBTYPES.contains(&x) => x.to_lowercase(),
+                _ => continue,
+            };
+            let text = match annot.get(b"Contents").ok().and_then(|x| resolve(&document, x)) {
+                Some(Object::String(bytes, _)) =>
+                    decode_string(bytes).split_whitespace().collect::<Vec<&str>>().join(" "),
+                _ => continue,
+            };
+            if text.is_empty() { continue }
+            let color = annot.get(b"C").ok().and_then(|x| resolve(&document, x)).and_then(|x| x.as_array().ok())
+                .and_then(|x| color_name(&x.iter().filter_map(|y| y.as_f64().ok()).collect::<Vec<f64>>()));
+            output.push(Annotation{page, kind, color, text});
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(find_doi("Science 310, 805 (2005); DOI: 10.1126/science.1112070."),
+                   Some("10.1126/science.1112070".to_owned()));
+        assert_eq!(find_doi("https://doi.org/10.1002/andp.19053221004)"), Some("10.1002/andp.19053221004".to_owned()));
+        assert_eq!(find_doi("version 10.2 of the software"), None);
+        assert_eq!(find_arxiv("arXiv:1706.03762v5 [cs.CL] 6 Dec 2017"), Some("1706.03762".to_owned()));
+        assert_eq!(find_arxiv("no identifier"), None);
+    }
+
+    #[test]
+    fn test_xmp() {
+        let xmp = r#"<x:xmpmeta><rdf:RDF><rdf:Description rdf:about=""
+            prism:publicationName="Science" prism:volume="310" prism:startingPage="805" prism:endingPage="810"
+            prism:coverDate="2005-11-04">
+            <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Patterning and Plasticity of the Cerebral Cortex</rdf:li>
+            </rdf:Alt></dc:title>
+            <dc:creator><rdf:Seq><rdf:li>Mriganka Sur</rdf:li><rdf:li>John L. R. Rubenstein</rdf:li></rdf:Seq></dc:creator>
+            <prism:doi>10.1126/science.1112070</prism:doi>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+        let entry = read_xmp(xmp);
+        assert_eq!(entry.title, "Patterning and Plasticity of the Cerebral Cortex");
+        assert_eq!(entry.authors.iter().map(|x| x.search_term.as_str()).collect::<Vec<&str>>(), ["sur", "rubenstein"]);
+        assert_eq!((entry.year, entry.volume), (2005, Some(310)));
+        assert_eq!(entry.journal, Some("Science".to_owned()));
+        assert_eq!(entry.pages, Some("805-810".to_owned()));
+        assert_eq!(entry.extra_fields.get("doi").unwrap(), "10.1126/science.1112070");
+        let entry = read_xmp(r#"<dc:title><rdf:Alt><rdf:li>paper.dvi</rdf:li></rdf:Alt></dc:title>"#);
+        assert!(entry.title.is_empty());
+    }
+
+    #[test]
+    fn test_decode_string() {
+        assert_eq!(decode_string(&[0xfe, 0xff, 0x00, 0x47, 0x00, 0xf6]), "Gö");
+        assert_eq!(decode_string(b"G\xf6del"), "Gödel");
+    }
+
+    #[test]
+    fn test_color_name() {
+        assert_eq!(color_name(&[1.0, 0.9, 0.1]), Some("yellow".to_owned()));
+        assert_eq!(color_name(&[0.0, 0.0, 1.0, 0.0]), Some("yellow".to_owned()));
+        assert_eq!(color_name(&[0.2, 0.3]), None);
+    }
+}
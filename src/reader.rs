@@ -1,2 +1,3 @@
 pub mod bibtex;
+pub mod crossref;
 pub mod pandoc;
@@ -12,41 +12,137 @@ use lazy_static::lazy_static;
 
 use crate::model::{Entry, Person};
 use crate::entry_type::EntryType;
+use crate::latex::{to_unicode, VERBATIM_FIELDS};
 
+/// ASCII letters and digits only, accents dropped and letters like ß or ø spelled out
 fn strip_accent(input: &str) -> String {
-    input.nfd().filter(|x| x.is_ascii_alphanumeric()).collect::<String>()
+    input.nfd().map(|x| match x {
+        'ß' => "ss".to_owned(),
+        'ø' | 'Ø' => "o".to_owned(),
+        'æ' | 'Æ' => "ae".to_owned(),
+        'œ' | 'Œ' => "oe".to_owned(),
+        'ł' | 'Ł' => "l".to_owned(),
+        'ı' => "i".to_owned(),
+        x if x.is_ascii_alphanumeric() => x.to_string(),
+        _ => "".to_owned(),
+    }).collect::<String>()
+}
+
+/// Split the input at separators outside of braces, each piece trimmed
+fn split_top_level<F: Fn(char) -> bool>(input: &str, is_separator: F) -> Vec<String> {
+    let mut output = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    for c in input.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => (),
+        }
+        if depth == 0 && is_separator(c) {
+            output.push(current.trim().to_owned());
+            current.clear();
+        } else { current.push(c); }
+    }
+    output.push(current.trim().to_owned());
+    output
+}
+
+/// lowercase outside of braces, so {World Health Organization} keeps its case
+fn lower_unbraced(input: &str) -> String {
+    let mut depth = 0;
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => (),
+        }
+        if depth == 0 { output.extend(c.to_lowercase()); } else { output.push(c); }
+    }
+    output
+}
+
+fn split_words(input: &str) -> Vec<String> {
+    split_top_level(input, |c| c.is_whitespace() || c == '~').into_iter().filter(|x| !x.is_empty()).collect()
+}
+
+/// A word is in the von part if its first letter outside of braces is lowercase. Braced words
+/// like {World} count as uppercase, except special characters like {\'e} which go by their letter.
+fn is_lowercase_word(word: &str) -> bool {
+    let mut depth = 0;
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if depth == 0 && chars.peek() == Some(&'\\') => {
+                return chars.find(|x| x.is_alphabetic()).map_or(false, |x| x.is_lowercase());
+            },
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            x if depth == 0 && x.is_alphabetic() => return x.is_lowercase(),
+            _ => (),
+        }
+    }
+    false
+}
+
+/// Split the words of "von Last" where von runs to the last lowercase word, but Last keeps at least
+/// one word
+fn split_von(words: &[String]) -> (&[String], &[String]) {
+    let end = words.iter().take(words.len().saturating_sub(1)).rposition(|x| is_lowercase_word(x)).map_or(0, |x| x + 1);
+    words.split_at(end)
+}
+
+/// Jr., Sr., 3rd or roman numerals II to IV
+fn is_suffix(input: &str) -> bool {
+    lazy_static!{ static ref SUFFIX_RE: Regex = Regex::new(r#"^((?i:jr|sr|\d+(st|nd|rd|th))\.?|II|III|IV)$"#).unwrap(); }
+    SUFFIX_RE.is_match(input)
 }
 
 impl Person {
+    /// Parse a name in any of the BibTeX forms "First von Last", "von Last, First" and
+    /// "von Last, Jr, First". "First Last, Jr." is also accepted if the part after the comma is
+    /// a suffix like Jr. or III. LaTeX accents are converted to Unicode.
     pub fn load(input: &str) -> Self {
-        if input.contains(", ") {
-            let mut substr_iter = input.split(", ");
-            let last_name = substr_iter.next().unwrap().to_owned().to_lowercase();
-            let mut first_name = substr_iter.next().unwrap().to_owned().to_lowercase();
-            let search_term = strip_accent(&last_name);
-            Person { id: None, last_name, first_name, search_term }
-        } else {
-            let mut substr_iter = input.rsplitn(2, ' ');  // ! return word order is reversed too
-            let mut last_name = substr_iter.next().unwrap().to_owned().to_lowercase();
-            let mut first_name = substr_iter.next().unwrap().to_owned().to_lowercase();
-            let search_term = strip_accent(&last_name);
-            Person { id: None, last_name, first_name, search_term }
+        let parts = split_top_level(to_unicode(input).trim(), |c| c == ',');
+        let (first, von_last, suffix) = match parts.len() {
+            1 | 2 if parts.len() == 1 || is_suffix(&parts[1]) => {
+                let words = split_words(&parts[0]);
+                let last_start = words.len().saturating_sub(1);
+                // von starts at the first lowercase word, and Last always has the final word
+                let von_start = words[..last_start].iter().position(|x| is_lowercase_word(x)).unwrap_or(last_start);
+                let suffix = if parts.len() == 2 { vec![parts[1].clone()] } else { vec![] };
+                (words[..von_start].to_vec(), words[von_start..].to_vec(), suffix)
+            },
+            2 => (split_words(&parts[1]), split_words(&parts[0]), vec![]),
+            _ => (split_words(&parts[2..].join(" ")), split_words(&parts[0]), split_words(&parts[1])),
+        };
+        let (prefix, last_name) = split_von(&von_last);
+        let last_name = lower_unbraced(&last_name.join(" "));
+        let search_term = strip_accent(&last_name.to_lowercase());
+        Person {
+            id: None,
+            last_name,
+            first_name: lower_unbraced(&first.join(" ")),
+            search_term,
+            prefix: lower_unbraced(&prefix.join(" ")),
+            suffix: suffix.join(" "),
         }
     }
 }
 
-fn load_people(input: &str) -> Vec<Person> {
-    let mut result = Vec::new();
-    for person_str in input.split(" and ") { result.push(Person::load(person_str)); }
-    result
+/// Split an author or editor field on the word "and" outside of braces
+pub fn load_people(input: &str) -> Vec<Person> {
+    split_words(input).split(|x| x == "and").filter(|x| !x.is_empty())
+        .map(|words| Person::load(&words.join(" "))).collect()
 }
 
-fn load_title(input: &str) -> String {
+pub fn load_title(input: &str) -> String {
     lazy_static!{ static ref ITALIC_RE: Regex = Regex::new(r#"<\s*i\s*>([\w\s]+)<\s*/i>"#).unwrap(); }
     ITALIC_RE.replace(input, r#"\\textit{\1}"#).to_string()
 }
 
-fn load_pages(input: &str) -> String {
+pub fn load_pages(input: &str) -> String {
     lazy_static!{
         // force pages formatting 123-126, 123-6, 123:126, 123--126, 123_126 to 123-126
         static ref PAGE_RE: Regex = Regex::new(r#"^(\w*)(\d+)[-:_]{1,2}(\d+)$"#).unwrap();
@@ -60,8 +156,11 @@ fn load_pages(input: &str) -> String {
                 let start = caps.get(2).unwrap().as_str().to_owned();
                 let mut end = caps.get(3).unwrap().as_str().to_owned();
                 if start.len() > end.len() { end = start[0..start.len() - end.len()].to_owned() + &end; }
-                format!{"{}{}-{}", caps.get(1).unwrap().as_str(), start.parse::<u32>().unwrap(),
-                    end.parse::<u32>().unwrap()}
+                // numbers too long for a page are kept as written
+                match (start.parse::<u32>(), end.parse::<u32>()) {
+                    (Ok(start), Ok(end)) => format!{"{}{}-{}", caps.get(1).unwrap().as_str(), start, end},
+                    _ => input.to_owned(),
+                }
             },
             None => input.to_owned()
         }
@@ -70,49 +169,154 @@ fn load_pages(input: &str) -> String {
 
 fn load_keywords(input: &str) -> HashSet<String> { input.split(", ").map(|s| s.to_owned()).collect() }
 
+const MONTHS: &[&str] = &["january", "february", "march", "april", "may", "june", "july", "august",
+                          "september", "october", "november", "december"];
+
+/// the standard month macros, so that month = jan can be parsed
+const MONTH_STRINGS: &str = "@string{jan = \"January\"} @string{feb = \"February\"} @string{mar = \"March\"}
+@string{apr = \"April\"} @string{may = \"May\"} @string{jun = \"June\"} @string{jul = \"July\"}
+@string{aug = \"August\"} @string{sep = \"September\"} @string{oct = \"October\"} @string{nov = \"November\"}
+@string{dec = \"December\"}
+";
+
+/// Month from 1 to 12, or a name or abbreviation like Jan., sept or January
+fn parse_month(input: &str) -> Option<i32> {
+    let input = input.trim().trim_end_matches('.').to_lowercase();
+    if let Ok(month) = input.parse::<i32>() { return if (1..=12).contains(&month) { Some(month) } else { None } }
+    if input.len() < 3 { return None }
+    MONTHS.iter().position(|x| x.starts_with(&input)).map(|x| x as i32 + 1)
+}
+
+/// The first four digit year in values like 1905, 2019a, c1999 or 2019/2020
+pub fn parse_year(input: &str) -> Option<i32> {
+    lazy_static!{ static ref YEAR_RE: Regex = Regex::new(r#"(?:^|\D)(\d{4})(?:\D|$)"#).unwrap(); }
+    YEAR_RE.captures(input).map(|caps| caps[1].parse::<i32>().unwrap())
+}
+
+/// biblatex pubstate for years like "in press" or "forthcoming"
+fn parse_pubstate(input: &str) -> Option<&'static str> {
+    match input.trim().to_lowercase().replace(|x: char| !x.is_ascii_alphabetic(), "").as_str() {
+        "inpress" => Some("inpress"),
+        "forthcoming" | "toappear" | "accepted" => Some("forthcoming"),
+        "submitted" => Some("submitted"),
+        "prepublished" | "preprint" => Some("prepublished"),
+        _ => None,
+    }
+}
+
+/// Edition from 2, 2nd, 2nd ed., or second
+fn parse_edition(input: &str) -> Option<i32> {
+    lazy_static!{ static ref EDITION_RE: Regex = Regex::new(r#"^(?i)(\d+)(st|nd|rd|th)?(\s*ed(\.|ition)?)?$"#).unwrap(); }
+    const ORDINALS: &[&str] = &["first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+                                "tenth"];
+    let input = input.trim();
+    if let Some(caps) = EDITION_RE.captures(input) { return caps[1].parse::<i32>().ok() }
+    let word = input.split_whitespace().next()?.to_lowercase();
+    ORDINALS.iter().position(|x| *x == word).map(|x| x as i32 + 1)
+}
+
+/// Volumes and issues that are not a single number but still valid, like 3-4, 12A, Suppl 2 or
+/// 12 (Pt 1). They are kept as text.
+fn is_issue_text(input: &str) -> bool {
+    lazy_static!{
+        static ref ISSUE_RES: Vec<Regex> = vec![
+            Regex::new(r#"^\d+\s*(-{1,2}|–|/|&|,)\s*\d+$"#).unwrap(),
+            Regex::new(r#"^[A-Za-z]?\d+[A-Za-z]?$"#).unwrap(),
+            Regex::new(r#"^(?i)(\d+\s*)?(s|suppl?\.?|supplement|pt\.?|part|special issue)\s*\d*[a-z]?$"#).unwrap(),
+            Regex::new(r#"^\d+\s*\(.+\)$"#).unwrap(),
+        ];
+    }
+    let input = input.trim();
+    ISSUE_RES.iter().any(|x| x.is_match(input))
+}
+
 fn read_file(filename: &Path) -> String {
     let mut content = String::new();
     let mut file = File::open(filename).unwrap().read_to_string(&mut content).unwrap();
     content
 }
 
-/// Read one or more bibtex entries from a single .bib file
-pub fn read_entries(filename: &Path) -> Vec<Entry> {
+/// Read bibtex entries from a string
+/// Returns:
+///     (entries, warnings on fields that could not be read)
+pub fn parse_entries(content: &str) -> (Vec<Entry>, Vec<String>) {
     let mut results: Vec<Entry> = Vec::new();
-    let file_content = read_file(filename);
-    let bibtex = Bibtex::parse(&file_content).unwrap();
+    let mut warnings: Vec<String> = Vec::new();
+    let bibtex = Bibtex::parse(&format!("{}{}", MONTH_STRINGS, content)).unwrap();
     for bib_entry in bibtex.bibliographies().iter() {
-        results.push(Entry::from_bib(bib_entry))
+        let (entry, mut entry_warnings) = Entry::from_bib(bib_entry);
+        results.push(entry);
+        warnings.append(&mut entry_warnings);
     }
+    (results, warnings)
+}
+
+/// Read one or more bibtex entries from a single .bib file, warnings are printed to stderr
+pub fn read_entries(filename: &Path) -> Vec<Entry> {
+    let (results, warnings) = parse_entries(&read_file(filename));
+    for warning in warnings.iter() { eprintln!("Warning: {}", warning); }
     results
 }
 
 impl Entry {
-    pub fn from_bib(bib_entry: &Bibliography) -> Self {
+    fn keep_text(&mut self, field_name: &str, content: &str) {
+        self.extra_fields.insert(field_name.to_owned(), content.trim().to_owned());
+    }
+
+    /// Values of numeric fields that cannot be read are kept as text in extra_fields
+    /// Returns:
+    ///     (entry, warnings on fields that could not be read)
+    pub fn from_bib(bib_entry: &Bibliography) -> (Self, Vec<String>) {
         let citation = strip_accent(bib_entry.citation_key());
         let entry_type = EntryType::parse(bib_entry.entry_type());
         let mut entry = Entry{citation, entry_type, ..Default::default()};
+        let mut warnings: Vec<String> = Vec::new();
+        let mut unreadable = |entry: &mut Entry, field_name: &str, content: &str| {
+            warnings.push(format!("{}: cannot read {} = {{{}}}, kept as text", entry.citation, field_name, content));
+            entry.keep_text(field_name, content);
+        };
         for (field_name, content) in bib_entry.tags().iter() {
             match field_name.as_ref() {
-                "title" => entry.title = load_title(content),
-                "booktitle" => entry.booktitle = Some(load_title(content)),
+                "title" => entry.title = load_title(&to_unicode(content)),
+                "booktitle" => entry.booktitle = Some(load_title(&to_unicode(content))),
                 "pages" => entry.pages = Some(load_pages(content)),
                 "author" => entry.authors = load_people(content),
                 "editor" => entry.editors = load_people(content),
-                "keywords" => entry.keywords = load_keywords(content),
-                "year" => entry.year = content.parse::<i32>().unwrap(),
-                "chapter" => entry.chapter = Some(content.parse::<i32>().unwrap()),
-                "edition" => entry.edition = Some(content.parse::<i32>().unwrap()),
-                "month" => entry.month = Some(content.parse::<i32>().unwrap()),
-                "number" => entry.number = Some(content.parse::<i32>().unwrap()),
-                "volume" => entry.volume = Some(content.parse::<i32>().unwrap()),
-                "journal" => entry.journal = Some(content.to_owned()),
-                "id" | "publisher" | "school" | "insititution" | "note" | "url" | "series" | "address" | "howpublished" |
-                     "organization" => {entry.extra_fields.insert(field_name.to_owned(), content.to_owned());},
+                "keywords" | "keyword" => entry.keywords = load_keywords(content),
+                "year" => match (parse_year(content), parse_pubstate(content)) {
+                    (Some(year), _) => entry.year = year,
+                    (None, Some(pubstate)) => entry.keep_text("pubstate", pubstate),
+                    (None, None) => unreadable(&mut entry, field_name, content),
+                },
+                "chapter" => match content.trim().parse::<i32>() {
+                    Ok(chapter) => entry.chapter = Some(chapter),
+                    Err(_) => unreadable(&mut entry, field_name, content),
+                },
+                "edition" => match parse_edition(content) {
+                    Some(edition) => entry.edition = Some(edition),
+                    None => unreadable(&mut entry, field_name, content),
+                },
+                "month" => match parse_month(content) {
+                    Some(month) => entry.month = Some(month),
+                    None => unreadable(&mut entry, field_name, content),
+                },
+                "number" | "volume" => match (content.trim().parse::<i32>(), field_name.as_str()) {
+                    (Ok(value), "number") => entry.number = Some(value),
+                    (Ok(value), _) => entry.volume = Some(value),
+                    (Err(_), _) if is_issue_text(content) => entry.keep_text(field_name, content),
+                    (Err(_), _) => unreadable(&mut entry, field_name, content),
+                },
+                "journal" => entry.journal = Some(to_unicode(content)),
+                "id" | "publisher" | "school" | "institution" | "note" | "url" | "series" | "address" | "howpublished" |
+                     "organization" | "doi" | "isbn" | "issn" | "eprint" | "pmid" | "archiveprefix" | "eprinttype" => {
+                    let value = if VERBATIM_FIELDS.contains(&field_name.as_str()) { content.to_owned() }
+                                else { to_unicode(content) };
+                    entry.extra_fields.insert(field_name.to_owned(), value);
+                },
                 _ => continue,
             }
         }
-        entry
+        (entry, warnings)
     }
 }
 
@@ -154,6 +358,7 @@ mod tests {
         assert_eq!(strip_accent(a), "aeabcd");
         let b = "bcdefg";
         assert_eq!(strip_accent(b), "bcdefg");
+        assert_eq!(strip_accent("strauß"), "strauss");
     }
     #[test]
     fn test_from_parser() {
@@ -163,6 +368,62 @@ mod tests {
         assert_eq!(entries[0].authors[0].first_name, "albert");
         assert_eq!(entries[0].journal, Some("Annalen der Physik".to_owned()));
         assert_eq!(entries[0].year, 1905);
+        assert!(entries[0].title.contains("Körper"));
         assert_eq!(entries[1].extra_fields.get("address").unwrap(), "Reading, Massachusetts");
     }
+    #[test]
+    fn test_tolerant_fields() {
+        assert_eq!(parse_month("Jan."), Some(1));
+        assert_eq!(parse_month("sept"), Some(9));
+        assert_eq!(parse_month("13"), None);
+        assert_eq!(parse_year("c1999"), Some(1999));
+        assert_eq!(parse_year("2019/2020"), Some(2019));
+        assert_eq!(parse_year("in press"), None);
+        assert_eq!(parse_edition("2nd ed."), Some(2));
+        assert_eq!(parse_edition("Third"), Some(3));
+        assert!(is_issue_text("Suppl 2") && is_issue_text("12A") && is_issue_text("12 (Pt 1)"));
+        assert!(!is_issue_text("unknown"));
+        let (entries, warnings) = parse_entries("@article{doe2020,
+            author = \"Jane Doe\",
+            title = \"Testing\",
+            year = \"in press\",
+            month = jan,
+            volume = \"12A\",
+            number = \"3-4\",
+            edition = \"2nd\",
+            chapter = \"three\"
+        }");
+        let entry = &entries[0];
+        assert_eq!((entry.year, entry.month, entry.edition), (0, Some(1), Some(2)));
+        assert_eq!((entry.volume, entry.number, entry.chapter), (None, None, None));
+        assert_eq!(entry.extra_fields.get("pubstate").unwrap(), "inpress");
+        assert_eq!(entry.extra_fields.get("volume").unwrap(), "12A");
+        assert_eq!(entry.extra_fields.get("number").unwrap(), "3-4");
+        assert_eq!(entry.extra_fields.get("chapter").unwrap(), "three");
+        assert_eq!(warnings, vec!["doe2020: cannot read chapter = {three}, kept as text"]);
+    }
+
+    #[test]
+    fn test_person() {
+        let names = |x: &Person| (x.first_name.clone(), x.prefix.clone(), x.last_name.clone(), x.suffix.clone());
+        let person = |first: &str, prefix: &str, last: &str, suffix: &str|
+            (first.to_owned(), prefix.to_owned(), last.to_owned(), suffix.to_owned());
+        assert_eq!(names(&Person::load("Ludwig van Beethoven")), person("ludwig", "van", "beethoven", ""));
+        assert_eq!(names(&Person::load("Martin Luther King, Jr.")), person("martin luther", "", "king", "Jr."));
+        assert_eq!(names(&Person::load("King, Jr., Martin Luther")), person("martin luther", "", "king", "Jr."));
+        assert_eq!(names(&Person::load("{World Health Organization}")),
+                   person("", "", "{World Health Organization}", ""));
+        assert_eq!(names(&Person::load("de la Fontaine, Jean")), person("jean", "de la", "fontaine", ""));
+        assert_eq!(names(&Person::load("Jean de la Fontaine")), person("jean", "de la", "fontaine", ""));
+        assert_eq!(names(&Person::load("Aristotle")), person("", "", "aristotle", ""));
+        assert_eq!(names(&Person::load("{\\'E}mile Zola")), person("émile", "", "zola", ""));
+        assert_eq!(names(&Person::load("Teresa de Ávila")), person("teresa", "de", "ávila", ""));
+        assert_eq!(Person::load("Ávila, Teresa").search_term, "avila");
+        assert_eq!(Person::load("G{\\\"o}del, Kurt").search_term, "godel");
+        assert_eq!(Person::load("Ludwig van Beethoven").search_term, "beethoven");
+        assert_eq!(Person::load("{World Health Organization}").search_term, "worldhealthorganization");
+        let people = load_people("Barnes and {Noble and Sons} and Knuth, Donald");
+        assert_eq!(people.iter().map(|x| x.last_name.as_str()).collect::<Vec<&str>>(),
+                   vec!["barnes", "{Noble and Sons}", "knuth"]);
+    }
 }
@@ -0,0 +1,181 @@
+//! Works in CrossRef and OpenAlex metadata dumps, one JSON object per line, and CSL-JSON items
+use serde_json::Value;
+
+use crate::model::{Entry, Person};
+use crate::entry_type::EntryType;
+use crate::validate::clean_doi;
+use super::bibtex::{load_pages, load_title};
+
+/// the value at a path of keys, like ["biblio", "volume"]
+fn field<'a>(record: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(record, |value, key| value.get(*key))
+}
+
+/// a string or number, or the first string of an array, as trimmed text
+fn text(value: &Value) -> Option<String> {
+    let output = match value {
+        Value::String(x) => x.clone(),
+        Value::Number(x) => x.to_string(),
+        Value::Array(x) => x.iter().find_map(|x| x.as_str())?.to_owned(),
+        _ => return None,
+    };
+    Some(output.trim().to_owned()).filter(|x| !x.is_empty())
+}
+
+/// text of the first path found in the record
+fn first_text(record: &Value, paths: &[&[&str]]) -> Option<String> {
+    paths.iter().find_map(|path| field(record, path).and_then(text))
+}
+
+fn entry_type(work_type: &str) -> EntryType {
+    match work_type {
+        "journal-article" | "article" | "article-journal" | "article-magazine" => EntryType::Article,
+        "book" | "monograph" | "edited-book" | "reference-book" => EntryType::Book,
+        "book-chapter" | "book-section" | "book-part" | "chapter" => EntryType::Incollection,
+        "proceedings-article" | "paper-conference" => EntryType::Inproceedings,
+        "proceedings" => EntryType::Proceedings,
+        "dissertation" | "thesis" => EntryType::Phdthesis,
+        "report" => EntryType::Techreport,
+        "manuscript" => EntryType::Unpublished,
+        _ => EntryType::Misc,
+    }
+}
+
+/// CrossRef people have given and family names, OpenAlex authorships a display name
+fn people(record: &Value, key: &str) -> Vec<Person> {
+    let list = match record.get(key).and_then(|x| x.as_array()) {
+        Some(x) => x,
+        None => return Vec::new(),
+    };
+    list.iter().filter_map(|person| {
+        let given = person.get("given").and_then(text);
+        // CSL keeps von and Jr apart
+        let family = person.get("family").and_then(text).map(|family|
+            match person.get("non-dropping-particle").and_then(text) {
+                Some(particle) => format!("{} {}", particle, family),
+                None => family,
+            });
+        let family = match (family, person.get("suffix").and_then(text)) {
+            (Some(family), Some(suffix)) => Some(format!("{}, {}", family, suffix)),
+            (family, _) => family,
+        };
+        match (family, given) {
+            (Some(family), Some(given)) => Some(Person::load(&format!("{}, {}", family, given))),
+            (Some(family), None) => Some(Person::load(&family)),
+            _ => first_text(person, &[&["name"], &["author", "display_name"]]).map(|x| Person::load(&x)),
+        }
+    }).collect()
+}
+
+/// (year, month) from CrossRef date-parts or the OpenAlex publication date
+fn date(record: &Value) -> (Option<i32>, Option<i32>) {
+    for key in ["issued", "published-print", "published-online", "published"].iter() {
+        if let Some(parts) = field(record, &[*key, "date-parts"]).and_then(|x| x.get(0)).and_then(|x| x.as_array()) {
+            let part = |idx: usize| parts.get(idx).and_then(|x| x.as_i64()).map(|x| x as i32);
+            if part(0).is_some() { return (part(0), part(1)) }
+        }
+    }
+    let month = record.get("publication_date").and_then(|x| x.as_str())
+        .and_then(|x| x.split('-').nth(1)?.parse::<i32>().ok());
+    (record.get("publication_year").and_then(|x| x.as_i64()).map(|x| x as i32), month)
+}
+
+/// Read one work of a CrossRef or OpenAlex dump, None if it has no title
+pub fn read_record(record: &Value) -> Option<Entry> {
+    let title = first_text(record, &[&["title"], &["display_name"]])?;
+    let work_type = first_text(record, &[&["type"]]).unwrap_or_default();
+    let (year, month) = date(record);
+    let mut entry = Entry{entry_type: entry_type(&work_type), title: load_title(&title), year: year.unwrap_or(0),
+                          month, authors: people(record, "author"), editors: people(record, "editor"),
+                          ..Default::default()};
+    if entry.authors.is_empty() { entry.authors = people(record, "authorships"); }
+    let container = first_text(record, &[&["container-title"], &["primary_location", "source", "display_name"],
+                                          &["host_venue", "display_name"]]);
+    match entry.entry_type {
+        EntryType::Incollection | EntryType::Inproceedings => entry.booktitle = container.map(|x| load_title(&x)),
+        _ => entry.journal = container,
+    }
+    entry.volume = first_text(record, &[&["volume"], &["biblio", "volume"]]).and_then(|x| x.parse().ok());
+    entry.number = first_text(record, &[&["issue"], &["biblio", "issue"]]).and_then(|x| x.parse().ok());
+    entry.pages = match (first_text(record, &[&["page"]]), first_text(record, &[&["biblio", "first_page"]]),
+                         first_text(record, &[&["biblio", "last_page"]])) {
+        (Some(pages), _, _) => Some(load_pages(&pages)),
+        (None, Some(first), Some(last)) => Some(format!("{}-{}", first, last)),
+        (None, first, None) => first,
+        (None, None, Some(_)) => None,
+    };
+    if let Some(doi) = first_text(record, &[&["DOI"], &["doi"]]) {
+        entry.extra_fields.insert("doi".to_owned(), clean_doi(&doi));
+    }
+    if let Some(pmid) = first_text(record, &[&["ids", "pmid"], &["PMID"]]) {
+        let digits = pmid.rsplit('/').next().unwrap_or_default().to_owned();
+        if !digits.is_empty() { entry.extra_fields.insert("pmid".to_owned(), digits); }
+    }
+    for (key, name) in [("publisher", "publisher"), ("ISSN", "issn"), ("ISBN", "isbn")].iter() {
+        if let Some(value) = first_text(record, &[&[*key]]) { entry.extra_fields.insert((*name).to_owned(), value); }
+    }
+    Some(entry)
+}
+
+/// Read a CSL-JSON item, its id is the citation
+pub fn read_csl(record: &Value) -> Option<Entry> {
+    let mut entry = read_record(record)?;
+    entry.citation = first_text(record, &[&["id"]])?;
+    if let Some(keywords) = first_text(record, &[&["keyword"]]) {
+        entry.keywords = keywords.split(',').map(|x| x.trim().to_lowercase()).filter(|x| !x.is_empty()).collect();
+    }
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossref() {
+        let record: Value = serde_json::from_str(r#"{"DOI": "10.1126/science.1112070", "type": "journal-article",
+            "title": ["Patterning and Plasticity of the Cerebral Cortex"], "container-title": ["Science"],
+            "author": [{"given": "Mriganka", "family": "Sur"}, {"given": "John L. R.", "family": "Rubenstein"}],
+            "issued": {"date-parts": [[2005, 11, 4]]}, "volume": "310", "issue": "5749", "page": "805-810",
+            "ISSN": ["0036-8075", "1095-9203"], "publisher": "AAAS"}"#).unwrap();
+        let entry = read_record(&record).unwrap();
+        assert_eq!(entry.entry_type, EntryType::Article);
+        assert_eq!((entry.year, entry.month, entry.volume, entry.number), (2005, Some(11), Some(310), Some(5749)));
+        assert_eq!(entry.authors[1].last_name, "rubenstein");
+        assert_eq!(entry.journal, Some("Science".to_owned()));
+        assert_eq!(entry.pages, Some("805-810".to_owned()));
+        assert_eq!(entry.extra_fields.get("issn").unwrap(), "0036-8075");
+    }
+
+    #[test]
+    fn test_openalex() {
+        let record: Value = serde_json::from_str(r#"{"doi": "https://doi.org/10.1126/science.1112070",
+            "display_name": "Patterning and Plasticity of the Cerebral Cortex", "type": "article",
+            "publication_year": 2005, "publication_date": "2005-11-04",
+            "ids": {"pmid": "https://pubmed.ncbi.nlm.nih.gov/16272113"},
+            "authorships": [{"author": {"display_name": "Mriganka Sur"}}],
+            "primary_location": {"source": {"display_name": "Science"}},
+            "biblio": {"volume": "310", "issue": "5749", "first_page": "805", "last_page": "810"}}"#).unwrap();
+        let entry = read_record(&record).unwrap();
+        assert_eq!((entry.year, entry.month), (2005, Some(11)));
+        assert_eq!(entry.authors[0].search_term, "sur");
+        assert_eq!(entry.pages, Some("805-810".to_owned()));
+        assert_eq!(entry.extra_fields.get("doi").unwrap(), "10.1126/science.1112070");
+        assert_eq!(entry.extra_fields.get("pmid").unwrap(), "16272113");
+        assert!(read_record(&serde_json::from_str(r#"{"doi": "10.1/x"}"#).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_csl() {
+        let record: Value = serde_json::from_str(r#"{"id": "beethoven1801", "type": "chapter",
+            "title": "Sonata quasi una fantasia", "container-title": "Opus 27",
+            "author": [{"family": "Beethoven", "non-dropping-particle": "van", "given": "Ludwig"}],
+            "issued": {"date-parts": [[1801]]}, "keyword": "Piano, sonata"}"#).unwrap();
+        let entry = read_csl(&record).unwrap();
+        assert_eq!(entry.citation, "beethoven1801");
+        assert_eq!(entry.entry_type, EntryType::Incollection);
+        assert_eq!(entry.booktitle, Some("Opus 27".to_owned()));
+        assert_eq!(entry.authors[0].prefix, "van");
+        assert!(entry.keywords.contains("sonata") && entry.keywords.contains("piano"));
+    }
+}
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub trait ToTitleCase {
     fn to_title(&self) -> Cow<str>;
@@ -14,6 +15,14 @@ macro_rules! str_hashset {
     }};
 }
 
+/// wrap the content in a terminal foreground color
+#[macro_export]
+macro_rules! fg {
+    ($col:ident, $content:expr) => {
+        format!("{}{}{}", termion::color::Fg(termion::color::$col), $content,
+                termion::color::Fg(termion::color::Reset))
+    }
+}
 
 /// Allow raw string to be converted to title case if it's the first letter after braces, spaces
 /// and periods.
@@ -21,27 +30,53 @@ impl ToTitleCase for String {
     fn to_title<'a>(&'a self) -> Cow<'a, str> {
         let mut in_curly = 0;
         let mut space = true;
-        self.chars().map(|cur|  // depends on state, cannot convert to parallel
+        let mut output = String::with_capacity(self.len());
+        for cur in self.chars() {  // depends on state, cannot convert to parallel
             match cur {
-                ' ' | '.' | ',' | '?' => { space = true; cur },
-                '{' => { space = false; in_curly += 1; cur },
-                '}' => { space = false; in_curly -= 1; cur },
-                _ if in_curly > 0 => { space = false; cur },
-                _ => {
-                    if space {space = false; cur.to_ascii_uppercase()}
-                    else {cur.to_ascii_lowercase()}
-                }
+                ' ' | '.' | ',' | '?' => { space = true; output.push(cur) },
+                '{' => { space = false; in_curly += 1; output.push(cur) },
+                '}' => { space = false; in_curly -= 1; output.push(cur) },
+                _ if in_curly > 0 => { space = false; output.push(cur) },
+                _ if space => { space = false; output.extend(cur.to_uppercase()) },
+                _ => output.extend(cur.to_lowercase()),
             }
-        ).collect::<Cow<'a, str>>()
+        }
+        Cow::Owned(output)
     }
 }
 
+/// unix timestamp of the current time
+pub fn now() -> i64 { SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64 }
+
+/// parse an age like 30d, 2w or 12h into seconds, plain numbers are days
+pub fn parse_age(input: &str) -> Option<i64> {
+    let input = input.trim();
+    let (number, unit) = match input.chars().last()? {
+        'h' => (&input[..input.len() - 1], 3600),
+        'd' => (&input[..input.len() - 1], 86400),
+        'w' => (&input[..input.len() - 1], 604800),
+        _ => (input, 86400),
+    };
+    number.parse::<i64>().ok().map(|x| x * unit)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     #[test]
     fn test_titlecase() {
         let temp_str = "this iS crazy YEAH. {NoT}, {yeAs}".to_owned();
-        assert_eq!(temp_str.to_title(), "This Is Crazy Yeah. {NoT}, {yeAs}")
+        assert_eq!(temp_str.to_title(), "This Is Crazy Yeah. {NoT}, {yeAs}");
+        assert_eq!("émile ávila".to_owned().to_title(), "Émile Ávila");
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d"), Some(30 * 86400));
+        assert_eq!(parse_age("2w"), Some(2 * 604800));
+        assert_eq!(parse_age("12h"), Some(12 * 3600));
+        assert_eq!(parse_age("7"), Some(7 * 86400));
+        assert_eq!(parse_age("a month"), None);
+        assert_eq!(parse_age(""), None);
     }
 }
@@ -0,0 +1,374 @@
+//! Checks of entries against the fields of their entry types, identifiers and formatting
+use std::fmt;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::entry_type::EntryType;
+use crate::model::Entry;
+use crate::reader::bibtex::load_pages;
+use crate::util::now;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printable = match *self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", printable)
+    }
+}
+
+/// One problem found in an entry
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    pub severity: Severity,
+    pub field: String,
+    pub message: String,
+    /// corrected by fix
+    pub fixable: bool,
+}
+
+impl Problem {
+    fn new(severity: Severity, field: &str, message: String, fixable: bool) -> Self {
+        Problem{severity, field: field.to_owned(), message, fixable}
+    }
+}
+
+/// Fields an entry type must have, alternatives are separated by |
+pub fn required_fields(entry_type: &EntryType) -> &'static [&'static str] {
+    match entry_type {
+        EntryType::Article => &["author", "title", "journal", "year"],
+        EntryType::Book => &["author|editor", "title", "publisher", "year"],
+        EntryType::Booklet => &["title"],
+        EntryType::Inbook => &["author|editor", "title", "chapter|pages", "publisher", "year"],
+        EntryType::Incollection => &["author", "title", "booktitle", "publisher", "year"],
+        EntryType::Inproceedings => &["author", "title", "booktitle", "year"],
+        EntryType::Manual => &["title"],
+        EntryType::Masterthesis | EntryType::Phdthesis => &["author", "title", "school", "year"],
+        EntryType::Misc => &[],
+        EntryType::Proceedings => &["title", "year"],
+        EntryType::Techreport => &["author", "title", "institution", "year"],
+        EntryType::Unpublished => &["author", "title", "note"],
+    }
+}
+
+/// Fields an entry type may have besides the required ones and the identifiers
+pub fn optional_fields(entry_type: &EntryType) -> &'static [&'static str] {
+    match entry_type {
+        EntryType::Article => &["volume", "number", "pages", "month"],
+        EntryType::Book => &["volume", "number", "series", "address", "edition", "month"],
+        EntryType::Booklet => &["author", "howpublished", "address", "month", "year"],
+        EntryType::Inbook => &["volume", "number", "series", "address", "edition", "month"],
+        EntryType::Incollection => &["editor", "volume", "number", "series", "chapter", "pages", "address", "edition",
+                                     "month"],
+        EntryType::Inproceedings => &["editor", "volume", "number", "series", "pages", "address", "month",
+                                      "organization", "publisher"],
+        EntryType::Manual => &["author", "organization", "address", "edition", "month", "year"],
+        EntryType::Masterthesis | EntryType::Phdthesis => &["address", "month"],
+        EntryType::Misc => &["author", "title", "howpublished", "month", "year"],
+        EntryType::Proceedings => &["editor", "volume", "number", "series", "address", "month", "organization",
+                                    "publisher"],
+        EntryType::Techreport => &["number", "address", "month"],
+        EntryType::Unpublished => &["month", "year"],
+    }
+}
+
+/// fields that fit every entry type
+const COMMON_FIELDS: &[&str] = &["note", "doi", "url", "eprint", "isbn", "issn", "pubstate", "id"];
+
+/// whether the entry has a value for the field
+fn has_field(entry: &Entry, field: &str) -> bool {
+    let in_struct = match field {
+        "author" => !entry.authors.is_empty(),
+        "editor" => !entry.editors.is_empty(),
+        "title" => !entry.title.trim().is_empty(),
+        "booktitle" => entry.booktitle.is_some(),
+        "journal" => entry.journal.is_some(),
+        "year" => entry.year != 0 || entry.extra_fields.contains_key("pubstate"),
+        "month" => entry.month.is_some(),
+        "chapter" => entry.chapter.is_some(),
+        "edition" => entry.edition.is_some(),
+        "volume" => entry.volume.is_some(),
+        "number" => entry.number.is_some(),
+        "pages" => entry.pages.is_some(),
+        _ => false,
+    };
+    in_struct || entry.extra_fields.contains_key(field)
+}
+
+fn check_fields(entry: &Entry, problems: &mut Vec<Problem>) {
+    let required = required_fields(&entry.entry_type);
+    for field in required.iter() {
+        if !field.split('|').any(|x| has_field(entry, x)) {
+            problems.push(Problem::new(Severity::Error, field, format!("required by @{}", entry.entry_type), false));
+        }
+    }
+    let known = required.iter().flat_map(|x| x.split('|')).chain(optional_fields(&entry.entry_type).iter().cloned())
+        .chain(COMMON_FIELDS.iter().cloned()).collect::<Vec<&str>>();
+    let struct_fields = ["editor", "booktitle", "journal", "month", "chapter", "edition", "volume", "number", "pages"];
+    for field in struct_fields.iter().cloned().chain(entry.extra_fields.keys().map(|x| x.as_str())).unique().sorted() {
+        if has_field(entry, field) && !known.contains(&field) {
+            problems.push(Problem::new(Severity::Info, field, format!("not used by @{}", entry.entry_type), false));
+        }
+    }
+}
+
+lazy_static!{
+    static ref DOI_PREFIX_RE: Regex = Regex::new(r#"^(?i)(https?://(dx\.)?doi\.org/|doi:\s*)"#).unwrap();
+    pub static ref DOI_RE: Regex = Regex::new(r#"^10\.\d{4,9}/\S+$"#).unwrap();
+    static ref PAGE_RANGE_RE: Regex = Regex::new(r#"^[A-Za-z]*(\d+)-[A-Za-z]*(\d+)$"#).unwrap();
+}
+
+/// DOI without the resolver or doi: prefix
+pub fn clean_doi(input: &str) -> String { DOI_PREFIX_RE.replace(input.trim(), "").to_string() }
+
+/// ISBN-10 or ISBN-13 with a correct check digit, hyphens and spaces are ignored
+pub fn isbn_valid(input: &str) -> bool {
+    let chars: Vec<char> = input.chars().filter(|x| *x != '-' && *x != ' ').collect();
+    let digit = |x: &char| x.to_digit(10);
+    match chars.len() {
+        10 => {
+            let check = match chars[9] { 'X' | 'x' => Some(10), ref x => digit(x) };
+            match (chars[..9].iter().map(digit).collect::<Option<Vec<u32>>>(), check) {
+                (Some(digits), Some(check)) =>
+                    (digits.iter().enumerate().map(|(idx, x)| (10 - idx as u32) * x).sum::<u32>() + check) % 11 == 0,
+                _ => false,
+            }
+        },
+        13 => match chars.iter().map(digit).collect::<Option<Vec<u32>>>() {
+            Some(digits) => digits.iter().enumerate().map(|(idx, x)| if idx % 2 == 0 { *x } else { x * 3 })
+                .sum::<u32>() % 10 == 0,
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// ISSN as 1234-567X with a correct check digit, the hyphen is optional
+pub fn issn_valid(input: &str) -> bool {
+    let chars: Vec<char> = input.trim().chars().filter(|x| *x != '-').collect();
+    if chars.len() != 8 { return false }
+    let digits = match chars[..7].iter().map(|x| x.to_digit(10)).collect::<Option<Vec<u32>>>() {
+        Some(x) => x,
+        None => return false,
+    };
+    let check = (11 - digits.iter().enumerate().map(|(idx, x)| (8 - idx as u32) * x).sum::<u32>() % 11) % 11;
+    match chars[7] {
+        'X' | 'x' => check == 10,
+        x => x.to_digit(10) == Some(check),
+    }
+}
+
+fn hyphenate_issn(input: &str) -> String {
+    let chars: String = input.trim().chars().filter(|x| *x != '-').collect();
+    format!("{}-{}", &chars[..4], chars[4..].to_uppercase())
+}
+
+fn check_identifiers(entry: &Entry, problems: &mut Vec<Problem>) {
+    if let Some(doi) = entry.extra_fields.get("doi") {
+        let cleaned = clean_doi(doi);
+        if !DOI_RE.is_match(&cleaned) {
+            problems.push(Problem::new(Severity::Error, "doi", format!("{} is not a DOI", doi), false));
+        } else if cleaned != *doi {
+            problems.push(Problem::new(Severity::Warning, "doi", format!("should be written as {}", cleaned), true));
+        }
+    }
+    if let Some(isbn) = entry.extra_fields.get("isbn") {
+        if !isbn_valid(isbn) {
+            problems.push(Problem::new(Severity::Error, "isbn", format!("{} is not a valid ISBN", isbn), false));
+        }
+    }
+    if let Some(issn) = entry.extra_fields.get("issn") {
+        if !issn_valid(issn) {
+            problems.push(Problem::new(Severity::Error, "issn", format!("{} is not a valid ISSN", issn), false));
+        } else if hyphenate_issn(issn) != *issn {
+            problems.push(Problem::new(Severity::Info, "issn", format!("should be written as {}", hyphenate_issn(issn)),
+                                       true));
+        }
+    }
+}
+
+fn check_pages(pages: &str, problems: &mut Vec<Problem>) {
+    let normalized = load_pages(pages);
+    if normalized != pages {
+        problems.push(Problem::new(Severity::Warning, "pages", format!("should be written as {}", normalized), true));
+    }
+    if let Some(caps) = PAGE_RANGE_RE.captures(&normalized) {
+        if caps[1].parse::<u64>().unwrap_or(0) > caps[2].parse::<u64>().unwrap_or(u64::MAX) {
+            problems.push(Problem::new(Severity::Error, "pages", format!("{} ends before it starts", pages), false));
+        }
+    }
+}
+
+/// approximate current year, good enough to catch typos
+fn current_year() -> i32 {
+    1970 + (now() / 31_556_952) as i32
+}
+
+fn check_year(year: i32, problems: &mut Vec<Problem>) {
+    if year != 0 && (year < 1450 || year > current_year() + 1) {
+        problems.push(Problem::new(Severity::Warning, "year", format!("{} is not a plausible year", year), false));
+    }
+}
+
+fn is_all_caps(text: &str) -> bool {
+    let letters: Vec<char> = text.chars().filter(|x| x.is_alphabetic()).collect();
+    letters.len() > 3 && letters.iter().all(|x| x.is_uppercase())
+}
+
+/// words with capitals after their first letter, like DNA or fMRI, outside of braces
+fn unprotected_words(title: &str) -> Vec<String> {
+    let mut depth = 0;
+    let mut output = Vec::new();
+    for word in title.split_whitespace() {
+        if depth == 0 && !word.contains(|x: char| x == '{' || x == '}' || x == '\\') {
+            let core = word.trim_matches(|x: char| !x.is_alphanumeric());
+            if core.chars().skip(1).any(|x| x.is_uppercase()) { output.push(core.to_owned()); }
+        }
+        for c in word.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => (),
+            }
+        }
+    }
+    output
+}
+
+/// Brace the unprotected words, so that BibTeX styles don't lowercase them
+fn protect_words(title: &str) -> String {
+    let words = unprotected_words(title);
+    let mut depth = 0;
+    title.split(' ').map(|word| {
+        let core = word.trim_matches(|x: char| !x.is_alphanumeric());
+        let output = if depth == 0 && !core.is_empty() && words.iter().any(|x| x == core) && !word.contains('{') {
+            word.replacen(core, &format!("{{{}}}", core), 1)
+        } else { word.to_owned() };
+        for c in word.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => (),
+            }
+        }
+        output
+    }).join(" ")
+}
+
+fn check_title(title: &str, problems: &mut Vec<Problem>) {
+    if title.split_whitespace().join(" ") != title {
+        problems.push(Problem::new(Severity::Info, "title", "has extra whitespace".to_owned(), true));
+    }
+    if title.ends_with('.') && !title.ends_with("..") {
+        problems.push(Problem::new(Severity::Info, "title", "ends with a period".to_owned(), true));
+    }
+    if is_all_caps(title) {
+        problems.push(Problem::new(Severity::Warning, "title", "is in all caps".to_owned(), false));
+    } else {
+        let words = unprotected_words(title);
+        if !words.is_empty() {
+            problems.push(Problem::new(Severity::Info, "title",
+                format!("{} will be lowercased by BibTeX styles unless braced", words.join(", ")), true));
+        }
+    }
+}
+
+/// All problems of an entry, most severe first
+pub fn validate(entry: &Entry) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    check_fields(entry, &mut problems);
+    check_identifiers(entry, &mut problems);
+    if let Some(ref pages) = entry.pages { check_pages(pages, &mut problems); }
+    check_year(entry.year, &mut problems);
+    if !entry.title.is_empty() { check_title(&entry.title, &mut problems); }
+    problems.sort_by(|a, b| b.severity.cmp(&a.severity));
+    problems
+}
+
+/// Apply the safe corrections: page ranges, DOI prefixes, ISSN hyphens, whitespace and trailing
+/// periods in titles, and braces around acronyms
+pub fn fix(entry: &Entry) -> Entry {
+    let mut fixed = entry.clone();
+    let mut title = entry.title.split_whitespace().join(" ");
+    if title.ends_with('.') && !title.ends_with("..") { title.pop(); }
+    fixed.title = if is_all_caps(&title) { title } else { protect_words(&title) };
+    if let Some(ref pages) = entry.pages { fixed.pages = Some(load_pages(pages)); }
+    if let Some(doi) = entry.extra_fields.get("doi") {
+        let cleaned = clean_doi(doi);
+        if DOI_RE.is_match(&cleaned) { fixed.extra_fields.insert("doi".to_owned(), cleaned); }
+    }
+    if let Some(issn) = entry.extra_fields.get("issn") {
+        if issn_valid(issn) { fixed.extra_fields.insert("issn".to_owned(), hyphenate_issn(issn)); }
+    }
+    fixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Person;
+
+    fn article() -> Entry {
+        let mut entry = Entry{citation: "watson1953".to_owned(), entry_type: EntryType::Article, year: 1953,
+            title: "Molecular structure of  nucleic acids: a structure for DNA.".to_owned(),
+            pages: Some("737--738".to_owned()), authors: vec![Person::load("Watson, James")], ..Default::default()};
+        entry.extra_fields.insert("doi".to_owned(), "https://doi.org/10.1038/171737a0".to_owned());
+        entry.extra_fields.insert("issn".to_owned(), "00280836".to_owned());
+        entry
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert!(isbn_valid("978-0-306-40615-7"));
+        assert!(isbn_valid("0-306-40615-2"));
+        assert!(!isbn_valid("0-306-40615-3"));
+        assert!(isbn_valid("0-8044-2957-X"));
+        assert!(issn_valid("0028-0836"));
+        assert!(issn_valid("2434-561X"));
+        assert!(!issn_valid("0028-0837"));
+        assert_eq!(clean_doi("doi: 10.1038/171737a0"), "10.1038/171737a0");
+    }
+
+    #[test]
+    fn test_validate() {
+        let entry = article();
+        let problems = validate(&entry);
+        assert_eq!(problems[0], Problem::new(Severity::Error, "journal", "required by @article".to_owned(), false));
+        let fields = problems.iter().map(|x| x.field.as_str()).collect::<Vec<&str>>();
+        assert!(fields.contains(&"pages") && fields.contains(&"doi") && fields.contains(&"issn"));
+        assert!(problems.iter().any(|x| x.message.starts_with("DNA will be lowercased")));
+        let mut book = entry.clone();
+        book.entry_type = EntryType::Book;
+        book.year = 953;
+        let problems = validate(&book);
+        assert!(!problems.iter().any(|x| x.field == "author|editor"));
+        assert!(problems.iter().any(|x| x.field == "publisher" && x.severity == Severity::Error));
+        assert!(problems.iter().any(|x| x.field == "year" && x.severity == Severity::Warning));
+        assert!(problems.iter().any(|x| x.field == "pages" && x.severity == Severity::Info));
+    }
+
+    #[test]
+    fn test_fix() {
+        let fixed = fix(&article());
+        assert_eq!(fixed.title, "Molecular structure of nucleic acids: a structure for {DNA}");
+        assert_eq!(fixed.pages, Some("737-738".to_owned()));
+        assert_eq!(fixed.extra_fields.get("doi").unwrap(), "10.1038/171737a0");
+        assert_eq!(fixed.extra_fields.get("issn").unwrap(), "0028-0836");
+        assert!(validate(&fixed).iter().all(|x| !x.fixable));
+        let mut entry = article();
+        entry.pages = Some("738-737".to_owned());
+        assert!(validate(&entry).iter().any(|x| x.message == "738-737 ends before it starts"));
+        entry.pages = Some("1-12345678901".to_owned());
+        assert_eq!(fix(&entry).pages, Some("1-12345678901".to_owned()));
+    }
+}